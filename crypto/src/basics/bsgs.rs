@@ -0,0 +1,142 @@
+use algebra::groups::{Group, GroupArithmetic, Scalar};
+use ruc::*;
+use sha2::Digest;
+use std::collections::HashMap;
+use utils::errors::ZeiError;
+
+/// Precomputed table solving discrete logarithms `v` of `v * base` for `v` in [0, 2^bits) with
+/// the baby-step giant-step algorithm.
+/// The table stores the 2^table_bits baby steps j * base, and solving takes at most
+/// 2^(bits - table_bits) giant steps of 2^table_bits * base, so that a larger table trades
+/// memory and precomputation time for faster decryptions.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BsgsTable<G> {
+    base: G,
+    bits: usize,
+    table_bits: usize,
+    giant_step: G,
+    // truncated hash of j * base -> every j with that hash
+    baby_steps: HashMap<u64, Vec<u32>>,
+}
+
+impl<G: Group> BsgsTable<G> {
+    /// Precomputes the 2^table_bits baby steps for values in [0, 2^bits).
+    /// Returns ZeiError::ParameterError if bits > 64, table_bits > bits or table_bits > 32
+    pub fn new(base: &G, bits: usize, table_bits: usize) -> Result<Self> {
+        if bits > 64 || table_bits > bits || table_bits > 32 {
+            return Err(eg!(ZeiError::ParameterError));
+        }
+        let size = 1u64 << table_bits;
+        let mut baby_steps: HashMap<u64, Vec<u32>> =
+            HashMap::with_capacity(size as usize);
+        let mut point = G::get_identity();
+        for j in 0..size {
+            baby_steps
+                .entry(point_key(&point))
+                .or_default()
+                .push(j as u32);
+            point = point.add(base);
+        }
+        Ok(BsgsTable {
+            base: base.clone(),
+            bits,
+            table_bits,
+            giant_step: point,
+            baby_steps,
+        })
+    }
+
+    pub fn get_base(&self) -> &G {
+        &self.base
+    }
+
+    /// Values in [0, 2^bits()) can be solved
+    pub fn bits(&self) -> usize {
+        self.bits
+    }
+
+    /// Returns `v` such that `encoded` = v * base.
+    /// Returns ZeiError::ElGamalDecryptionError if `v` is not in [0, 2^bits)
+    pub fn solve(&self, encoded: &G) -> Result<u64> {
+        // deserialized tables are not checked by `new`
+        if self.bits > 64 || self.table_bits > self.bits {
+            return Err(eg!(ZeiError::ParameterError));
+        }
+        let steps = 1u128 << (self.bits - self.table_bits);
+        let mut gamma = encoded.clone();
+        for i in 0..steps {
+            if let Some(candidates) = self.baby_steps.get(&point_key(&gamma)) {
+                for j in candidates {
+                    let value = ((i as u64) << self.table_bits) + *j as u64;
+                    // truncated hashes can collide
+                    if self.base.mul(&G::S::from_u64(value)) == *encoded {
+                        return Ok(value);
+                    }
+                }
+            }
+            gamma = gamma.sub(&self.giant_step);
+        }
+        Err(eg!(ZeiError::ElGamalDecryptionError))
+    }
+}
+
+fn point_key<G: Group>(point: &G) -> u64 {
+    let digest = sha2::Sha512::digest(&point.to_compressed_bytes());
+    let mut bytes = [0u8; 8];
+    bytes.copy_from_slice(&digest[..8]);
+    u64::from_le_bytes(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::basics::bsgs::{point_key, BsgsTable};
+    use algebra::bls12_381::BLSG1;
+    use algebra::groups::{Group, GroupArithmetic, Scalar};
+    use algebra::ristretto::RistrettoPoint;
+    use utils::errors::ZeiError;
+
+    fn solve<G: Group>() {
+        let base = G::get_base();
+        let table = BsgsTable::new(&base, 20, 8).unwrap();
+        for value in [0u64, 1, 255, 256, 1000, 123_456, (1 << 20) - 1].iter() {
+            let encoded = base.mul(&G::S::from_u64(*value));
+            assert_eq!(table.solve(&encoded).unwrap(), *value);
+        }
+        let encoded = base.mul(&G::S::from_u64(1 << 20));
+        err_eq!(
+            ZeiError::ElGamalDecryptionError,
+            table.solve(&encoded).unwrap_err()
+        );
+
+        // the table survives serialization
+        let json = serde_json::to_string(&table).unwrap();
+        let table: BsgsTable<G> = serde_json::from_str(&json).unwrap();
+        let encoded = base.mul(&G::S::from_u64(777_777));
+        assert_eq!(table.solve(&encoded).unwrap(), 777_777);
+
+        // a false match with a colliding baby step does not hide the right one
+        let mut table = table;
+        let encoded = base.mul(&G::S::from_u64(5));
+        table
+            .baby_steps
+            .get_mut(&point_key(&encoded))
+            .unwrap()
+            .insert(0, 77);
+        assert_eq!(table.solve(&encoded).unwrap(), 5);
+
+        err_eq!(
+            ZeiError::ParameterError,
+            BsgsTable::new(&base, 8, 9).unwrap_err()
+        );
+        err_eq!(
+            ZeiError::ParameterError,
+            BsgsTable::new(&base, 65, 16).unwrap_err()
+        );
+    }
+
+    #[test]
+    fn test_bsgs() {
+        solve::<RistrettoPoint>();
+        solve::<BLSG1>();
+    }
+}
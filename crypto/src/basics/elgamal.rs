@@ -1,3 +1,4 @@
+use crate::basics::bsgs::BsgsTable;
 use crate::basics::hash::rescue::{RescueCtr, RescueInstance};
 use algebra::bls12_381::{BLSScalar, BLS_SCALAR_LEN};
 use algebra::groups::{Group, GroupArithmetic, Scalar};
@@ -28,9 +29,16 @@ pub fn elgamal_key_gen<R: CryptoRng + RngCore, G: Group>(
     prng: &mut R,
     base: &G,
 ) -> (ElGamalDecKey<G::S>, ElGamalEncKey<G>) {
-    let secret_key = ElGamalDecKey(G::S::random(prng));
-    let public_key = ElGamalEncKey(base.mul(&secret_key.0));
-    (secret_key, public_key)
+    elgamal_key_from_secret(G::S::random(prng), base)
+}
+
+/// Returns the key pair whose decryption key is `secret`
+pub fn elgamal_key_from_secret<G: Group>(
+    secret: G::S,
+    base: &G,
+) -> (ElGamalDecKey<G::S>, ElGamalEncKey<G>) {
+    let public_key = ElGamalEncKey(base.mul(&secret));
+    (ElGamalDecKey(secret), public_key)
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
@@ -217,6 +225,17 @@ pub fn elgamal_decrypt_hinted<G: Group>(
     brute_force::<G>(base, &encoded, lower_bound, upper_bound).c(d!())
 }
 
+/// I decrypt en ElGamal ciphertext on the exponent with a precomputed baby-step giant-step table
+/// Return ZeiError::ElGamalDecryptionError if value is not in the range [0..2^table.bits()-1]
+pub fn elgamal_decrypt_with_table<G: Group>(
+    ctext: &ElGamalCiphertext<G>,
+    sec_key: &ElGamalDecKey<G::S>,
+    table: &BsgsTable<G>,
+) -> Result<u64> {
+    let encoded = elgamal_decrypt_elem(ctext, sec_key);
+    table.solve(&encoded).c(d!())
+}
+
 fn brute_force<G: Group>(
     base: &G,
     encoded: &G,
@@ -235,6 +254,7 @@ fn brute_force<G: Group>(
 
 #[cfg(test)]
 mod elgamal_test {
+    use crate::basics::bsgs::BsgsTable;
     use crate::basics::elgamal::{
         ElGamalCiphertext, ElGamalDecKey, ElGamalEncKey, ElGamalHybridCiphertext,
     };
@@ -310,6 +330,28 @@ mod elgamal_test {
         err_eq!(ZeiError::ElGamalDecryptionError, err);
     }
 
+    fn decryption_with_table<G: Group>() {
+        let mut prng = ChaChaRng::from_seed([0u8; 32]);
+        let base = G::get_base();
+        let table = BsgsTable::new(&base, 16, 8).unwrap();
+
+        let (secret_key, public_key) = super::elgamal_key_gen::<_, G>(&mut prng, &base);
+        let r = G::S::random(&mut prng);
+        let ctext =
+            super::elgamal_encrypt(&base, &G::S::from_u32(40_000), &r, &public_key);
+        assert_eq!(
+            super::elgamal_decrypt_with_table(&ctext, &secret_key, &table).unwrap(),
+            40_000
+        );
+
+        let ctext =
+            super::elgamal_encrypt(&base, &G::S::from_u32(70_000), &r, &public_key);
+        let err = super::elgamal_decrypt_with_table(&ctext, &secret_key, &table)
+            .err()
+            .unwrap();
+        err_eq!(ZeiError::ElGamalDecryptionError, err);
+    }
+
     fn serialize_to_json<G: Group>() {
         let mut prng = ChaChaRng::from_seed([0u8; 32]);
         let base = G::get_base();
@@ -394,6 +436,12 @@ mod elgamal_test {
         decryption::<JubjubPoint>();
     }
 
+    #[test]
+    fn decrypt_with_table() {
+        decryption_with_table::<RistrettoPoint>();
+        decryption_with_table::<BLSG1>();
+    }
+
     #[test]
     fn to_json() {
         serialize_to_json::<RistrettoPoint>();
@@ -1,3 +1,4 @@
+pub mod bsgs;
 pub mod commitments;
 pub mod elgamal;
 pub mod hash;
@@ -95,6 +95,14 @@ impl<G: Group> KeyPair<G, G::S> {
     pub fn generate<R: CryptoRng + RngCore>(prng: &mut R) -> Self {
         gen_keys(prng)
     }
+    /// Build the keypair with secret key `scalar`
+    pub fn from_secret_scalar(scalar: G::S) -> Self {
+        KeyPair {
+            sec_key: SecretKey(scalar),
+            pub_key: PublicKey(G::get_base().mul(&scalar)),
+        }
+    }
+
     /// Return scalar representing secret key
     pub fn get_secret_scalar(&self) -> G::S {
         self.sec_key.scalar()
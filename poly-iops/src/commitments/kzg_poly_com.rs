@@ -3,7 +3,7 @@ use crate::commitments::pcs::{
 };
 use crate::polynomials::field_polynomial::FpPolynomial;
 use algebra::bls12_381::{BLSScalar, Bls12381, BLSG1};
-use algebra::groups::{Group, GroupArithmetic, One, Scalar, ScalarArithmetic};
+use algebra::groups::{Group, GroupArithmetic, One, Scalar, ScalarArithmetic, Zero};
 use algebra::multi_exp::MultiExp;
 use algebra::pairing::Pairing;
 use merlin::Transcript;
@@ -333,6 +333,60 @@ impl<'b> PolyComScheme for KZGCommitmentSchemeBLS {
             Err(eg!(PolyComSchemeError::PCSProveEvalError))
         }
     }
+
+    /// Each proof is valid iff e(C_i/g1^{y_i} * proof_i^{x_i}, g2) == e(proof_i, g2^s).
+    /// For random scalars r_i, all of them are checked with two pairings:
+    /// e(prod_i (C_i/g1^{y_i} * proof_i^{x_i})^{r_i}, g2) == e(prod_i proof_i^{r_i}, g2^s)
+    #[allow(non_snake_case)]
+    fn batch_verify_eval_proofs<R: CryptoRng + RngCore>(
+        &self,
+        prng: &mut R,
+        commitments: &[Self::Commitment],
+        _max_degree: usize,
+        points: &[Self::Field],
+        values: &[Self::Field],
+        proofs: &[&Self::EvalProof],
+    ) -> Result<()> {
+        let n = commitments.len();
+        if n != points.len() || n != values.len() || n != proofs.len() {
+            return Err(eg!(PolyComSchemeError::PCSProveEvalError));
+        }
+        let g1_0 = self.public_parameter_group_1[0].clone();
+        let g2_0 = self.public_parameter_group_2[0].clone();
+        let g2_1 = self.public_parameter_group_2[1].clone();
+
+        let mut left_scalars = Vec::with_capacity(2 * n + 1);
+        let mut left_elems = Vec::with_capacity(2 * n + 1);
+        let mut right_scalars = Vec::with_capacity(n);
+        let mut right_elems = Vec::with_capacity(n);
+        let mut values_lc = BLSScalar::zero();
+        for (((C, x), y), proof) in
+            commitments.iter().zip(points).zip(values).zip(proofs)
+        {
+            let r = BLSScalar::random(prng);
+            values_lc.add_assign(&r.mul(y));
+            left_scalars.push(r.mul(x));
+            left_elems.push(&proof.0);
+            left_scalars.push(r);
+            left_elems.push(&C.value);
+            right_scalars.push(r);
+            right_elems.push(&proof.0);
+        }
+        left_scalars.push(values_lc.neg());
+        left_elems.push(&g1_0);
+
+        let left_scalars_ref: Vec<&BLSScalar> = left_scalars.iter().collect();
+        let right_scalars_ref: Vec<&BLSScalar> = right_scalars.iter().collect();
+        let left = BLSG1::vartime_multi_exp(&left_scalars_ref[..], &left_elems[..]);
+        let right = BLSG1::vartime_multi_exp(&right_scalars_ref[..], &right_elems[..]);
+
+        // e(prod_i (C_i/g1^{y_i} * proof_i^{x_i})^{r_i}, g2) == e(prod_i proof_i^{r_i}, g2^s)
+        if Bls12381::pairing(&left, &g2_0) == Bls12381::pairing(&right, &g2_1) {
+            Ok(())
+        } else {
+            Err(eg!(PolyComSchemeError::PCSProveEvalError))
+        }
+    }
 }
 
 #[cfg(test)]
@@ -1,5 +1,6 @@
 use crate::commitments::transcript::PolyComTranscript;
 use merlin::Transcript;
+use rand_core::{CryptoRng, RngCore};
 use std::fmt::Debug;
 
 use crate::polynomials::field_polynomial::FpPolynomial;
@@ -46,7 +47,7 @@ pub trait HomomorphicPolyComElem: ToBytes {
 #[derive(Debug, Deserialize, Eq, PartialEq, Serialize, Clone)]
 pub struct BatchProofEval<C, E> {
     commitment: C,
-    eval_proof: E,
+    pub(crate) eval_proof: E,
 }
 
 pub struct BatchEvalParams<'a, C, F> {
@@ -251,6 +252,42 @@ pub trait PolyComScheme {
         proof: &BatchPfEval<Self>,
         params: OptionParams<Self>,
     ) -> Result<()> {
+        let (derived_commitment, rho) = self
+            .batch_verify_eval_derive(
+                transcript,
+                commitments,
+                max_degree,
+                points,
+                values,
+                proof,
+                params,
+            )
+            .c(d!())?;
+        self.verify_eval(
+            transcript,
+            &derived_commitment,
+            max_degree,
+            &rho,
+            &Self::Field::zero(),
+            &proof.eval_proof,
+        )
+        .c(d!())
+    }
+
+    /// Reduce a batch eval proof to a single evaluation check: returns the derived commitment F
+    /// and the point rho such that the batch proof is valid iff `proof.eval_proof` proves F(rho) = 0
+    #[allow(non_snake_case)]
+    #[allow(clippy::too_many_arguments)]
+    fn batch_verify_eval_derive(
+        &self,
+        transcript: &mut Transcript,
+        commitments: &[&Self::Commitment],
+        max_degree: usize,
+        points: &[Self::Field],
+        values: &[Self::Field],
+        proof: &BatchPfEval<Self>,
+        params: OptionParams<Self>,
+    ) -> Result<(Self::Commitment, Self::Field)> {
         Self::init_pcs_batch_eval_transcript(transcript, max_degree, points, params);
         let alpha = transcript.get_challenge_field_elem::<Self::Field>(b"alpha");
         transcript.append_commitment::<Self::Commitment>(&proof.commitment);
@@ -289,15 +326,42 @@ pub trait PolyComScheme {
         // - Com(q(X) * z(\rho))
         let com_z_q = proof.commitment.exp(&z_eval_rho);
         let derived_commitment = com_lc.op(&com_z_q.inv());
-        self.verify_eval(
-            transcript,
-            &derived_commitment,
-            max_degree,
-            &rho,
-            &Self::Field::zero(),
-            &proof.eval_proof,
-        )
-        .c(d!())
+        Ok((derived_commitment, rho))
+    }
+
+    /// Verify several independent evaluation proofs, the i-th one proving that the polynomial
+    /// inside `commitments[i]` evaluates to `values[i]` on input `points[i]`.
+    /// Schemes supporting it should override this method to check all the proofs at once.
+    fn batch_verify_eval_proofs<R: CryptoRng + RngCore>(
+        &self,
+        _prng: &mut R,
+        commitments: &[Self::Commitment],
+        max_degree: usize,
+        points: &[Self::Field],
+        values: &[Self::Field],
+        proofs: &[&Self::EvalProof],
+    ) -> Result<()> {
+        if commitments.len() != points.len()
+            || commitments.len() != values.len()
+            || commitments.len() != proofs.len()
+        {
+            return Err(eg!(PolyComSchemeError::PCSProveEvalError));
+        }
+        for (((commitment, point), value), proof) in
+            commitments.iter().zip(points).zip(values).zip(proofs)
+        {
+            let mut transcript = Transcript::new(b"PCS Batch Verify Eval Proofs");
+            self.verify_eval(
+                &mut transcript,
+                commitment,
+                max_degree,
+                point,
+                value,
+                proof,
+            )
+            .c(d!())?;
+        }
+        Ok(())
     }
 
     fn init_pcs_batch_eval_transcript(
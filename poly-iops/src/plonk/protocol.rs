@@ -90,7 +90,7 @@ pub mod prover {
         transcript_init_plonk,
     };
     use crate::polynomials::field_polynomial::FpPolynomial;
-    use algebra::groups::ScalarArithmetic;
+    use algebra::groups::{ScalarArithmetic, Zero};
     use merlin::Transcript;
     use rand_core::{CryptoRng, RngCore};
     use ruc::*;
@@ -340,6 +340,95 @@ pub mod prover {
         public_values: &[PCS::Field],
         proof: &PlonkPf<PCS>,
     ) -> Result<()> {
+        let (derived_commitment, rho) = verifier_derive_eval_check(
+            transcript,
+            pcs,
+            cs,
+            cs_params,
+            public_values,
+            proof,
+        )
+        .c(d!())?;
+        pcs.verify_eval(
+            transcript,
+            &derived_commitment,
+            cs_params.cs_size + 2,
+            &rho,
+            &PCS::Field::zero(),
+            &proof.batch_eval_proof.eval_proof,
+        )
+        .c(d!(PlonkError::VerificationError))
+    }
+
+    /// Batch verify proofs for a constraint system previously preprocessed into `cs_params`.
+    /// The i-th proof is verified against `transcripts[i]` and `public_values[i]`.
+    /// The final evaluation checks of all the proofs are combined into a single check by the
+    /// polynomial commitment scheme using randomness from `prng`.
+    /// # Example
+    /// ```
+    /// // See plonk::prover::prover
+    /// ```
+    pub fn batch_verifier<
+        R: CryptoRng + RngCore,
+        PCS: PolyComScheme,
+        CS: ConstraintSystem<Field = PCS::Field>,
+    >(
+        prng: &mut R,
+        transcripts: &mut [Transcript],
+        pcs: &PCS,
+        cs: &CS,
+        cs_params: &VerifierParams<PCS>,
+        public_values: &[&[PCS::Field]],
+        proofs: &[&PlonkPf<PCS>],
+    ) -> Result<()> {
+        if transcripts.len() != proofs.len() || public_values.len() != proofs.len() {
+            return Err(eg!(PlonkError::VerificationError));
+        }
+        let mut commitments = Vec::with_capacity(proofs.len());
+        let mut points = Vec::with_capacity(proofs.len());
+        let mut eval_proofs = Vec::with_capacity(proofs.len());
+        for ((transcript, public_values), proof) in
+            transcripts.iter_mut().zip(public_values).zip(proofs)
+        {
+            let (derived_commitment, rho) = verifier_derive_eval_check(
+                transcript,
+                pcs,
+                cs,
+                cs_params,
+                public_values,
+                proof,
+            )
+            .c(d!())?;
+            commitments.push(derived_commitment);
+            points.push(rho);
+            eval_proofs.push(&proof.batch_eval_proof.eval_proof);
+        }
+        let values = vec![PCS::Field::zero(); proofs.len()];
+        pcs.batch_verify_eval_proofs(
+            prng,
+            &commitments[..],
+            cs_params.cs_size + 2,
+            &points[..],
+            &values[..],
+            &eval_proofs[..],
+        )
+        .c(d!(PlonkError::VerificationError))
+    }
+
+    /// Run all the verifier steps but the final evaluation check of the batch eval proof.
+    /// Returns the commitment F and the point rho such that the proof is valid iff
+    /// `proof.batch_eval_proof.eval_proof` proves F(rho) = 0.
+    fn verifier_derive_eval_check<
+        PCS: PolyComScheme,
+        CS: ConstraintSystem<Field = PCS::Field>,
+    >(
+        transcript: &mut Transcript,
+        pcs: &PCS,
+        cs: &CS,
+        cs_params: &VerifierParams<PCS>,
+        public_values: &[PCS::Field],
+        proof: &PlonkPf<PCS>,
+    ) -> Result<(PCS::Commitment, PCS::Field)> {
         transcript_init_plonk(transcript, cs_params, public_values);
 
         let mut challenges = PlonkChallenges::new();
@@ -433,7 +522,7 @@ pub mod prover {
         values.push(derived_q_eval_beta);
         values.push(proof.L_eval_beta);
         values.push(proof.Sigma_eval_g_beta);
-        pcs.batch_verify_eval(
+        pcs.batch_verify_eval_derive(
             transcript,
             &commitments[..],
             cs_params.cs_size + 2,
@@ -453,7 +542,7 @@ mod test {
     use crate::plonk::plonk_setup::{
         preprocess_prover, preprocess_verifier, PlonkConstraintSystem,
     };
-    use crate::plonk::protocol::prover::{prover, verifier, PlonkPf};
+    use crate::plonk::protocol::prover::{batch_verifier, prover, verifier, PlonkPf};
     use algebra::bls12_381::BLSScalar;
     use algebra::groups::{One, ScalarArithmetic};
     use merlin::Transcript;
@@ -640,4 +729,95 @@ mod test {
             .is_ok())
         }
     }
+
+    #[test]
+    fn test_plonk_batch_verifier() {
+        let mut prng = ChaChaRng::from_seed([0u8; 32]);
+        let pcs = KZGCommitmentScheme::new(30, &mut prng);
+        type F = BLSScalar;
+        let one = F::one();
+        let two = one.add(&one);
+        let three = two.add(&one);
+        let five = three.add(&two);
+
+        // circuit x_0 * x_1 + x_0 with public x_1
+        let mut cs = PlonkConstraintSystem::<F>::new(4);
+        cs.insert_mul_gate(0, 1, 2);
+        cs.insert_add_gate(0, 2, 3);
+        cs.prepare_io_variable(1);
+        cs.pad();
+
+        let common_seed = [0u8; 32];
+        let prover_params = preprocess_prover(&cs, &pcs, common_seed).unwrap();
+        let verifier_params = preprocess_verifier(&cs, &pcs, common_seed).unwrap();
+        let instances = [
+            ([one, two, two, three], [two]),
+            ([two, one, two, F::from_u32(4)], [one]),
+            ([one, three, three, F::from_u32(4)], [three]),
+            ([two, two, F::from_u32(4), F::from_u32(6)], [two]),
+            (
+                [one, F::from_u32(4), F::from_u32(4), five],
+                [F::from_u32(4)],
+            ),
+        ];
+        let proofs: Vec<_> = instances
+            .iter()
+            .map(|(witness, online_vars)| {
+                assert!(cs.verify_witness(witness, online_vars).is_ok());
+                let mut transcript = Transcript::new(b"TestPlonk");
+                prover(
+                    &mut prng,
+                    &mut transcript,
+                    &pcs,
+                    &cs,
+                    &prover_params,
+                    witness,
+                )
+                .unwrap()
+            })
+            .collect();
+        let proofs_ref: Vec<_> = proofs.iter().collect();
+        let mut online_vars: Vec<&[F]> =
+            instances.iter().map(|(_, online)| &online[..]).collect();
+
+        let mut transcripts = vec![Transcript::new(b"TestPlonk"); proofs.len()];
+        assert!(batch_verifier(
+            &mut prng,
+            &mut transcripts,
+            &pcs,
+            &cs,
+            &verifier_params,
+            &online_vars,
+            &proofs_ref,
+        )
+        .is_ok());
+
+        // a single invalid instance makes the whole batch fail
+        let bad_online_vars = [five];
+        online_vars[2] = &bad_online_vars;
+        let mut transcripts = vec![Transcript::new(b"TestPlonk"); proofs.len()];
+        assert!(batch_verifier(
+            &mut prng,
+            &mut transcripts,
+            &pcs,
+            &cs,
+            &verifier_params,
+            &online_vars,
+            &proofs_ref,
+        )
+        .is_err());
+
+        // mismatched number of transcripts
+        let mut transcripts = vec![Transcript::new(b"TestPlonk"); proofs.len() - 1];
+        assert!(batch_verifier(
+            &mut prng,
+            &mut transcripts,
+            &pcs,
+            &cs,
+            &verifier_params,
+            &online_vars,
+            &proofs_ref,
+        )
+        .is_err());
+    }
 }
@@ -8,6 +8,7 @@ pub enum ZeiError {
     AXfrVerifierParamsError,
     AXfrVerificationError,
     AXfrProofError,
+    AXfrDoubleSpendError,
     ArgumentVerificationError,
     CommitmentInputError,
     CommitmentVerificationError,
@@ -57,6 +58,7 @@ impl fmt::Display for ZeiError {
       ZeiError::AXfrVerifierParamsError => "Could not preprocess anonymous transfer verifier",
       ZeiError::AXfrVerificationError => "Invalid AXfrBody for merkle root",
       ZeiError::AXfrProofError => "Could not create anonymous transfer proof",
+      ZeiError::AXfrDoubleSpendError => "Nullifier already spent",
                   ZeiError::ArgumentVerificationError => "Proof(argument) not valid for statement",
                   ZeiError::CommitmentInputError => "The number of messages to be committed is invalid",
                   ZeiError::CommitmentVerificationError => "Commitment verification failed",
@@ -0,0 +1,484 @@
+use crate::anon_xfr::structs::{AnonBlindAssetRecord, MTLeafInfo, MTNode, MTPath};
+use algebra::bls12_381::{BLSScalar, BLS_SCALAR_LEN};
+use algebra::groups::{Scalar, Zero};
+use crypto::basics::hash::rescue::RescueInstance;
+use ruc::*;
+use std::collections::{HashMap, VecDeque};
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Write};
+use std::path::Path;
+use utils::errors::ZeiError;
+
+// level (u32) || index (u64) || node value
+const RECORD_LEN: usize = 4 + 8 + BLS_SCALAR_LEN;
+// level marker of the records storing the number of leaves
+const N_LEAVES_LEVEL: u32 = u32::MAX;
+
+/// Storage backend of a `MerkleAccumulator`.
+/// Nodes are addressed by their `level` (0 for the leaves) and their `index` within the level.
+pub trait MTStore {
+    /// Return the node at (`level`, `index`), or None if it was never set
+    fn get_node(&self, level: usize, index: u64) -> Result<Option<BLSScalar>>;
+
+    /// Store `value` as the node at (`level`, `index`)
+    fn set_node(&mut self, level: usize, index: u64, value: BLSScalar) -> Result<()>;
+
+    /// Return the number of leaves appended so far
+    fn get_n_leaves(&self) -> Result<u64>;
+
+    /// Update the number of leaves appended so far
+    fn set_n_leaves(&mut self, n_leaves: u64) -> Result<()>;
+}
+
+/// In-memory accumulator storage
+#[derive(Debug, Default, Clone)]
+pub struct MemoryMTStore {
+    nodes: HashMap<(usize, u64), BLSScalar>,
+    n_leaves: u64,
+}
+
+impl MemoryMTStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl MTStore for MemoryMTStore {
+    fn get_node(&self, level: usize, index: u64) -> Result<Option<BLSScalar>> {
+        Ok(self.nodes.get(&(level, index)).copied())
+    }
+
+    fn set_node(&mut self, level: usize, index: u64, value: BLSScalar) -> Result<()> {
+        self.nodes.insert((level, index), value);
+        Ok(())
+    }
+
+    fn get_n_leaves(&self) -> Result<u64> {
+        Ok(self.n_leaves)
+    }
+
+    fn set_n_leaves(&mut self, n_leaves: u64) -> Result<()> {
+        self.n_leaves = n_leaves;
+        Ok(())
+    }
+}
+
+/// File-backed accumulator storage.
+/// Every update is appended to the file as a fixed size record, and the records are
+/// replayed into memory when the file is opened.
+pub struct FileMTStore {
+    cache: MemoryMTStore,
+    file: File,
+}
+
+impl FileMTStore {
+    /// Open the store saved at `path`, creating an empty one if the file does not exist
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let mut file = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .create(true)
+            .open(path)
+            .c(d!(ZeiError::ParameterError))?;
+        let mut contents = vec![];
+        file.read_to_end(&mut contents)
+            .c(d!(ZeiError::ParameterError))?;
+        if contents.len() % RECORD_LEN != 0 {
+            return Err(eg!(ZeiError::DeserializationError));
+        }
+
+        let mut cache = MemoryMTStore::new();
+        for record in contents.chunks(RECORD_LEN) {
+            let level = utils::u8_le_slice_to_u32(&record[0..4]);
+            let index = utils::u8_le_slice_to_u64(&record[4..12]);
+            if level == N_LEAVES_LEVEL {
+                cache.n_leaves = index;
+            } else {
+                let value = BLSScalar::from_bytes(&record[12..])
+                    .c(d!(ZeiError::DeserializationError))?;
+                cache.nodes.insert((level as usize, index), value);
+            }
+        }
+        Ok(FileMTStore { cache, file })
+    }
+
+    fn append_record(
+        &mut self,
+        level: u32,
+        index: u64,
+        value: &BLSScalar,
+    ) -> Result<()> {
+        let mut record = Vec::with_capacity(RECORD_LEN);
+        record.extend_from_slice(&level.to_le_bytes());
+        record.extend_from_slice(&index.to_le_bytes());
+        record.extend_from_slice(&value.to_bytes());
+        self.file
+            .write_all(&record)
+            .c(d!(ZeiError::SerializationError))
+    }
+}
+
+impl MTStore for FileMTStore {
+    fn get_node(&self, level: usize, index: u64) -> Result<Option<BLSScalar>> {
+        self.cache.get_node(level, index)
+    }
+
+    fn set_node(&mut self, level: usize, index: u64, value: BLSScalar) -> Result<()> {
+        if level >= N_LEAVES_LEVEL as usize {
+            return Err(eg!(ZeiError::ParameterError));
+        }
+        self.append_record(level as u32, index, &value).c(d!())?;
+        self.cache.set_node(level, index, value)
+    }
+
+    fn get_n_leaves(&self) -> Result<u64> {
+        self.cache.get_n_leaves()
+    }
+
+    fn set_n_leaves(&mut self, n_leaves: u64) -> Result<()> {
+        self.append_record(N_LEAVES_LEVEL, n_leaves, &BLSScalar::zero())
+            .c(d!())?;
+        self.cache.set_n_leaves(n_leaves)
+    }
+}
+
+/// Set of Merkle roots against which an anonymous transfer proof is accepted
+pub trait RootHistory {
+    /// Return true if `root` is an accepted state of the accumulator
+    fn is_known_root(&self, root: &BLSScalar) -> bool;
+}
+
+/// Window holding the last `capacity` roots of an accumulator.
+/// It is not persisted by the MTStore.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecentRoots {
+    capacity: usize,
+    roots: VecDeque<BLSScalar>,
+}
+
+impl RecentRoots {
+    /// Create an empty window keeping up to `capacity` roots
+    pub fn new(capacity: usize) -> Result<Self> {
+        if capacity == 0 {
+            return Err(eg!(ZeiError::ParameterError));
+        }
+        Ok(RecentRoots {
+            capacity,
+            roots: VecDeque::with_capacity(capacity),
+        })
+    }
+
+    /// Record `root` as the latest root, evicting the oldest one if the window is full
+    pub fn push(&mut self, root: BLSScalar) {
+        if self.roots.back() == Some(&root) {
+            return;
+        }
+        if self.roots.len() == self.capacity {
+            self.roots.pop_front();
+        }
+        self.roots.push_back(root);
+    }
+
+    /// Return the latest recorded root
+    pub fn latest(&self) -> Option<&BLSScalar> {
+        self.roots.back()
+    }
+
+    /// Iterate over the recorded roots, from the oldest to the latest
+    pub fn iter(&self) -> impl Iterator<Item = &BLSScalar> {
+        self.roots.iter()
+    }
+}
+
+impl RootHistory for RecentRoots {
+    fn is_known_root(&self, root: &BLSScalar) -> bool {
+        self.roots.contains(root)
+    }
+}
+
+/// Native append-only ternary Merkle tree over anonymous blind asset records.
+/// It computes the same tree as the one authenticated by the anonymous transfer circuit:
+/// the leaf of the record with identifier `uid` is `H(uid, commitment, H(pk_x, pk_y, 0, 0), 0)`,
+/// an inner node is `H(left, middle, right, 0)` and nodes that were never set are zero.
+pub struct MerkleAccumulator<S: MTStore> {
+    store: S,
+    depth: usize,
+    hash: RescueInstance<BLSScalar>,
+    roots: RecentRoots,
+}
+
+impl<S: MTStore> MerkleAccumulator<S> {
+    /// Build an accumulator of depth `depth` on top of `store`.
+    /// `store` may already contain a tree of the same depth.
+    /// Only the current root is accepted as known root.
+    pub fn new(store: S, depth: usize) -> Result<Self> {
+        Self::with_root_history(store, depth, 1).c(d!())
+    }
+
+    /// Build an accumulator of depth `depth` on top of `store` that remembers
+    /// the roots produced by its last `n_roots` updates (including the current root).
+    /// The window is kept in memory only: an accumulator reopened on an existing store
+    /// starts with the current root as its only known root.
+    pub fn with_root_history(store: S, depth: usize, n_roots: usize) -> Result<Self> {
+        if depth == 0 || 3u64.checked_pow(depth as u32).is_none() {
+            return Err(eg!(ZeiError::ParameterError));
+        }
+        let mut acc = MerkleAccumulator {
+            store,
+            depth,
+            hash: RescueInstance::new(),
+            roots: RecentRoots::new(n_roots).c(d!())?,
+        };
+        let root = acc.get_root().c(d!())?;
+        acc.roots.push(root);
+        Ok(acc)
+    }
+
+    /// Return the depth of the tree
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// Return the number of records appended so far
+    pub fn size(&self) -> Result<u64> {
+        self.store.get_n_leaves().c(d!())
+    }
+
+    /// Return the current root of the tree
+    pub fn get_root(&self) -> Result<BLSScalar> {
+        self.get_node(self.depth, 0).c(d!())
+    }
+
+    /// Append `abar` to the tree and return its uid
+    pub fn add_abar(&mut self, abar: &AnonBlindAssetRecord) -> Result<u64> {
+        let uid = self.size().c(d!())?;
+        if uid >= 3u64.pow(self.depth as u32) {
+            return Err(eg!(ZeiError::ParameterError));
+        }
+
+        let mut node = self.compute_leaf(uid, abar);
+        let mut index = uid;
+        self.store.set_node(0, index, node).c(d!())?;
+        for level in 1..=self.depth {
+            index /= 3;
+            let zero = BLSScalar::zero();
+            let mut input = [zero; 4];
+            for (i, child) in input.iter_mut().take(3).enumerate() {
+                *child = self.get_node(level - 1, 3 * index + i as u64).c(d!())?;
+            }
+            node = self.hash.rescue_hash(&input)[0];
+            self.store.set_node(level, index, node).c(d!())?;
+        }
+        self.store.set_n_leaves(uid + 1).c(d!())?;
+        self.roots.push(node);
+        Ok(uid)
+    }
+
+    /// Return the window of recent roots
+    pub fn recent_roots(&self) -> &RecentRoots {
+        &self.roots
+    }
+
+    /// Return the authentication path of the record with identifier `uid`
+    pub fn get_path(&self, uid: u64) -> Result<MTPath> {
+        if uid >= self.size().c(d!())? {
+            return Err(eg!(ZeiError::ParameterError));
+        }
+        let mut nodes = Vec::with_capacity(self.depth);
+        let mut index = uid;
+        for level in 0..self.depth {
+            let position = index % 3;
+            let first = index - position;
+            let siblings: Vec<u64> =
+                (first..first + 3).filter(|&i| i != index).collect();
+            nodes.push(MTNode {
+                siblings1: self.get_node(level, siblings[0]).c(d!())?,
+                siblings2: self.get_node(level, siblings[1]).c(d!())?,
+                is_left_child: (position == 0) as u8,
+                is_right_child: (position == 2) as u8,
+            });
+            index /= 3;
+        }
+        // the path is ordered from the root to the leaf
+        nodes.reverse();
+        Ok(MTPath::new(nodes))
+    }
+
+    /// Return the Merkle tree information needed to spend the record with identifier `uid`
+    /// against the current root
+    pub fn get_leaf_info(&self, uid: u64) -> Result<MTLeafInfo> {
+        Ok(MTLeafInfo {
+            path: self.get_path(uid).c(d!())?,
+            root: self.get_root().c(d!())?,
+            uid,
+        })
+    }
+
+    fn get_node(&self, level: usize, index: u64) -> Result<BLSScalar> {
+        Ok(self
+            .store
+            .get_node(level, index)
+            .c(d!())?
+            .unwrap_or_else(BLSScalar::zero))
+    }
+
+    fn compute_leaf(&self, uid: u64, abar: &AnonBlindAssetRecord) -> BLSScalar {
+        let zero = BLSScalar::zero();
+        let pk = abar.public_key.as_jubjub_point();
+        let pk_hash = self.hash.rescue_hash(&[pk.get_x(), pk.get_y(), zero, zero])[0];
+        self.hash.rescue_hash(&[
+            BLSScalar::from_u64(uid),
+            abar.amount_type_commitment,
+            pk_hash,
+            zero,
+        ])[0]
+    }
+}
+
+impl<S: MTStore> RootHistory for MerkleAccumulator<S> {
+    fn is_known_root(&self, root: &BLSScalar) -> bool {
+        self.roots.is_known_root(root)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::anon_xfr::accumulator::{
+        FileMTStore, MemoryMTStore, MerkleAccumulator, RecentRoots, RootHistory,
+    };
+    use crate::anon_xfr::keys::AXfrKeyPair;
+    use crate::anon_xfr::structs::{AnonBlindAssetRecord, MTLeafInfo};
+    use algebra::bls12_381::BLSScalar;
+    use algebra::groups::{Scalar, Zero};
+    use crypto::basics::hash::rescue::RescueInstance;
+    use rand_chacha::ChaChaRng;
+    use rand_core::SeedableRng;
+
+    fn random_abar(prng: &mut ChaChaRng) -> AnonBlindAssetRecord {
+        AnonBlindAssetRecord {
+            amount_type_commitment: BLSScalar::random(prng),
+            public_key: AXfrKeyPair::generate(prng).pub_key(),
+        }
+    }
+
+    // recompute the root the same way the anonymous transfer circuit does
+    fn compute_root(abar: &AnonBlindAssetRecord, leaf_info: &MTLeafInfo) -> BLSScalar {
+        let hash = RescueInstance::new();
+        let zero = BLSScalar::zero();
+        let pk = abar.public_key.as_jubjub_point();
+        let pk_hash = hash.rescue_hash(&[pk.get_x(), pk.get_y(), zero, zero])[0];
+        let mut node = hash.rescue_hash(&[
+            BLSScalar::from_u64(leaf_info.uid),
+            abar.amount_type_commitment,
+            pk_hash,
+            zero,
+        ])[0];
+        for path_node in leaf_info.path.nodes.iter().rev() {
+            let input = match (path_node.is_left_child, path_node.is_right_child) {
+                (1, 0) => vec![node, path_node.siblings1, path_node.siblings2, zero],
+                (0, 0) => vec![path_node.siblings1, node, path_node.siblings2, zero],
+                _ => vec![path_node.siblings1, path_node.siblings2, node, zero],
+            };
+            node = hash.rescue_hash(&input)[0];
+        }
+        node
+    }
+
+    #[test]
+    fn test_accumulator_paths() {
+        let mut prng = ChaChaRng::from_seed([0u8; 32]);
+        let mut acc = MerkleAccumulator::new(MemoryMTStore::new(), 2).unwrap();
+        assert_eq!(acc.get_root().unwrap(), BLSScalar::zero());
+        assert!(acc.get_leaf_info(0).is_err());
+
+        let abars: Vec<AnonBlindAssetRecord> =
+            (0..9).map(|_| random_abar(&mut prng)).collect();
+        let mut roots = vec![];
+        for (i, abar) in abars.iter().enumerate() {
+            assert_eq!(acc.add_abar(abar).unwrap(), i as u64);
+            let root = acc.get_root().unwrap();
+            assert!(!roots.contains(&root));
+            roots.push(root);
+            // every appended record authenticates against the current root
+            for (uid, abar) in abars.iter().enumerate().take(i + 1) {
+                let leaf_info = acc.get_leaf_info(uid as u64).unwrap();
+                assert_eq!(leaf_info.uid, uid as u64);
+                assert_eq!(leaf_info.path.nodes.len(), 2);
+                assert_eq!(compute_root(abar, &leaf_info), root);
+            }
+        }
+        // the tree is full
+        assert!(acc.add_abar(&random_abar(&mut prng)).is_err());
+        assert_eq!(acc.size().unwrap(), 9);
+
+        assert!(MerkleAccumulator::new(MemoryMTStore::new(), 0).is_err());
+    }
+
+    #[test]
+    fn test_file_store() {
+        let mut prng = ChaChaRng::from_seed([1u8; 32]);
+        let path = std::env::temp_dir().join("zei_test_file_mt_store.bin");
+        let _ = std::fs::remove_file(&path);
+
+        let abars: Vec<AnonBlindAssetRecord> =
+            (0..5).map(|_| random_abar(&mut prng)).collect();
+        let mut mem_acc = MerkleAccumulator::new(MemoryMTStore::new(), 3).unwrap();
+        {
+            let mut file_acc =
+                MerkleAccumulator::new(FileMTStore::open(&path).unwrap(), 3).unwrap();
+            for abar in abars.iter().take(3) {
+                file_acc.add_abar(abar).unwrap();
+                mem_acc.add_abar(abar).unwrap();
+            }
+        }
+        // reopen the file and keep appending
+        let mut file_acc =
+            MerkleAccumulator::new(FileMTStore::open(&path).unwrap(), 3).unwrap();
+        assert_eq!(file_acc.size().unwrap(), 3);
+        // the window of recent roots starts with the current root only
+        assert_eq!(file_acc.recent_roots().iter().count(), 1);
+        assert_eq!(file_acc.get_root().unwrap(), mem_acc.get_root().unwrap());
+        for abar in abars.iter().skip(3) {
+            assert_eq!(
+                file_acc.add_abar(abar).unwrap(),
+                mem_acc.add_abar(abar).unwrap()
+            );
+        }
+        assert_eq!(file_acc.get_root().unwrap(), mem_acc.get_root().unwrap());
+        assert_eq!(
+            file_acc.get_leaf_info(1).unwrap(),
+            mem_acc.get_leaf_info(1).unwrap()
+        );
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_root_history() {
+        let mut prng = ChaChaRng::from_seed([2u8; 32]);
+        assert!(RecentRoots::new(0).is_err());
+
+        let mut acc =
+            MerkleAccumulator::with_root_history(MemoryMTStore::new(), 2, 3).unwrap();
+        let mut roots = vec![acc.get_root().unwrap()];
+        for _ in 0..4 {
+            acc.add_abar(&random_abar(&mut prng)).unwrap();
+            roots.push(acc.get_root().unwrap());
+        }
+        // only the last 3 roots are remembered
+        assert!(!acc.is_known_root(&roots[0]));
+        assert!(!acc.is_known_root(&roots[1]));
+        for root in roots.iter().skip(2) {
+            assert!(acc.is_known_root(root));
+        }
+        assert_eq!(acc.recent_roots().latest(), Some(&roots[4]));
+
+        // by default only the current root is known
+        let mut acc = MerkleAccumulator::new(MemoryMTStore::new(), 2).unwrap();
+        let old_root = acc.get_root().unwrap();
+        assert!(acc.is_known_root(&old_root));
+        acc.add_abar(&random_abar(&mut prng)).unwrap();
+        assert!(!acc.is_known_root(&old_root));
+        assert!(acc.is_known_root(&acc.get_root().unwrap()));
+    }
+}
@@ -1,20 +1,30 @@
-use crate::anon_xfr::keys::AXfrKeyPair;
+use crate::anon_xfr::circuits::{
+    AMultiXfrPubInputs, AMultiXfrWitness, PayeeSecret, PayerSecret,
+};
+use crate::anon_xfr::keys::{AXfrKeyPair, AXfrPubKey, AXfrSignature};
+use crate::anon_xfr::nullifier;
 use crate::anon_xfr::proofs::{
-    prove_eq_committed_vals, verify_eq_committed_vals, AXfrPlonkPf,
+    prove_eq_committed_vals, prove_xfr, verify_eq_committed_vals, verify_xfr,
+    AXfrPlonkPf,
 };
 use crate::anon_xfr::structs::{
-    AnonBlindAssetRecord, OpenAnonBlindAssetRecord, OpenAnonBlindAssetRecordBuilder,
+    AXfrProof, AnonBlindAssetRecord, Commitment, Nullifier, OpenAnonBlindAssetRecord,
+    OpenAnonBlindAssetRecordBuilder,
 };
 use crate::setup::{NodeParams, UserParams};
+use crate::xfr::asset_record::{build_open_asset_record, AssetRecordType};
 use crate::xfr::sig::{XfrKeyPair, XfrPublicKey, XfrSignature};
 use crate::xfr::structs::{
-    BlindAssetRecord, OpenAssetRecord, OwnerMemo, XfrAmount, XfrAssetType,
+    AssetRecordTemplate, BlindAssetRecord, OpenAssetRecord, OwnerMemo, XfrAmount,
+    XfrAssetType,
 };
 use algebra::bls12_381::BLSScalar;
 use algebra::groups::{GroupArithmetic, Scalar, ScalarArithmetic, Zero};
 use algebra::jubjub::{JubjubPoint, JubjubScalar};
 use algebra::ristretto::{RistrettoPoint, RistrettoScalar};
 use crypto::basics::commitments::pedersen::PedersenGens;
+use crypto::basics::commitments::rescue::HashCommitment;
+use crypto::basics::commitments::ristretto_pedersen::RistrettoPedersenGens;
 use crypto::basics::hybrid_encryption::XPublicKey;
 use crypto::pc_eq_groups::{prove_pair_to_vector_pc, Proof as PCEqProof};
 use merlin::Transcript;
@@ -31,6 +41,15 @@ pub struct ConvertBarAbarProof {
     pc_rescue_commitments_eq_proof: AXfrPlonkPf,
 }
 
+/// Proof that an ABAR was spent into an intermediate Rescue commitment
+/// and that the output BAR commits to the same amount and asset type.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ConvertAbarBarProof {
+    spending_proof: AXfrProof,
+    commitment: Commitment,
+    conversion_proof: ConvertBarAbarProof,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct BarToAbarBody {
     pub input: BlindAssetRecord,
@@ -41,9 +60,10 @@ pub struct BarToAbarBody {
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct AbarToBarBody {
-    pub input: AnonBlindAssetRecord,
+    pub input: (Nullifier, AXfrPubKey),
     pub output: BlindAssetRecord,
-    pub proof: ConvertBarAbarProof,
+    pub proof: ConvertAbarBarProof,
+    pub memo: Option<OwnerMemo>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -52,6 +72,12 @@ pub struct BarToAbarNote {
     pub signature: XfrSignature,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AbarToBarNote {
+    pub body: AbarToBarBody,
+    pub signature: AXfrSignature,
+}
+
 /// Generate Bar To Abar conversion note body
 /// Returns note Body and ABAR opening keys
 pub fn gen_bar_to_abar_body<R: CryptoRng + RngCore>(
@@ -110,6 +136,193 @@ pub fn verify_bar_to_abar_note(
     bar_pub_key.verify(&msg, &note.signature).c(d!())
 }
 
+/// Generate AnonymousBlindAssetRecord To BlindAssetRecord conversion note body.
+/// Returns the note body and the diversified key pair that must sign it.
+/// * `xfr_params` - User parameters for a 1-input/1-output anonymous transfer, without padding
+/// * `eq_params` - User parameters for the equality of committed values proof
+/// * `record` - Open ABAR to spend, it must contain its Merkle tree leaf information
+/// * `abar_keypair` - Key pair owning `record`
+/// * `bar_pub_key` - Owner of the output BAR
+/// * `asset_record_type` - Confidentiality flags of the output BAR
+pub fn gen_abar_to_bar_body<R: CryptoRng + RngCore>(
+    prng: &mut R,
+    xfr_params: &UserParams,
+    eq_params: &UserParams,
+    record: &OpenAnonBlindAssetRecord,
+    abar_keypair: &AXfrKeyPair,
+    bar_pub_key: &XfrPublicKey,
+    asset_record_type: AssetRecordType,
+) -> Result<(AbarToBarBody, AXfrKeyPair)> {
+    if xfr_params.padding.is_some() {
+        return Err(eg!(ZeiError::AXfrProverParamsError));
+    }
+    if record.pub_key != abar_keypair.pub_key() {
+        return Err(eg!(ZeiError::ParameterError));
+    }
+    let mt_leaf_info = record
+        .mt_leaf_info
+        .as_ref()
+        .c(d!(ZeiError::ParameterError))?;
+
+    // 1. randomize input key pair with open_abar rand key and diversify it
+    let rand_keypair = abar_keypair.randomize(&record.key_rand_factor);
+    let diversifier = JubjubScalar::random(prng);
+    let input = (
+        nullifier(
+            &rand_keypair,
+            record.amount,
+            &record.asset_type,
+            mt_leaf_info.uid,
+        ),
+        rand_keypair.pub_key().randomize(&diversifier),
+    );
+
+    // 2. prove the input is spent into an intermediate Rescue commitment
+    let blind_hash = BLSScalar::random(prng);
+    let payer_secret = PayerSecret {
+        sec_key: rand_keypair.get_secret_scalar(),
+        diversifier,
+        uid: mt_leaf_info.uid,
+        amount: record.amount,
+        asset_type: record.asset_type.as_scalar(),
+        path: mt_leaf_info.path.clone(),
+        blind: record.blind,
+        is_dummy: false,
+    };
+    let payee_secret = PayeeSecret {
+        amount: record.amount,
+        blind: blind_hash,
+        asset_type: record.asset_type.as_scalar(),
+        tracer: None,
+    };
+    let secret_inputs = AMultiXfrWitness {
+        payers_secrets: vec![payer_secret],
+        payees_secrets: vec![payee_secret],
+        public_outputs: vec![],
+        fee_amount: 0,
+        fee_asset_type: BLSScalar::zero(),
+        padded: false,
+    };
+    let snark_proof = prove_xfr(prng, xfr_params, secret_inputs).c(d!())?;
+    let commitment = HashCommitment::new()
+        .commit(
+            &blind_hash,
+            &[
+                BLSScalar::from_u64(record.amount),
+                record.asset_type.as_scalar(),
+            ],
+        )
+        .c(d!())?;
+
+    // 3. build the output BAR and prove it commits to the same values
+    let pc_gens = RistrettoPedersenGens::default();
+    let template = AssetRecordTemplate::with_no_asset_tracing(
+        record.amount,
+        record.asset_type,
+        asset_record_type,
+        *bar_pub_key,
+    );
+    let (obar, _, memo) = build_open_asset_record(prng, &pc_gens, &template, vec![]);
+    let conversion_proof =
+        prove_eq_commitments(prng, eq_params, &obar, blind_hash).c(d!())?;
+
+    let body = AbarToBarBody {
+        input,
+        output: obar.blind_asset_record,
+        proof: ConvertAbarBarProof {
+            spending_proof: AXfrProof {
+                snark_proof,
+                merkle_root: mt_leaf_info.root,
+            },
+            commitment,
+            conversion_proof,
+        },
+        memo,
+    };
+    Ok((body, rand_keypair.randomize(&diversifier)))
+}
+
+/// Generate AnonymousBlindAssetRecord To BlindAssetRecord conversion note: body + signature
+/// by the diversified input key
+pub fn gen_abar_to_bar_note<R: CryptoRng + RngCore>(
+    prng: &mut R,
+    xfr_params: &UserParams,
+    eq_params: &UserParams,
+    record: &OpenAnonBlindAssetRecord,
+    abar_keypair: &AXfrKeyPair,
+    bar_pub_key: &XfrPublicKey,
+    asset_record_type: AssetRecordType,
+) -> Result<AbarToBarNote> {
+    let (body, diversified_keypair) = gen_abar_to_bar_body(
+        prng,
+        xfr_params,
+        eq_params,
+        record,
+        abar_keypair,
+        bar_pub_key,
+        asset_record_type,
+    )
+    .c(d!())?;
+    let msg = bincode::serialize(&body)
+        .map_err(|_| ZeiError::SerializationError)
+        .c(d!())?;
+    let signature = diversified_keypair.sign(&msg);
+    Ok(AbarToBarNote { body, signature })
+}
+
+/// Verifies AnonymousBlindAssetRecord To BlindAssetRecord conversion body
+/// * `merkle_root` - candidate state of the accumulator. It must match the root the spending proof was built against.
+/// Warning: This function doesn't check that input owner has signed the body, nor that the nullifier is unspent
+pub fn verify_abar_to_bar_body(
+    xfr_params: &NodeParams,
+    eq_params: &NodeParams,
+    body: &AbarToBarBody,
+    merkle_root: &BLSScalar,
+) -> Result<()> {
+    if *merkle_root != body.proof.spending_proof.merkle_root {
+        return Err(eg!(ZeiError::AXfrVerificationError));
+    }
+    let pub_inputs = AMultiXfrPubInputs {
+        payers_inputs: vec![body.input.clone()],
+        payees_commitments: vec![body.proof.commitment],
+        payees_tracer_memos: vec![None],
+        merkle_root: *merkle_root,
+        public_outputs: vec![],
+        fee_amount: 0,
+        fee_asset_type: BLSScalar::zero(),
+    };
+    verify_xfr(
+        xfr_params,
+        &pub_inputs,
+        &body.proof.spending_proof.snark_proof,
+    )
+    .c(d!(ZeiError::AXfrVerificationError))?;
+    verify_eq_commitments(
+        eq_params,
+        &body.output,
+        body.proof.commitment,
+        &body.proof.conversion_proof,
+    )
+    .c(d!())
+}
+
+/// Verifies AnonymousBlindAssetRecord To BlindAssetRecord conversion note by verifying proof of conversion
+/// and signature by the diversified input key
+pub fn verify_abar_to_bar_note(
+    xfr_params: &NodeParams,
+    eq_params: &NodeParams,
+    note: &AbarToBarNote,
+    merkle_root: &BLSScalar,
+) -> Result<()> {
+    verify_abar_to_bar_body(xfr_params, eq_params, &note.body, merkle_root).c(d!())?;
+    let msg = bincode::serialize(&note.body).c(d!(ZeiError::SerializationError))?;
+    note.body
+        .input
+        .1
+        .verify(&msg, note.signature.clone())
+        .c(d!())
+}
+
 pub(crate) fn bar_to_abar<R: CryptoRng + RngCore>(
     prng: &mut R,
     params: &UserParams,
@@ -117,6 +330,38 @@ pub(crate) fn bar_to_abar<R: CryptoRng + RngCore>(
     abar_keypair: &AXfrKeyPair,
     enc_key: &XPublicKey,
 ) -> Result<(OpenAnonBlindAssetRecord, ConvertBarAbarProof)> {
+    // 1. compute ABAR
+    let oabar = OpenAnonBlindAssetRecordBuilder::new()
+        .amount(obar.amount)
+        .asset_type(obar.asset_type)
+        .pub_key(abar_keypair.pub_key())
+        .finalize(prng, &enc_key)
+        .c(d!())?
+        .build()
+        .c(d!())?;
+    // 2. prove abar correctness
+    let proof = prove_eq_commitments(prng, params, obar, oabar.blind).c(d!())?;
+
+    Ok((oabar, proof))
+}
+
+pub(crate) fn verify_bar_to_abar(
+    params: &NodeParams,
+    bar: &BlindAssetRecord,
+    abar: &AnonBlindAssetRecord,
+    proof: &ConvertBarAbarProof,
+) -> Result<()> {
+    verify_eq_commitments(params, bar, abar.amount_type_commitment, proof).c(d!())
+}
+
+/// Prove that the Pedersen commitments in `obar` and the Rescue commitment with blinding `blind_hash`
+/// hide the same amount and asset type
+fn prove_eq_commitments<R: CryptoRng + RngCore>(
+    prng: &mut R,
+    params: &UserParams,
+    obar: &OpenAssetRecord,
+    blind_hash: BLSScalar,
+) -> Result<ConvertBarAbarProof> {
     // 1. compute commitments under jubjub
     let pc_gens_jubjub = PedersenGens::<JubjubPoint>::new(2);
     let pc_gens_ristretto =
@@ -149,41 +394,30 @@ pub(crate) fn bar_to_abar<R: CryptoRng + RngCore>(
     )
     .c(d!())?;
 
-    // 3. compute ABAR
-    let oabar = OpenAnonBlindAssetRecordBuilder::new()
-        .amount(obar.amount)
-        .asset_type(obar.asset_type)
-        .pub_key(abar_keypair.pub_key())
-        .finalize(prng, &enc_key)
-        .c(d!())?
-        .build()
-        .c(d!())?;
-    // 4. prove abar correctness
+    // 3. prove equality of the jubjub and Rescue commitments
     let pc_rescue_commitments_eq_proof = prove_eq_committed_vals(
         prng,
         params,
         BLSScalar::from_u64(obar.amount),
         BLSScalar::from(&asset_type_scalar),
         BLSScalar::from(&blind),
-        oabar.blind,
+        blind_hash,
         &pc_gens_jubjub,
     )
     .c(d!())?;
 
-    Ok((
-        oabar,
-        ConvertBarAbarProof {
-            commitment_amount_asset_type,
-            commitment_eq_proof,
-            pc_rescue_commitments_eq_proof,
-        },
-    ))
+    Ok(ConvertBarAbarProof {
+        commitment_amount_asset_type,
+        commitment_eq_proof,
+        pc_rescue_commitments_eq_proof,
+    })
 }
 
-pub(crate) fn verify_bar_to_abar(
+/// Verify that `bar` and the Rescue commitment `hash_comm` hide the same amount and asset type
+fn verify_eq_commitments(
     params: &NodeParams,
     bar: &BlindAssetRecord,
-    abar: &AnonBlindAssetRecord,
+    hash_comm: Commitment,
     proof: &ConvertBarAbarProof,
 ) -> Result<()> {
     let pc_gens_rist =
@@ -213,6 +447,15 @@ pub(crate) fn verify_bar_to_abar(
                     .c(d!())?,
             )
         }
+        XfrAmount::Confidential64(com) => (
+            com.decompress()
+                .ok_or(ZeiError::DecompressElementError)
+                .c(d!())?,
+            // commitment to zero as high part
+            pc_gens_rist
+                .commit(&[RistrettoScalar::zero()], &RistrettoScalar::zero())
+                .c(d!())?,
+        ),
     };
 
     // 1.2 get asset type commitment
@@ -248,7 +491,7 @@ pub(crate) fn verify_bar_to_abar(
     // 3. verify PLONK proof
     verify_eq_committed_vals(
         params,
-        abar.amount_type_commitment,
+        hash_comm,
         com_amount_asset_type,
         &proof.pc_rescue_commitments_eq_proof,
     )
@@ -258,13 +501,15 @@ pub(crate) fn verify_bar_to_abar(
 #[cfg(test)]
 mod test {
     use crate::anon_xfr::bar_to_from_abar::{
-        gen_bar_to_abar_note, verify_bar_to_abar_note,
+        gen_abar_to_bar_note, gen_bar_to_abar_note, verify_abar_to_bar_note,
+        verify_bar_to_abar_note,
     };
     use crate::anon_xfr::keys::AXfrKeyPair;
     use crate::anon_xfr::structs::{
-        AnonBlindAssetRecord, OpenAnonBlindAssetRecordBuilder,
+        AnonBlindAssetRecord, MTLeafInfo, MTNode, MTPath,
+        OpenAnonBlindAssetRecordBuilder,
     };
-    use crate::setup::{NodeParams, UserParams};
+    use crate::setup::{NodeParams, UserParams, DEFAULT_BP_NUM_GENS};
     use crate::xfr::asset_record::{
         build_blind_asset_record, open_blind_asset_record, AssetRecordType,
     };
@@ -272,7 +517,10 @@ mod test {
     use crate::xfr::structs::{
         AssetRecordTemplate, AssetType, BlindAssetRecord, OwnerMemo,
     };
+    use algebra::bls12_381::BLSScalar;
+    use algebra::groups::{Scalar, Zero};
     use crypto::basics::commitments::ristretto_pedersen::RistrettoPedersenGens;
+    use crypto::basics::hash::rescue::RescueInstance;
     use crypto::basics::hybrid_encryption::{XPublicKey, XSecretKey};
     use rand_chacha::ChaChaRng;
     use rand_core::SeedableRng;
@@ -412,4 +660,121 @@ mod test {
             verify_bar_to_abar_note(&node_params, &note, &bar_keypair.pub_key).is_err()
         )
     }
+
+    #[test]
+    fn test_abar_to_bar_note() {
+        let mut prng = ChaChaRng::from_seed([0u8; 32]);
+        let xfr_params =
+            UserParams::from_file_if_exists(1, 1, Some(1), DEFAULT_BP_NUM_GENS, None)
+                .unwrap();
+        let eq_params = UserParams::eq_committed_vals_params();
+        let abar_keypair = AXfrKeyPair::generate(&mut prng);
+        let dec_key = XSecretKey::new(&mut prng);
+        let enc_key = XPublicKey::from(&dec_key);
+        let bar_keypair = XfrKeyPair::generate(&mut prng);
+        let amount = 10u64;
+        let asset_type = AssetType::from_identical_byte(1u8);
+
+        // simulate input abar as the single leaf of the accumulator
+        let oabar = OpenAnonBlindAssetRecordBuilder::new()
+            .amount(amount)
+            .asset_type(asset_type)
+            .pub_key(abar_keypair.pub_key())
+            .finalize(&mut prng, &enc_key)
+            .unwrap()
+            .build()
+            .unwrap();
+        let abar = AnonBlindAssetRecord::from_oabar(&oabar);
+        let zero = BLSScalar::zero();
+        let hash = RescueInstance::new();
+        let pk_jj = abar.public_key.as_jubjub_point();
+        let pk_hash = hash.rescue_hash(&[pk_jj.get_x(), pk_jj.get_y(), zero, zero])[0];
+        let leaf = hash.rescue_hash(&[
+            BLSScalar::from_u32(0),
+            abar.amount_type_commitment,
+            pk_hash,
+            zero,
+        ])[0];
+        let merkle_root = hash.rescue_hash(&[leaf, zero, zero, zero])[0];
+        let mt_leaf_info = MTLeafInfo {
+            path: MTPath::new(vec![MTNode {
+                siblings1: zero,
+                siblings2: zero,
+                is_left_child: 1u8,
+                is_right_child: 0u8,
+            }]),
+            root: merkle_root,
+            uid: 0,
+        };
+        let oabar = OpenAnonBlindAssetRecordBuilder::from_abar(
+            &abar,
+            oabar.get_owner_memo().unwrap(),
+            &abar_keypair,
+            &dec_key,
+        )
+        .unwrap()
+        .mt_leaf_info(mt_leaf_info)
+        .build()
+        .unwrap();
+
+        let notes: Vec<_> = [
+            AssetRecordType::ConfidentialAmount_ConfidentialAssetType,
+            AssetRecordType::NonConfidentialAmount_NonConfidentialAssetType,
+        ]
+        .iter()
+        .map(|ar_type| {
+            gen_abar_to_bar_note(
+                &mut prng,
+                &xfr_params,
+                &eq_params,
+                &oabar,
+                &abar_keypair,
+                &bar_keypair.pub_key,
+                *ar_type,
+            )
+            .unwrap()
+        })
+        .collect();
+
+        let node_xfr_params = NodeParams::from(xfr_params);
+        let node_eq_params = NodeParams::from(eq_params);
+        for note in notes {
+            // 1. check that bar_keypair opens the output
+            let obar = open_blind_asset_record(
+                &note.body.output,
+                &note.body.memo,
+                &bar_keypair,
+            )
+            .unwrap();
+            assert_eq!(obar.amount, amount);
+            assert_eq!(obar.asset_type, asset_type);
+
+            // 2. verify the note
+            assert!(verify_abar_to_bar_note(
+                &node_xfr_params,
+                &node_eq_params,
+                &note,
+                &merkle_root
+            )
+            .is_ok());
+            assert!(verify_abar_to_bar_note(
+                &node_xfr_params,
+                &node_eq_params,
+                &note,
+                &zero
+            )
+            .is_err());
+
+            // 3. a tampered signature must be rejected
+            let mut note = note;
+            note.signature = abar_keypair.sign(b"anymessage");
+            assert!(verify_abar_to_bar_note(
+                &node_xfr_params,
+                &node_eq_params,
+                &note,
+                &merkle_root
+            )
+            .is_err());
+        }
+    }
 }
@@ -1,13 +1,18 @@
-use crate::anon_xfr::keys::AXfrPubKey;
-use crate::anon_xfr::structs::{BlindFactor, Commitment, MTNode, MTPath, Nullifier};
+use crate::anon_xfr::keys::{AXfrPubKey, AXfrTracerEncKey};
+use crate::anon_xfr::structs::{
+    AXfrTracerMemo, BlindFactor, Commitment, MTNode, MTPath, Nullifier,
+};
 use algebra::bls12_381::BLSScalar;
 use algebra::groups::{Group, GroupArithmetic, One, Scalar, ScalarArithmetic, Zero};
 use algebra::jubjub::{JubjubPoint, JubjubScalar};
 use crypto::basics::commitments::pedersen::PedersenGens;
 use crypto::basics::commitments::rescue::HashCommitment as CommScheme;
+use crypto::basics::elgamal::{
+    elgamal_hybrid_encrypt as native_hybrid_encrypt, ElGamalEncKey,
+};
 use crypto::basics::hash::rescue::RescueInstance;
 use crypto::basics::prf::PRF;
-use poly_iops::plonk::turbo_plonk_cs::ecc::PointVar;
+use poly_iops::plonk::turbo_plonk_cs::ecc::{Point, PointVar};
 use poly_iops::plonk::turbo_plonk_cs::rescue::StateVar;
 use poly_iops::plonk::turbo_plonk_cs::{TurboPlonkConstraintSystem, VarIndex};
 
@@ -28,6 +33,7 @@ pub(crate) struct PayerSecret {
     pub uid: u64,
     pub path: MTPath,
     pub blind: BlindFactor,
+    pub is_dummy: bool, // zero-amount input padding a transfer, it is not in the accumulator
 }
 
 #[derive(Debug, Clone)]
@@ -35,6 +41,21 @@ pub(crate) struct PayeeSecret {
     pub amount: u64,
     pub blind: BlindFactor,
     pub asset_type: BLSScalar,
+    pub tracer: Option<TracerSecret>, // encryption of amount and asset type to an asset tracer
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct TracerSecret {
+    pub enc_key: AXfrTracerEncKey,
+    pub rand: JubjubScalar, // ElGamal encryption randomness
+}
+
+/// Output whose amount, asset type and owner are public.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct PublicOutput {
+    pub amount: u64,
+    pub asset_type: BLSScalar,
+    pub owner: BLSScalar, // hash of the public key owning the output
 }
 
 /// Secret witness of an anonymous transaction.
@@ -42,11 +63,52 @@ pub(crate) struct PayeeSecret {
 pub(crate) struct AMultiXfrWitness {
     pub payers_secrets: Vec<PayerSecret>,
     pub payees_secrets: Vec<PayeeSecret>,
+    pub public_outputs: Vec<PublicOutput>,
+    pub fee_amount: u64, // public fee subtracted from the inputs
+    pub fee_asset_type: BLSScalar, // asset type of the public fee
+    pub padded: bool,    // the circuit accepts dummy inputs
 }
 
 impl AMultiXfrWitness {
     // create a default `AMultiXfrWitness`.
     pub(crate) fn fake(n_payers: usize, n_payees: usize, tree_depth: usize) -> Self {
+        Self::fake_with_tracing(n_payers, &vec![false; n_payees], tree_depth)
+    }
+
+    // create a default `AMultiXfrWitness` for a circuit accepting dummy inputs.
+    pub(crate) fn fake_padded(
+        n_payers: usize,
+        n_payees: usize,
+        tree_depth: usize,
+    ) -> Self {
+        let mut witness = Self::fake(n_payers, n_payees, tree_depth);
+        witness.padded = true;
+        witness
+    }
+
+    // create a default `AMultiXfrWitness` with `n_public_outputs` public outputs.
+    pub(crate) fn fake_with_public_outputs(
+        n_payers: usize,
+        n_payees: usize,
+        n_public_outputs: usize,
+        tree_depth: usize,
+    ) -> Self {
+        let mut witness = Self::fake(n_payers, n_payees, tree_depth);
+        let public_output = PublicOutput {
+            amount: 0,
+            asset_type: BLSScalar::zero(),
+            owner: BLSScalar::zero(),
+        };
+        witness.public_outputs = vec![public_output; n_public_outputs];
+        witness
+    }
+
+    // create a default `AMultiXfrWitness` where the i-th output is traced iff `outputs_tracing[i]`.
+    pub(crate) fn fake_with_tracing(
+        n_payers: usize,
+        outputs_tracing: &[bool],
+        tree_depth: usize,
+    ) -> Self {
         let bls_zero = BLSScalar::zero();
         let jubjub_zero = JubjubScalar::zero();
         let node = MTNode {
@@ -63,16 +125,32 @@ impl AMultiXfrWitness {
             asset_type: bls_zero,
             path: MTPath::new(vec![node; tree_depth]),
             blind: bls_zero,
+            is_dummy: false,
         };
-        let payee_secret = PayeeSecret {
-            amount: 0,
-            blind: bls_zero,
-            asset_type: bls_zero,
-        };
+        let payees_secrets = outputs_tracing
+            .iter()
+            .map(|&traced| PayeeSecret {
+                amount: 0,
+                blind: bls_zero,
+                asset_type: bls_zero,
+                tracer: if traced {
+                    Some(TracerSecret {
+                        enc_key: ElGamalEncKey(JubjubPoint::get_base()),
+                        rand: jubjub_zero,
+                    })
+                } else {
+                    None
+                },
+            })
+            .collect();
 
         AMultiXfrWitness {
             payers_secrets: vec![payer_secret; n_payers],
-            payees_secrets: vec![payee_secret; n_payees],
+            payees_secrets,
+            public_outputs: vec![],
+            fee_amount: 0,
+            fee_asset_type: bls_zero,
+            padded: false,
         }
     }
 }
@@ -82,7 +160,11 @@ impl AMultiXfrWitness {
 pub(crate) struct AMultiXfrPubInputs {
     pub payers_inputs: Vec<(Nullifier, AXfrPubKey)>,
     pub payees_commitments: Vec<Commitment>,
+    pub payees_tracer_memos: Vec<Option<AXfrTracerMemo>>,
     pub merkle_root: BLSScalar,
+    pub public_outputs: Vec<PublicOutput>,
+    pub fee_amount: u64,
+    pub fee_asset_type: BLSScalar,
 }
 
 impl AMultiXfrPubInputs {
@@ -96,10 +178,30 @@ impl AMultiXfrPubInputs {
         }
         // merkle_root
         result.push(self.merkle_root);
-        // output commitments
-        for comm in &self.payees_commitments {
+        // output commitments, followed by the tracer key and ciphertext of traced outputs
+        for (comm, tracer_memo) in self
+            .payees_commitments
+            .iter()
+            .zip(self.payees_tracer_memos.iter())
+        {
             result.push(*comm);
+            if let Some(memo) = tracer_memo {
+                result.push(memo.enc_key.get_point_ref().get_x());
+                result.push(memo.enc_key.get_point_ref().get_y());
+                result.push(memo.lock.e1.get_x());
+                result.push(memo.lock.e1.get_y());
+                result.extend_from_slice(&memo.lock.symm_ctxts);
+            }
+        }
+        // public outputs
+        for output in &self.public_outputs {
+            result.push(BLSScalar::from_u64(output.amount));
+            result.push(output.asset_type);
+            result.push(output.owner);
         }
+        // public fee
+        result.push(BLSScalar::from_u64(self.fee_amount));
+        result.push(self.fee_asset_type);
         result
     }
 
@@ -149,6 +251,23 @@ impl AMultiXfrPubInputs {
             })
             .collect();
 
+        // tracer memos
+        let payees_tracer_memos: Vec<Option<AXfrTracerMemo>> = witness
+            .payees_secrets
+            .iter()
+            .map(|sec| {
+                sec.tracer.as_ref().map(|tracer| AXfrTracerMemo {
+                    enc_key: tracer.enc_key.clone(),
+                    lock: native_hybrid_encrypt(
+                        &base,
+                        &tracer.enc_key,
+                        &tracer.rand,
+                        &[BLSScalar::from_u64(sec.amount), sec.asset_type],
+                    ),
+                })
+            })
+            .collect();
+
         // merkle root
         let hash = RescueInstance::new();
         let payer = &witness.payers_secrets[0];
@@ -180,7 +299,11 @@ impl AMultiXfrPubInputs {
         Self {
             payers_inputs,
             payees_commitments,
+            payees_tracer_memos,
             merkle_root: node,
+            public_outputs: witness.public_outputs.clone(),
+            fee_amount: witness.fee_amount,
+            fee_asset_type: witness.fee_asset_type,
         }
     }
 }
@@ -188,6 +311,9 @@ impl AMultiXfrPubInputs {
 /// Returns the constraint system (and associated number of constraints) for a multi-inputs/outputs transaction.
 /// A prover can provide honest `secret_inputs` and obtain the cs witness by calling `cs.get_and_clear_witness()`.
 /// One provide an empty secret_inputs to get the constraint system `cs` for verification only.
+/// If `secret_inputs.padded` is true, every input but the first one can be a zero-amount dummy input
+/// whose Merkle path is not checked.
+/// The public outputs and the fee are public inputs, they are paid from the inputs of the same asset type.
 pub(crate) fn build_multi_xfr_cs(
     secret_inputs: AMultiXfrWitness,
 ) -> (TurboPlonkCS, usize) {
@@ -204,7 +330,10 @@ pub(crate) fn build_multi_xfr_cs(
     let one = BLSScalar::one();
     let zero_var = cs.zero_var();
     let mut root_var: Option<VarIndex> = None;
-    for payer in &payers_secrets {
+    for (payer, secret) in payers_secrets
+        .iter()
+        .zip(secret_inputs.payers_secrets.iter())
+    {
         // prove knowledge of payer's secret key: pk = base^{sk}
         let (pk_var, pk_point) = cs.scalar_mul(base.clone(), payer.sec_key, SK_LEN);
         let pk_x = pk_var.get_x();
@@ -245,7 +374,19 @@ pub(crate) fn build_multi_xfr_cs(
         let tmp_root_var = compute_merkle_root(&mut cs, acc_elem, &payer.path);
 
         if let Some(root) = root_var {
-            cs.equal(root, tmp_root_var);
+            if secret_inputs.padded {
+                // a dummy input has zero amount, its Merkle root is not checked
+                let is_dummy =
+                    cs.new_variable(BLSScalar::from_u32(secret.is_dummy as u32));
+                cs.insert_boolean_gate(is_dummy);
+                cs.insert_mul_gate(is_dummy, payer.amount, zero_var);
+                let one_var = cs.one_var();
+                let is_real = cs.sub(one_var, is_dummy);
+                let diff = cs.sub(root, tmp_root_var);
+                cs.insert_mul_gate(is_real, diff, zero_var);
+            } else {
+                cs.equal(root, tmp_root_var);
+            }
         } else {
             root_var = Some(tmp_root_var);
         }
@@ -257,7 +398,10 @@ pub(crate) fn build_multi_xfr_cs(
     // prepare the publc input for merkle_root
     cs.prepare_io_variable(root_var.unwrap()); // safe unwrap
 
-    for payee in &payees_secrets {
+    for (payee, secret) in payees_secrets
+        .iter()
+        .zip(secret_inputs.payees_secrets.iter())
+    {
         // commitment
         let com_abar_out_var =
             commit(&mut cs, payee.blind, payee.amount, payee.asset_type);
@@ -270,7 +414,40 @@ pub(crate) fn build_multi_xfr_cs(
 
         // prepare the public input for the output commitment
         cs.prepare_io_variable(com_abar_out_var);
+
+        // encrypt amount and asset type to the asset tracer,
+        // the tracer key and the ciphertext are public inputs
+        if let Some(tracer) = &secret.tracer {
+            let pk_point = tracer.enc_key.get_point();
+            let pk_var = cs.new_point_variable(Point::from(&pk_point));
+            let rand_var = cs.new_variable(BLSScalar::from(&tracer.rand));
+            elgamal_hybrid_encrypt(
+                &mut cs,
+                base.clone(),
+                pk_var,
+                pk_point,
+                rand_var,
+                &[payee.amount, payee.asset_type],
+            );
+        }
+    }
+
+    // prepare the public inputs for the public outputs and the fee
+    let mut public_outputs: Vec<(VarIndex, VarIndex)> = vec![];
+    for output in secret_inputs.public_outputs.iter() {
+        let amount_var = cs.new_variable(BLSScalar::from_u64(output.amount));
+        let asset_type_var = cs.new_variable(output.asset_type);
+        let owner_var = cs.new_variable(output.owner);
+        cs.prepare_io_variable(amount_var);
+        cs.prepare_io_variable(asset_type_var);
+        cs.prepare_io_variable(owner_var);
+        public_outputs.push((asset_type_var, amount_var));
     }
+    let fee_amount_var = cs.new_variable(BLSScalar::from_u64(secret_inputs.fee_amount));
+    let fee_type_var = cs.new_variable(secret_inputs.fee_asset_type);
+    cs.prepare_io_variable(fee_amount_var);
+    cs.prepare_io_variable(fee_type_var);
+    public_outputs.push((fee_type_var, fee_amount_var));
 
     // add asset-mixing constraints
     let inputs: Vec<(VarIndex, VarIndex)> = payers_secrets
@@ -281,7 +458,7 @@ pub(crate) fn build_multi_xfr_cs(
         .into_iter()
         .map(|payee| (payee.asset_type, payee.amount))
         .collect();
-    asset_mixing(&mut cs, &inputs, &outputs);
+    asset_mixing(&mut cs, &inputs, &outputs, &public_outputs);
 
     // pad the number of constraints to power of two
     cs.pad();
@@ -343,6 +520,65 @@ pub(crate) fn build_eq_committed_vals_cs(
     (cs, n_constraints)
 }
 
+/// Returns the constraint system (and associated number of constraints) for the issuance of
+/// `amount` units of `asset_type` into the Rescue commitment with blinding `blind`.
+/// The public inputs are the commitment, the asset type, a flag set to one if the amount is
+/// public, and the amount if public (zero otherwise).
+pub(crate) fn build_anon_issuance_cs(
+    amount: BLSScalar,
+    asset_type: BLSScalar,
+    blind: BLSScalar,
+    amount_is_public: bool,
+) -> (TurboPlonkCS, usize) {
+    let mut cs = TurboPlonkConstraintSystem::new();
+    anon_issuance_constraints(&mut cs, amount, asset_type, blind, amount_is_public);
+
+    // pad the number of constraints to power of two
+    cs.pad();
+
+    let n_constraints = cs.size;
+    (cs, n_constraints)
+}
+
+/// Adds the constraints and public inputs of the issuance circuit to `cs`.
+/// Returns the variable of the public amount.
+fn anon_issuance_constraints(
+    cs: &mut TurboPlonkCS,
+    amount: BLSScalar,
+    asset_type: BLSScalar,
+    blind: BLSScalar,
+    amount_is_public: bool,
+) -> VarIndex {
+    // add secret inputs
+    let amount_var = cs.new_variable(amount);
+    let at_var = cs.new_variable(asset_type);
+    let blind_var = cs.new_variable(blind);
+    let (is_public, public_amount) = if amount_is_public {
+        (BLSScalar::one(), amount)
+    } else {
+        (BLSScalar::zero(), BLSScalar::zero())
+    };
+    let is_public_var = cs.new_variable(is_public);
+    let public_amount_var = cs.new_variable(public_amount);
+
+    // amount is a 64-bit value, equal to the public amount if the flag is set
+    cs.range_check(amount_var, AMOUNT_LEN);
+    cs.insert_boolean_gate(is_public_var);
+    let diff_var = cs.sub(amount_var, public_amount_var);
+    let zero_var = cs.zero_var();
+    cs.insert_mul_gate(is_public_var, diff_var, zero_var);
+
+    // rescue commitment
+    let comm_var = commit(cs, blind_var, amount_var, at_var);
+
+    // prepare public inputs
+    cs.prepare_io_variable(comm_var);
+    cs.prepare_io_variable(at_var);
+    cs.prepare_io_variable(is_public_var);
+    cs.prepare_io_variable(public_amount_var);
+    public_amount_var
+}
+
 fn add_payers_secrets(
     cs: &mut TurboPlonkCS,
     secrets: &[PayerSecret],
@@ -564,11 +800,20 @@ fn nullify(
 /// 1. Compute [sum_in_1, ..., sum_in_n] from inputs, where sum_in_i = \sum_{j : type_in_j == type_in_i} v_in_j
 /// 2. Similarly, compute [sum_out_1, ..., sum_out_m] from outputs.
 /// 3. Enumerate pair (i \in [n], j \in [m]), check that: (type_in_i != type_out_j) \lor (sum_in_i == sum_out_j)
+/// Public outputs = [(type_pub_1, v_pub_1), ..., (type_pub_k, v_pub_k)], e.g. the fee, are counted as outputs,
+/// except that the asset type of a zero-amount public output does not need to appear in the inputs.
 fn asset_mixing(
     cs: &mut TurboPlonkCS,
     inputs: &[(VarIndex, VarIndex)],
     outputs: &[(VarIndex, VarIndex)],
+    public_outputs: &[(VarIndex, VarIndex)],
 ) {
+    // the public outputs are balanced as additional outputs
+    let all_outputs: Vec<(VarIndex, VarIndex)> = outputs
+        .iter()
+        .chain(public_outputs.iter())
+        .cloned()
+        .collect();
     let inputs_type_sum_amounts: Vec<(VarIndex, VarIndex)> = inputs
         .iter()
         .map(|input| {
@@ -586,11 +831,11 @@ fn asset_mixing(
         })
         .collect();
 
-    let outputs_type_sum_amounts: Vec<(VarIndex, VarIndex)> = outputs
+    let outputs_type_sum_amounts: Vec<(VarIndex, VarIndex)> = all_outputs
         .iter()
         .map(|output| {
             let zero_var = cs.zero_var();
-            let sum_var = outputs.iter().fold(zero_var, |sum, other_output| {
+            let sum_var = all_outputs.iter().fold(zero_var, |sum, other_output| {
                 let adder = match_select(
                     cs,
                     output.0,       // asset_type
@@ -618,7 +863,7 @@ fn asset_mixing(
     for &(input_type, _) in inputs {
         // \prod_j (input_type - output_type_j) == 0
         let mut product = cs.one_var();
-        for &(output_type, _) in outputs {
+        for &(output_type, _) in &all_outputs {
             let diff = cs.sub(input_type, output_type);
             product = cs.mul(product, diff);
         }
@@ -635,6 +880,18 @@ fn asset_mixing(
         }
         cs.insert_constant_gate(product, BLSScalar::zero());
     }
+
+    // check that every non-zero public output type appears in the set of input types
+    for &(output_type, output_amount) in public_outputs {
+        // \prod_i (input_type_i - output_type) * output_amount == 0
+        let mut product = cs.one_var();
+        for &(input_type, _) in inputs {
+            let diff = cs.sub(input_type, output_type);
+            product = cs.mul(product, diff);
+        }
+        let zero_var = cs.zero_var();
+        cs.insert_mul_gate(product, output_amount, zero_var);
+    }
 }
 
 // If `type1` == `type2`, returns a variable that equals `val`, otherwise returns a zero variable
@@ -648,7 +905,6 @@ fn match_select(
     cs.mul(is_equal_var, val)
 }
 
-#[allow(dead_code)]
 fn elgamal_hybrid_encrypt(
     cs: &mut TurboPlonkCS,
     base: JubjubPoint,
@@ -732,6 +988,7 @@ pub(crate) mod tests {
                     asset_type,
                     path: MTPath::new(vec![node]),
                     blind: BLSScalar::random(&mut prng),
+                    is_dummy: false,
                 }
             })
             .collect();
@@ -784,12 +1041,17 @@ pub(crate) mod tests {
                 amount,
                 blind: BLSScalar::random(&mut prng),
                 asset_type,
+                tracer: None,
             })
             .collect();
 
         AMultiXfrWitness {
             payers_secrets,
             payees_secrets,
+            public_outputs: vec![],
+            fee_amount: 0,
+            fee_asset_type: zero,
+            padded: false,
         }
     }
 
@@ -892,7 +1154,7 @@ pub(crate) mod tests {
             .map(|(&asset_type, &amount)| (asset_type, amount))
             .collect();
 
-        asset_mixing(&mut cs, &inputs, &outputs);
+        asset_mixing(&mut cs, &inputs, &outputs, &[]);
         let witness = cs.get_and_clear_witness();
         assert!(cs.verify_witness(&witness, &[]).is_err());
 
@@ -944,7 +1206,7 @@ pub(crate) mod tests {
             .map(|(&asset_type, &amount)| (asset_type, amount))
             .collect();
 
-        asset_mixing(&mut cs, &inputs, &outputs);
+        asset_mixing(&mut cs, &inputs, &outputs, &[]);
         let witness = cs.get_and_clear_witness();
         assert!(cs.verify_witness(&witness, &[]).is_ok());
 
@@ -986,7 +1248,7 @@ pub(crate) mod tests {
             .zip(out_amounts.iter())
             .map(|(&asset_type, &amount)| (asset_type, amount))
             .collect();
-        asset_mixing(&mut cs, &inputs, &outputs);
+        asset_mixing(&mut cs, &inputs, &outputs, &[]);
         let witness = cs.get_and_clear_witness();
         assert!(cs.verify_witness(&witness, &[]).is_err());
 
@@ -1025,7 +1287,7 @@ pub(crate) mod tests {
             .zip(out_amounts.iter())
             .map(|(&asset_type, &amount)| (asset_type, amount))
             .collect();
-        asset_mixing(&mut cs, &inputs, &outputs);
+        asset_mixing(&mut cs, &inputs, &outputs, &[]);
         let witness = cs.get_and_clear_witness();
         assert!(cs.verify_witness(&witness, &[]).is_err());
     }
@@ -1066,6 +1328,47 @@ pub(crate) mod tests {
         assert!(cs.verify_witness(&witness, &pub_inputs).is_err());
     }
 
+    #[test]
+    fn test_anon_issuance_cs() {
+        let mut prng = ChaChaRng::from_seed([0u8; 32]);
+        let comm = HashCommitment::new();
+        let amount = BLSScalar::from_u32(71);
+        let asset_type = BLSScalar::from_u32(52);
+        let blind = BLSScalar::random(&mut prng);
+        let hash_comm = comm.commit(&blind, &[amount, asset_type]).unwrap();
+        let zero = BLSScalar::zero();
+        let one = BLSScalar::one();
+
+        // confidential amount
+        let (mut cs, _) = build_anon_issuance_cs(amount, asset_type, blind, false);
+        let witness = cs.get_and_clear_witness();
+        assert!(cs
+            .verify_witness(&witness, &[hash_comm, asset_type, zero, zero])
+            .is_ok());
+        // wrong asset type
+        assert!(cs
+            .verify_witness(&witness, &[hash_comm, one, zero, zero])
+            .is_err());
+
+        // public amount
+        let mut cs = TurboPlonkConstraintSystem::new();
+        let public_amount_var =
+            anon_issuance_constraints(&mut cs, amount, asset_type, blind, true);
+        cs.pad();
+        let mut witness = cs.get_and_clear_witness();
+        assert!(cs
+            .verify_witness(&witness, &[hash_comm, asset_type, one, amount])
+            .is_ok());
+        // the public amount must match the committed one
+        witness[public_amount_var] = BLSScalar::from_u32(70);
+        assert!(cs
+            .verify_witness(
+                &witness,
+                &[hash_comm, asset_type, one, BLSScalar::from_u32(70)]
+            )
+            .is_err());
+    }
+
     #[test]
     fn test_commit() {
         let mut cs = TurboPlonkConstraintSystem::new();
@@ -1331,4 +1634,215 @@ pub(crate) mod tests {
             assert!(verify.is_err());
         }
     }
+
+    #[test]
+    fn test_xfr_cs_with_tracing() {
+        let zero = BLSScalar::zero();
+        let inputs = vec![(30, zero)];
+        let outputs = vec![(13, zero), (17, zero)];
+        let mut secret_inputs =
+            new_multi_xfr_witness_for_test(inputs, outputs, [0u8; 32]);
+        let mut prng = ChaChaRng::from_seed([1u8; 32]);
+        let (_, enc_key) = crypto::basics::elgamal::elgamal_key_gen::<_, JubjubPoint>(
+            &mut prng,
+            &JubjubPoint::get_base(),
+        );
+        // only the second output is traced
+        secret_inputs.payees_secrets[1].tracer = Some(TracerSecret {
+            enc_key,
+            rand: JubjubScalar::random(&mut prng),
+        });
+        let pub_inputs = AMultiXfrPubInputs::from_witness(&secret_inputs);
+        assert!(pub_inputs.payees_tracer_memos[0].is_none());
+        assert!(pub_inputs.payees_tracer_memos[1].is_some());
+
+        let (mut cs, _) = build_multi_xfr_cs(secret_inputs);
+        let witness = cs.get_and_clear_witness();
+        let online_inputs = pub_inputs.to_vec();
+        pnk!(cs.verify_witness(&witness, &online_inputs));
+
+        // a ciphertext that does not encrypt the output amount is rejected
+        let mut bad_pub_inputs = pub_inputs;
+        let memo = bad_pub_inputs.payees_tracer_memos[1].as_mut().unwrap();
+        memo.lock.symm_ctxts[0] = memo.lock.symm_ctxts[0].add(&BLSScalar::one());
+        let bad_online_inputs = bad_pub_inputs.to_vec();
+        assert!(cs.verify_witness(&witness, &bad_online_inputs).is_err());
+    }
+
+    #[test]
+    fn test_xfr_cs_with_padding() {
+        let one = BLSScalar::one();
+        let inputs = vec![(30, one), (0, one), (0, one)];
+        let outputs = vec![(13, one), (17, one), (0, one)];
+        let mut secret_inputs =
+            new_multi_xfr_witness_for_test(inputs, outputs, [0u8; 32]);
+        secret_inputs.padded = true;
+        // the last input is a dummy input with a random Merkle path
+        let mut prng = ChaChaRng::from_seed([1u8; 32]);
+        secret_inputs.payers_secrets[2].is_dummy = true;
+        secret_inputs.payers_secrets[2].path.nodes[0].siblings1 =
+            BLSScalar::random(&mut prng);
+        let pub_inputs = AMultiXfrPubInputs::from_witness(&secret_inputs);
+        let (mut cs, _) = build_multi_xfr_cs(secret_inputs);
+        let witness = cs.get_and_clear_witness();
+        pnk!(cs.verify_witness(&witness, &pub_inputs.to_vec()));
+
+        // the dummy input is not in the accumulator, it cannot be a real input
+        let mut secret_inputs = new_multi_xfr_witness_for_test(
+            vec![(30, one), (0, one), (0, one)],
+            vec![(13, one), (17, one), (0, one)],
+            [0u8; 32],
+        );
+        secret_inputs.padded = true;
+        secret_inputs.payers_secrets[2].path.nodes[0].siblings1 =
+            BLSScalar::random(&mut prng);
+        let pub_inputs = AMultiXfrPubInputs::from_witness(&secret_inputs);
+        let (mut cs, _) = build_multi_xfr_cs(secret_inputs);
+        let witness = cs.get_and_clear_witness();
+        assert!(cs.verify_witness(&witness, &pub_inputs.to_vec()).is_err());
+
+        // a dummy input cannot carry any amount
+        let mut secret_inputs = new_multi_xfr_witness_for_test(
+            vec![(30, one), (0, one), (5, one)],
+            vec![(13, one), (17, one), (5, one)],
+            [0u8; 32],
+        );
+        secret_inputs.padded = true;
+        secret_inputs.payers_secrets[2].is_dummy = true;
+        secret_inputs.payers_secrets[2].path.nodes[0].siblings1 =
+            BLSScalar::random(&mut prng);
+        let pub_inputs = AMultiXfrPubInputs::from_witness(&secret_inputs);
+        let (mut cs, _) = build_multi_xfr_cs(secret_inputs);
+        let witness = cs.get_and_clear_witness();
+        assert!(cs.verify_witness(&witness, &pub_inputs.to_vec()).is_err());
+
+        // padding does not change the number of public inputs
+        let (cs, _) = build_multi_xfr_cs(AMultiXfrWitness::fake(3, 3, 1));
+        let (padded_cs, _) = build_multi_xfr_cs(AMultiXfrWitness::fake_padded(3, 3, 1));
+        assert_eq!(
+            cs.public_vars_witness_indices.len(),
+            padded_cs.public_vars_witness_indices.len()
+        );
+        assert!(padded_cs.size >= cs.size);
+    }
+
+    #[test]
+    fn test_xfr_cs_with_fee() {
+        let zero = BLSScalar::zero();
+        let one = BLSScalar::one();
+        let two = one.add(&one);
+        let check = |inputs: Vec<(u64, BLSScalar)>,
+                     outputs: Vec<(u64, BLSScalar)>,
+                     fee: (u64, BLSScalar)| {
+            let mut secret_inputs =
+                new_multi_xfr_witness_for_test(inputs, outputs, [0u8; 32]);
+            secret_inputs.fee_amount = fee.0;
+            secret_inputs.fee_asset_type = fee.1;
+            let pub_inputs = AMultiXfrPubInputs::from_witness(&secret_inputs);
+            let (mut cs, _) = build_multi_xfr_cs(secret_inputs);
+            let witness = cs.get_and_clear_witness();
+            cs.verify_witness(&witness, &pub_inputs.to_vec())
+        };
+
+        // the fee is subtracted from the inputs of its asset type
+        pnk!(check(
+            vec![(30, zero), (5, one)],
+            vec![(28, zero), (5, one)],
+            (2, zero)
+        ));
+        pnk!(check(
+            vec![(30, zero), (5, one)],
+            vec![(30, zero), (4, one)],
+            (1, one)
+        ));
+        // the fee can use up an asset type
+        pnk!(check(
+            vec![(30, zero), (5, one)],
+            vec![(30, zero)],
+            (5, one)
+        ));
+        // a zero fee can have any asset type
+        pnk!(check(vec![(30, zero)], vec![(30, zero)], (0, two)));
+
+        // unbalanced transfers
+        assert!(check(vec![(30, zero)], vec![(30, zero)], (2, zero)).is_err());
+        assert!(check(vec![(30, zero)], vec![(27, zero)], (2, zero)).is_err());
+        assert!(check(
+            vec![(30, zero), (5, one)],
+            vec![(28, zero), (5, one)],
+            (2, one)
+        )
+        .is_err());
+        // a non-zero fee in an asset type not spent by the inputs
+        assert!(check(vec![(30, zero)], vec![(30, zero)], (2, two)).is_err());
+
+        // the public fee must match the fee in the witness
+        let mut secret_inputs = new_multi_xfr_witness_for_test(
+            vec![(30, zero)],
+            vec![(28, zero)],
+            [0u8; 32],
+        );
+        secret_inputs.fee_amount = 2;
+        let mut pub_inputs = AMultiXfrPubInputs::from_witness(&secret_inputs);
+        let (mut cs, _) = build_multi_xfr_cs(secret_inputs);
+        let witness = cs.get_and_clear_witness();
+        pub_inputs.fee_amount = 3;
+        assert!(cs.verify_witness(&witness, &pub_inputs.to_vec()).is_err());
+    }
+
+    #[test]
+    fn test_xfr_cs_with_public_outputs() {
+        let zero = BLSScalar::zero();
+        let one = BLSScalar::one();
+        let owner = BLSScalar::from_u32(7);
+        let mut secret_inputs = new_multi_xfr_witness_for_test(
+            vec![(30, zero), (5, one)],
+            vec![(20, zero), (4, one)],
+            [0u8; 32],
+        );
+        secret_inputs.public_outputs = vec![
+            PublicOutput {
+                amount: 10,
+                asset_type: zero,
+                owner,
+            },
+            PublicOutput {
+                amount: 1,
+                asset_type: one,
+                owner,
+            },
+        ];
+        let pub_inputs = AMultiXfrPubInputs::from_witness(&secret_inputs);
+        let (mut cs, _) = build_multi_xfr_cs(secret_inputs);
+        let witness = cs.get_and_clear_witness();
+        pnk!(cs.verify_witness(&witness, &pub_inputs.to_vec()));
+
+        // the public outputs are bound to the witness
+        let mut bad_pub_inputs = pub_inputs;
+        bad_pub_inputs.public_outputs[0].owner = one;
+        assert!(cs
+            .verify_witness(&witness, &bad_pub_inputs.to_vec())
+            .is_err());
+        bad_pub_inputs.public_outputs[0].owner = owner;
+        bad_pub_inputs.public_outputs[1].amount = 2;
+        assert!(cs
+            .verify_witness(&witness, &bad_pub_inputs.to_vec())
+            .is_err());
+
+        // the public outputs must balance the inputs
+        let mut secret_inputs = new_multi_xfr_witness_for_test(
+            vec![(30, zero)],
+            vec![(20, zero)],
+            [0u8; 32],
+        );
+        secret_inputs.public_outputs = vec![PublicOutput {
+            amount: 11,
+            asset_type: zero,
+            owner,
+        }];
+        let pub_inputs = AMultiXfrPubInputs::from_witness(&secret_inputs);
+        let (mut cs, _) = build_multi_xfr_cs(secret_inputs);
+        let witness = cs.get_and_clear_witness();
+        assert!(cs.verify_witness(&witness, &pub_inputs.to_vec()).is_err());
+    }
 }
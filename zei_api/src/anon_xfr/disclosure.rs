@@ -0,0 +1,124 @@
+use crate::anon_xfr::keys::AXfrPubKey;
+use crate::anon_xfr::structs::{AnonBlindAssetRecord, OpenAnonBlindAssetRecord};
+use crate::xfr::structs::AssetType;
+use algebra::bls12_381::BLSScalar;
+use algebra::jubjub::JubjubScalar;
+use ruc::*;
+use utils::errors::ZeiError;
+
+/// Disclosure of the amount, asset type and recipient of an anonymous blind asset record.
+/// It reveals the commitment blinding and the key randomization factor of the record, but neither
+/// the spending key of the creator nor the other records of the transfer.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AXfrPaymentDisclosure {
+    pub amount: u64,
+    pub asset_type: AssetType,
+    pub pub_key: AXfrPubKey,
+    blind: BLSScalar,
+    key_rand_factor: JubjubScalar,
+}
+
+impl AXfrPaymentDisclosure {
+    /// Build the disclosure of the record opened by `oabar`
+    pub fn from_oabar(oabar: &OpenAnonBlindAssetRecord) -> AXfrPaymentDisclosure {
+        AXfrPaymentDisclosure {
+            amount: oabar.amount,
+            asset_type: oabar.asset_type,
+            pub_key: oabar.pub_key.clone(),
+            blind: oabar.blind,
+            key_rand_factor: oabar.key_rand_factor,
+        }
+    }
+}
+
+/// Verify that `record` pays `disclosure.amount` units of `disclosure.asset_type` to `disclosure.pub_key`
+pub fn verify_payment_disclosure(
+    record: &AnonBlindAssetRecord,
+    disclosure: &AXfrPaymentDisclosure,
+) -> Result<()> {
+    let oabar = OpenAnonBlindAssetRecord {
+        amount: disclosure.amount,
+        asset_type: disclosure.asset_type,
+        blind: disclosure.blind,
+        pub_key: disclosure.pub_key.clone(),
+        key_rand_factor: disclosure.key_rand_factor,
+        ..Default::default()
+    };
+    if AnonBlindAssetRecord::from_oabar(&oabar) != *record {
+        return Err(eg!(ZeiError::AXfrVerificationError));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::anon_xfr::disclosure::{
+        verify_payment_disclosure, AXfrPaymentDisclosure,
+    };
+    use crate::anon_xfr::keys::AXfrKeyPair;
+    use crate::anon_xfr::structs::{
+        AnonBlindAssetRecord, OpenAnonBlindAssetRecordBuilder,
+    };
+    use crate::xfr::structs::AssetType;
+    use crypto::basics::hybrid_encryption::{XPublicKey, XSecretKey};
+    use rand_chacha::ChaChaRng;
+    use rand_core::SeedableRng;
+    use utils::errors::ZeiError;
+
+    #[test]
+    fn test_payment_disclosure() {
+        let mut prng = ChaChaRng::from_seed([0u8; 32]);
+        let asset_type = AssetType::from_identical_byte(1);
+        let keypair = AXfrKeyPair::generate(&mut prng);
+        let other_keypair = AXfrKeyPair::generate(&mut prng);
+        let enc_key = XPublicKey::from(&XSecretKey::new(&mut prng));
+
+        let oabars = (0..2)
+            .map(|_| {
+                OpenAnonBlindAssetRecordBuilder::new()
+                    .amount(10)
+                    .asset_type(asset_type)
+                    .pub_key(keypair.pub_key())
+                    .finalize(&mut prng, &enc_key)
+                    .unwrap()
+                    .build()
+                    .unwrap()
+            })
+            .collect::<Vec<_>>();
+        let abar = AnonBlindAssetRecord::from_oabar(&oabars[0]);
+
+        let disclosure = AXfrPaymentDisclosure::from_oabar(&oabars[0]);
+        assert_eq!(disclosure.amount, 10);
+        assert_eq!(disclosure.asset_type, asset_type);
+        assert_eq!(disclosure.pub_key, keypair.pub_key());
+        assert!(verify_payment_disclosure(&abar, &disclosure).is_ok());
+
+        // the disclosure survives serialization
+        let disclosure: AXfrPaymentDisclosure =
+            bincode::deserialize(&bincode::serialize(&disclosure).unwrap()).unwrap();
+        assert!(verify_payment_disclosure(&abar, &disclosure).is_ok());
+
+        // the disclosure of another record with the same values does not verify
+        let other = AXfrPaymentDisclosure::from_oabar(&oabars[1]);
+        let err = verify_payment_disclosure(&abar, &other).unwrap_err();
+        err_eq!(ZeiError::AXfrVerificationError, err);
+
+        // wrong amount
+        let mut bad = disclosure.clone();
+        bad.amount = 11;
+        let err = verify_payment_disclosure(&abar, &bad).unwrap_err();
+        err_eq!(ZeiError::AXfrVerificationError, err);
+
+        // wrong asset type
+        let mut bad = disclosure.clone();
+        bad.asset_type = AssetType::from_identical_byte(2);
+        let err = verify_payment_disclosure(&abar, &bad).unwrap_err();
+        err_eq!(ZeiError::AXfrVerificationError, err);
+
+        // wrong recipient
+        let mut bad = disclosure;
+        bad.pub_key = other_keypair.pub_key();
+        let err = verify_payment_disclosure(&abar, &bad).unwrap_err();
+        err_eq!(ZeiError::AXfrVerificationError, err);
+    }
+}
@@ -0,0 +1,201 @@
+use crate::anon_xfr::proofs::{prove_anon_issuance, verify_anon_issuance};
+use crate::anon_xfr::structs::{
+    AnonBlindAssetRecord, OpenAnonBlindAssetRecord, SnarkProof,
+};
+use crate::setup::{NodeParams, UserParams};
+use crate::xfr::sig::{XfrKeyPair, XfrPublicKey, XfrSignature};
+use crate::xfr::structs::{AssetType, OwnerMemo};
+use algebra::bls12_381::BLSScalar;
+use algebra::groups::Scalar;
+use rand_core::{CryptoRng, RngCore};
+use ruc::*;
+use utils::errors::ZeiError;
+
+/// Issuance of new units of an asset directly into an anonymous blind asset record
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AnonIssuanceBody {
+    pub asset_type: AssetType,
+    /// Issued amount, None if the amount is confidential
+    pub amount: Option<u64>,
+    pub output: AnonBlindAssetRecord,
+    pub memo: OwnerMemo,
+    pub proof: SnarkProof,
+}
+
+/// Anonymous issuance body signed by the issuer of the asset type
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AnonIssuanceNote {
+    pub body: AnonIssuanceBody,
+    pub signature: XfrSignature,
+}
+
+/// Generate the body issuing `oabar` into the anonymous pool.
+/// * `params` - User parameters for the issuance proof
+/// * `oabar` - Finalized open record to issue, its owner memo is published in the body
+/// * `amount_is_public` - If true, the issued amount is revealed in the body
+pub fn gen_anon_issuance_body<R: CryptoRng + RngCore>(
+    prng: &mut R,
+    params: &UserParams,
+    oabar: &OpenAnonBlindAssetRecord,
+    amount_is_public: bool,
+) -> Result<AnonIssuanceBody> {
+    let memo = oabar.owner_memo.clone().c(d!(ZeiError::ParameterError))?;
+    let proof = prove_anon_issuance(
+        prng,
+        params,
+        BLSScalar::from_u64(oabar.amount),
+        oabar.asset_type.as_scalar(),
+        oabar.blind,
+        amount_is_public,
+    )
+    .c(d!())?;
+    Ok(AnonIssuanceBody {
+        asset_type: oabar.asset_type,
+        amount: if amount_is_public {
+            Some(oabar.amount)
+        } else {
+            None
+        },
+        output: AnonBlindAssetRecord::from_oabar(oabar),
+        memo,
+        proof,
+    })
+}
+
+/// Generate the anonymous issuance note: body + signature of the issuer.
+/// The issuer signature binds the asset type and, if public, the amount of the issued record.
+pub fn gen_anon_issuance_note<R: CryptoRng + RngCore>(
+    prng: &mut R,
+    params: &UserParams,
+    oabar: &OpenAnonBlindAssetRecord,
+    amount_is_public: bool,
+    issuer_keypair: &XfrKeyPair,
+) -> Result<AnonIssuanceNote> {
+    let body = gen_anon_issuance_body(prng, params, oabar, amount_is_public).c(d!())?;
+    let msg = bincode::serialize(&body)
+        .map_err(|_| ZeiError::SerializationError)
+        .c(d!())?;
+    let signature = issuer_keypair.sign(&msg);
+    Ok(AnonIssuanceNote { body, signature })
+}
+
+/// Verifies that the output of an anonymous issuance body hides the issued asset type and amount
+/// Warning: This function doesn't check that the issuer has signed the body
+pub fn verify_anon_issuance_body(
+    params: &NodeParams,
+    body: &AnonIssuanceBody,
+) -> Result<()> {
+    verify_anon_issuance(
+        params,
+        body.output.amount_type_commitment,
+        body.asset_type.as_scalar(),
+        body.amount.map(BLSScalar::from_u64),
+        &body.proof,
+    )
+    .c(d!(ZeiError::AXfrVerificationError))
+}
+
+/// Verifies an anonymous issuance note: the issuance proof and the signature by `issuer_pub_key`.
+/// The caller is responsible for checking that `issuer_pub_key` is allowed to issue the asset type.
+pub fn verify_anon_issuance_note(
+    params: &NodeParams,
+    note: &AnonIssuanceNote,
+    issuer_pub_key: &XfrPublicKey,
+) -> Result<()> {
+    verify_anon_issuance_body(params, &note.body).c(d!())?;
+    let msg = bincode::serialize(&note.body).c(d!(ZeiError::SerializationError))?;
+    issuer_pub_key.verify(&msg, &note.signature).c(d!())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::anon_xfr::issuance::{gen_anon_issuance_note, verify_anon_issuance_note};
+    use crate::anon_xfr::keys::AXfrKeyPair;
+    use crate::anon_xfr::structs::OpenAnonBlindAssetRecordBuilder;
+    use crate::setup::{NodeParams, UserParams};
+    use crate::xfr::sig::XfrKeyPair;
+    use crate::xfr::structs::AssetType;
+    use crypto::basics::hybrid_encryption::{XPublicKey, XSecretKey};
+    use rand_chacha::ChaChaRng;
+    use rand_core::SeedableRng;
+    use utils::errors::ZeiError;
+
+    #[test]
+    fn test_anon_issuance() {
+        let mut prng = ChaChaRng::from_seed([0u8; 32]);
+        let user_params = UserParams::anon_issuance_params();
+        let node_params = NodeParams::from(UserParams::anon_issuance_params());
+        let issuer_keypair = XfrKeyPair::generate(&mut prng);
+        let other_keypair = XfrKeyPair::generate(&mut prng);
+        let abar_keypair = AXfrKeyPair::generate(&mut prng);
+        let dec_key = XSecretKey::new(&mut prng);
+        let enc_key = XPublicKey::from(&dec_key);
+        let asset_type = AssetType::from_identical_byte(3);
+
+        let oabar = OpenAnonBlindAssetRecordBuilder::new()
+            .amount(100)
+            .asset_type(asset_type)
+            .pub_key(abar_keypair.pub_key())
+            .finalize(&mut prng, &enc_key)
+            .unwrap()
+            .build()
+            .unwrap();
+
+        for amount_is_public in [false, true].iter() {
+            let mut note = gen_anon_issuance_note(
+                &mut prng,
+                &user_params,
+                &oabar,
+                *amount_is_public,
+                &issuer_keypair,
+            )
+            .unwrap();
+            assert_eq!(note.body.amount.is_some(), *amount_is_public);
+            assert!(verify_anon_issuance_note(
+                &node_params,
+                &note,
+                &issuer_keypair.pub_key
+            )
+            .is_ok());
+
+            // only the issuer key verifies the note
+            let err =
+                verify_anon_issuance_note(&node_params, &note, &other_keypair.pub_key)
+                    .unwrap_err();
+            err_eq!(ZeiError::SignatureError, err);
+
+            // the owner opens the issued record
+            let opened = OpenAnonBlindAssetRecordBuilder::from_abar(
+                &note.body.output,
+                note.body.memo.clone(),
+                &abar_keypair,
+                &dec_key,
+            )
+            .unwrap()
+            .build()
+            .unwrap();
+            assert_eq!(opened.get_amount(), 100);
+            assert_eq!(opened.get_asset_type(), asset_type);
+
+            // the record does not hide the claimed asset type
+            note.body.asset_type = AssetType::from_identical_byte(4);
+            let err =
+                verify_anon_issuance_note(&node_params, &note, &issuer_keypair.pub_key)
+                    .unwrap_err();
+            err_eq!(ZeiError::AXfrVerificationError, err);
+            note.body.asset_type = asset_type;
+
+            // the record does not hide the claimed amount
+            if *amount_is_public {
+                note.body.amount = Some(101);
+                let err = verify_anon_issuance_note(
+                    &node_params,
+                    &note,
+                    &issuer_keypair.pub_key,
+                )
+                .unwrap_err();
+                err_eq!(ZeiError::AXfrVerificationError, err);
+            }
+        }
+    }
+}
@@ -1,19 +1,61 @@
+use algebra::groups::{Group, Scalar};
 use algebra::jubjub::{JubjubPoint, JubjubScalar};
+use crypto::basics::elgamal::{elgamal_key_gen, ElGamalDecKey, ElGamalEncKey};
+use crypto::basics::hybrid_encryption::{XPublicKey, XSecretKey};
 use crypto::basics::signatures::schnorr;
 use rand_core::{CryptoRng, RngCore};
 use ruc::*;
+use sha2::{Digest, Sha512};
+use utils::serialization::ZeiFromToBytes;
+
+pub const AXFR_PUB_KEY_LENGTH: usize = 32;
+pub const AXFR_SIGNATURE_LENGTH: usize = 64;
+
+const VIEWING_KEY_DOMAIN: &[u8] = b"Zei AXfr viewing key";
 
 /// Public key used to address an Anonymous records and verify transaction spending it
-#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
 pub struct AXfrPubKey(pub(crate) schnorr::PublicKey<JubjubPoint>);
 
 /// Keypair associated with an Anonymous records. It is used to spending it.
-#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, PartialEq, Eq)]
 pub struct AXfrKeyPair(pub(crate) schnorr::KeyPair<JubjubPoint, JubjubScalar>);
 
-#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+/// Incoming viewing key of anonymous records. It opens the records addressed to the associated
+/// key pair, but it does not grant spending authority.
+/// Its decryption key is derived from the secret key of the key pair, so the owner memos of the
+/// records it opens must be encrypted to `AXfrViewingKey::enc_key`.
+#[derive(Clone, PartialEq, Eq)]
+pub struct AXfrViewingKey {
+    pub(crate) dec_key: XSecretKey,
+    pub(crate) pub_key: AXfrPubKey,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct AXfrSignature(pub(crate) schnorr::Signature<JubjubPoint, JubjubScalar>);
 
+/// Public key of an anonymous transfer asset tracer. Amount and asset type of traced outputs
+/// are encrypted under this key inside the transfer circuit.
+pub type AXfrTracerEncKey = ElGamalEncKey<JubjubPoint>;
+
+/// Secret key of an anonymous transfer asset tracer
+pub type AXfrTracerDecKey = ElGamalDecKey<JubjubScalar>;
+
+/// Key pair of an anonymous transfer asset tracer
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AXfrTracerKeyPair {
+    pub enc_key: AXfrTracerEncKey,
+    pub dec_key: AXfrTracerDecKey,
+}
+
+impl AXfrTracerKeyPair {
+    /// Generate a new anonymous transfer asset tracer key pair
+    pub fn generate<R: CryptoRng + RngCore>(prng: &mut R) -> Self {
+        let (dec_key, enc_key) = elgamal_key_gen(prng, &JubjubPoint::get_base());
+        AXfrTracerKeyPair { enc_key, dec_key }
+    }
+}
+
 impl AXfrKeyPair {
     /// Generate a new signature key pair
     pub fn generate<R: CryptoRng + RngCore>(prng: &mut R) -> AXfrKeyPair {
@@ -38,6 +80,31 @@ impl AXfrKeyPair {
     pub fn sign(&self, msg: &[u8]) -> AXfrSignature {
         AXfrSignature(self.0.sign(msg))
     }
+
+    /// Return the viewing key of the records addressed to this key pair.
+    /// The same key pair always derives the same viewing key
+    pub fn viewing_key(&self) -> AXfrViewingKey {
+        let mut hasher = Sha512::new();
+        hasher.update(VIEWING_KEY_DOMAIN);
+        hasher.update(&self.get_secret_scalar().to_bytes());
+        let dec_key = XSecretKey::zei_from_bytes(&hasher.finalize()[..32]).unwrap(); // safe unwrap
+        AXfrViewingKey {
+            dec_key,
+            pub_key: self.pub_key(),
+        }
+    }
+}
+
+impl AXfrViewingKey {
+    /// Return the public key of the records the viewing key opens
+    pub fn pub_key(&self) -> &AXfrPubKey {
+        &self.pub_key
+    }
+
+    /// Return the key owner memos must be encrypted to
+    pub fn enc_key(&self) -> XPublicKey {
+        XPublicKey::from(&self.dec_key)
+    }
 }
 
 impl AXfrPubKey {
@@ -1,39 +1,63 @@
+use crate::anon_xfr::accumulator::RootHistory;
 use crate::anon_xfr::circuits::{
-    AMultiXfrPubInputs, AMultiXfrWitness, PayeeSecret, PayerSecret,
+    AMultiXfrPubInputs, AMultiXfrWitness, PayeeSecret, PayerSecret, PublicOutput,
+    TracerSecret,
 };
-use crate::anon_xfr::keys::AXfrKeyPair;
-use crate::anon_xfr::proofs::{prove_xfr, verify_xfr};
+use crate::anon_xfr::keys::{
+    AXfrKeyPair, AXfrPubKey, AXfrTracerDecKey, AXfrTracerEncKey, AXfrTracerKeyPair,
+    AXfrViewingKey,
+};
+use crate::anon_xfr::nullifier_set::NullifierSet;
+use crate::anon_xfr::proofs::{batch_verify_xfr, prove_xfr, verify_xfr};
 use crate::anon_xfr::structs::{
-    AXfrBody, AXfrProof, AnonBlindAssetRecord, OpenAnonBlindAssetRecord,
+    AXfrBody, AXfrFee, AXfrNote, AXfrProof, AXfrTracerMemo, AnonBlindAssetRecord,
+    MTLeafInfo, MTNode, MTPath, Nullifier, OpenAnonBlindAssetRecord,
+    OpenAnonBlindAssetRecordBuilder,
 };
 use crate::setup::{NodeParams, UserParams};
-use crate::xfr::structs::{AssetType, OwnerMemo, ASSET_TYPE_LENGTH};
+use crate::xfr::sig::XfrPublicKey;
+use crate::xfr::structs::{
+    AssetType, BlindAssetRecord, OwnerMemo, XfrAmount, XfrAssetType, ASSET_TYPE_LENGTH,
+};
 use algebra::bls12_381::{BLSScalar, BLS_SCALAR_LEN};
-use algebra::groups::{Scalar, ScalarArithmetic};
-use algebra::jubjub::{JubjubScalar, JUBJUB_SCALAR_LEN};
+use algebra::groups::{Group, Scalar, ScalarArithmetic, Zero};
+use algebra::jubjub::{JubjubPoint, JubjubScalar, JUBJUB_SCALAR_LEN};
+use crypto::basics::elgamal::{elgamal_hybrid_decrypt, elgamal_hybrid_encrypt};
 use crypto::basics::hybrid_encryption::{
     hybrid_decrypt_with_x25519_secret_key,
     //hybrid_encrypt_with_x25519_key,
+    XPublicKey,
     XSecretKey,
 };
 use crypto::basics::prf::PRF;
+use digest::Digest;
 use itertools::Itertools;
 use rand_core::{CryptoRng, RngCore};
 use ruc::*;
-use std::collections::HashMap;
+use sha2::Sha512;
+use std::collections::{HashMap, HashSet};
 use utils::errors::ZeiError;
 
+pub mod accumulator;
 pub mod bar_to_from_abar;
 pub(crate) mod circuits;
+pub mod disclosure;
+pub mod issuance;
 pub mod keys;
+pub mod nullifier_set;
 pub(crate) mod proofs;
+pub mod scanner;
 pub mod structs;
 
+const PUBLIC_OUTPUT_OWNER_DOMAIN: &[u8] = b"Anon Xfr public output owner";
+
 /// Build a anonymous transfer structure AXfrBody. It also returns randomized signature keys to sign the transfer,
 /// * `rng` - pseudo-random generator.
 /// * `params` - User parameters
 /// * `inputs` - Open source asset records
 /// * `outputs` - Description of output asset records.
+/// If `params` were generated with padding, zero-amount dummy inputs and outputs are appended to
+/// `inputs` and `outputs`, and the returned keys include the keys of the dummy inputs.
 pub fn gen_anon_xfr_body<R: CryptoRng + RngCore>(
     prng: &mut R,
     params: &UserParams,
@@ -41,22 +65,180 @@ pub fn gen_anon_xfr_body<R: CryptoRng + RngCore>(
     outputs: &[OpenAnonBlindAssetRecord],
     input_keypairs: &[AXfrKeyPair],
 ) -> Result<(AXfrBody, Vec<AXfrKeyPair>)> {
+    gen_anon_xfr_body_with_options(
+        prng,
+        params,
+        inputs,
+        outputs,
+        input_keypairs,
+        &AXfrBodyOptions::default(),
+    )
+    .c(d!())
+}
+
+/// Build a anonymous transfer structure AXfrBody where the amount and asset type of some outputs are
+/// encrypted to asset tracers. It also returns randomized signature keys to sign the transfer,
+/// * `rng` - pseudo-random generator.
+/// * `params` - User parameters, they must have been generated for the tracing flags of `outputs_tracing`.
+///   Tracing is not supported with padded parameters.
+/// * `inputs` - Open source asset records
+/// * `outputs` - Description of output asset records.
+/// * `outputs_tracing` - Asset tracer key of each output, None if the output is not traced
+pub fn gen_anon_xfr_body_with_tracing<R: CryptoRng + RngCore>(
+    prng: &mut R,
+    params: &UserParams,
+    inputs: &[OpenAnonBlindAssetRecord],
+    outputs: &[OpenAnonBlindAssetRecord],
+    input_keypairs: &[AXfrKeyPair],
+    outputs_tracing: &[Option<AXfrTracerEncKey>],
+) -> Result<(AXfrBody, Vec<AXfrKeyPair>)> {
+    gen_anon_xfr_body_with_fee(
+        prng,
+        params,
+        inputs,
+        outputs,
+        input_keypairs,
+        outputs_tracing,
+        &AXfrFee::default(),
+    )
+    .c(d!())
+}
+
+/// Build a anonymous transfer structure AXfrBody paying a public fee. It also returns randomized signature keys to sign the transfer,
+/// * `rng` - pseudo-random generator.
+/// * `params` - User parameters, they must have been generated for the tracing flags of `outputs_tracing`.
+///   Tracing is not supported with padded parameters.
+/// * `inputs` - Open source asset records
+/// * `outputs` - Description of output asset records.
+/// * `outputs_tracing` - Asset tracer key of each output, None if the output is not traced
+/// * `fee` - Public fee, for its asset type the input amount must equal the output amount plus the fee amount
+pub fn gen_anon_xfr_body_with_fee<R: CryptoRng + RngCore>(
+    prng: &mut R,
+    params: &UserParams,
+    inputs: &[OpenAnonBlindAssetRecord],
+    outputs: &[OpenAnonBlindAssetRecord],
+    input_keypairs: &[AXfrKeyPair],
+    outputs_tracing: &[Option<AXfrTracerEncKey>],
+    fee: &AXfrFee,
+) -> Result<(AXfrBody, Vec<AXfrKeyPair>)> {
+    let options = AXfrBodyOptions {
+        outputs_tracing: outputs_tracing.to_vec(),
+        public_outputs: vec![],
+        fee: *fee,
+    };
+    gen_anon_xfr_body_with_options(
+        prng,
+        params,
+        inputs,
+        outputs,
+        input_keypairs,
+        &options,
+    )
+    .c(d!())
+}
+
+/// Parts of an anonymous transfer other than its input and output anonymous records.
+/// The default options trace no output and have no public output and no fee.
+#[derive(Clone, Debug, Default)]
+pub struct AXfrBodyOptions {
+    /// Asset tracer key of each output, None if the output is not traced. Empty if no output
+    /// is traced. Tracing is not supported with padded parameters.
+    pub outputs_tracing: Vec<Option<AXfrTracerEncKey>>,
+    /// Amount, asset type and owner of each public output, that is, of each non-confidential
+    /// BlindAssetRecord paid by the inputs. Public outputs are not supported with padded
+    /// parameters.
+    pub public_outputs: Vec<(u64, AssetType, XfrPublicKey)>,
+    /// Public fee, for its asset type the input amount must equal the output amount plus the fee
+    /// amount
+    pub fee: AXfrFee,
+}
+
+/// Build a anonymous transfer structure AXfrBody with some outputs traced, public outputs or a
+/// public fee. It also returns randomized signature keys to sign the transfer,
+/// * `rng` - pseudo-random generator.
+/// * `params` - User parameters, they must have been generated for the tracing flags of
+///   `options.outputs_tracing` and for `options.public_outputs.len()` public outputs.
+/// * `inputs` - Open source asset records
+/// * `outputs` - Description of output asset records.
+/// * `options` - Tracing keys of the outputs, public outputs and fee of the transfer
+pub fn gen_anon_xfr_body_with_options<R: CryptoRng + RngCore>(
+    prng: &mut R,
+    params: &UserParams,
+    inputs: &[OpenAnonBlindAssetRecord],
+    outputs: &[OpenAnonBlindAssetRecord],
+    input_keypairs: &[AXfrKeyPair],
+    options: &AXfrBodyOptions,
+) -> Result<(AXfrBody, Vec<AXfrKeyPair>)> {
+    let AXfrBodyOptions {
+        outputs_tracing,
+        public_outputs,
+        fee,
+    } = options;
+    let fee = *fee;
+    let mut outputs_tracing = if outputs_tracing.is_empty() {
+        vec![None; outputs.len()]
+    } else {
+        outputs_tracing.clone()
+    };
+    // the fee is paid like a public output
+    let mut public_amounts = public_outputs
+        .iter()
+        .map(|(amount, asset_type, _)| (*amount, *asset_type))
+        .collect_vec();
+    public_amounts.push((fee.amount, fee.asset_type));
+    let public_outputs = public_outputs
+        .iter()
+        .map(|(amount, asset_type, public_key)| BlindAssetRecord {
+            amount: XfrAmount::NonConfidential(*amount),
+            asset_type: XfrAssetType::NonConfidential(*asset_type),
+            public_key: *public_key,
+        })
+        .collect_vec();
+
     // 1. check input correctness
     if inputs.is_empty() || outputs.is_empty() {
         return Err(eg!(ZeiError::AXfrProverParamsError));
     }
+    if outputs.len() != outputs_tracing.len() {
+        return Err(eg!(ZeiError::ParameterError));
+    }
     check_inputs(inputs, input_keypairs).c(d!())?;
-    check_asset_amount(inputs, outputs).c(d!())?;
+    check_asset_amount(inputs, outputs, &public_amounts).c(d!())?;
     check_roots(inputs).c(d!())?;
 
-    // 2. randomize input key pair with open_abar rand key
+    // 2. pad with dummy records if the circuit accepts up to a maximum number of them
+    let n_real_inputs = inputs.len();
+    let (dummy_inputs, dummy_keypairs, dummy_outputs) = match params.padding {
+        Some((max_inputs, max_outputs)) => {
+            // dummy outputs are not traced, padded circuits have no public outputs
+            if outputs_tracing.iter().any(Option::is_some) || !public_outputs.is_empty()
+            {
+                return Err(eg!(ZeiError::AXfrProverParamsError));
+            }
+            gen_dummy_records(
+                prng,
+                &inputs[0],
+                inputs.len(),
+                outputs.len(),
+                max_inputs,
+                max_outputs,
+            )
+            .c(d!())?
+        }
+        None => (vec![], vec![], vec![]),
+    };
+    let inputs = inputs.iter().cloned().chain(dummy_inputs).collect_vec();
+    let outputs = outputs.iter().cloned().chain(dummy_outputs).collect_vec();
+    outputs_tracing.resize(outputs.len(), None);
+
+    // 3. randomize input key pair with open_abar rand key
     let rand_input_keypairs = inputs
         .iter()
-        .zip(input_keypairs.iter())
+        .zip(input_keypairs.iter().chain(dummy_keypairs.iter()))
         .map(|(input, keypair)| keypair.randomize(&input.key_rand_factor))
         .collect_vec();
 
-    // 3. build input witness infos
+    // 4. build input witness infos
     let diversifiers: Vec<JubjubScalar> =
         inputs.iter().map(|_| JubjubScalar::random(prng)).collect();
     let nullifiers_and_signing_keys = inputs
@@ -72,12 +254,13 @@ pub fn gen_anon_xfr_body<R: CryptoRng + RngCore>(
         })
         .collect();
 
-    // 4. build proof
+    // 5. build proof
     let payers_secrets = inputs
         .iter()
         .zip(rand_input_keypairs.iter())
         .zip(diversifiers.iter())
-        .map(|((input, keypair), &diversifier)| {
+        .enumerate()
+        .map(|(i, ((input, keypair), &diversifier))| {
             let mt_leaf_info = input.mt_leaf_info.as_ref().unwrap();
             PayerSecret {
                 sec_key: keypair.get_secret_scalar(),
@@ -87,21 +270,49 @@ pub fn gen_anon_xfr_body<R: CryptoRng + RngCore>(
                 asset_type: input.asset_type.as_scalar(),
                 path: mt_leaf_info.path.clone(),
                 blind: input.blind,
+                is_dummy: i >= n_real_inputs,
             }
         })
         .collect();
-    let payees_secrets = outputs
+    let payees_secrets: Vec<PayeeSecret> = outputs
         .iter()
-        .map(|output| PayeeSecret {
+        .zip(outputs_tracing.iter())
+        .map(|(output, enc_key)| PayeeSecret {
             amount: output.amount,
             blind: output.blind,
             asset_type: output.asset_type.as_scalar(),
+            tracer: enc_key.as_ref().map(|enc_key| TracerSecret {
+                enc_key: enc_key.clone(),
+                rand: JubjubScalar::random(prng),
+            }),
+        })
+        .collect();
+    let tracer_memos = payees_secrets
+        .iter()
+        .map(|payee| {
+            payee.tracer.as_ref().map(|tracer| AXfrTracerMemo {
+                enc_key: tracer.enc_key.clone(),
+                lock: elgamal_hybrid_encrypt(
+                    &JubjubPoint::get_base(),
+                    &tracer.enc_key,
+                    &tracer.rand,
+                    &[BLSScalar::from_u64(payee.amount), payee.asset_type],
+                ),
+            })
         })
         .collect();
 
     let secret_inputs = AMultiXfrWitness {
         payers_secrets,
         payees_secrets,
+        public_outputs: public_outputs
+            .iter()
+            .map(public_output_pub_inputs)
+            .collect::<Result<Vec<PublicOutput>>>()
+            .c(d!())?,
+        fee_amount: fee.amount,
+        fee_asset_type: fee.asset_type.as_scalar(),
+        padded: params.padding.is_some(),
     };
     let proof = prove_xfr(prng, params, secret_inputs).c(d!())?;
 
@@ -129,11 +340,83 @@ pub fn gen_anon_xfr_body<R: CryptoRng + RngCore>(
                 merkle_root: inputs[0].mt_leaf_info.as_ref().unwrap().root,
             },
             owner_memos: out_memos.c(d!())?,
+            tracer_memos,
+            public_outputs,
+            fee,
         },
         diversified_key_pairs,
     ))
 }
 
+/// Generate the zero-amount dummy inputs, with their key pairs, and the zero-amount dummy outputs
+/// padding a transfer of `n_inputs` inputs and `n_outputs` outputs up to `max_inputs` and `max_outputs`.
+/// Dummy records have the asset type of `first_input` so that they do not change the asset mixing,
+/// and dummy inputs claim the Merkle root of `first_input`.
+fn gen_dummy_records<R: CryptoRng + RngCore>(
+    prng: &mut R,
+    first_input: &OpenAnonBlindAssetRecord,
+    n_inputs: usize,
+    n_outputs: usize,
+    max_inputs: usize,
+    max_outputs: usize,
+) -> Result<(
+    Vec<OpenAnonBlindAssetRecord>,
+    Vec<AXfrKeyPair>,
+    Vec<OpenAnonBlindAssetRecord>,
+)> {
+    if n_inputs > max_inputs || n_outputs > max_outputs {
+        return Err(eg!(ZeiError::AXfrProverParamsError));
+    }
+    let mt_leaf_info = first_input
+        .mt_leaf_info
+        .as_ref()
+        .c(d!(ZeiError::ParameterError))?;
+    let zero = BLSScalar::zero();
+    let node = MTNode {
+        siblings1: zero,
+        siblings2: zero,
+        is_left_child: 0,
+        is_right_child: 0,
+    };
+    let dummy_leaf_info = MTLeafInfo {
+        path: MTPath::new(vec![node; mt_leaf_info.path.nodes.len()]),
+        root: mt_leaf_info.root,
+        uid: 0,
+    };
+
+    let mut dummy_inputs = vec![];
+    let mut dummy_keypairs = vec![];
+    for _ in n_inputs..max_inputs {
+        let keypair = AXfrKeyPair::generate(prng);
+        dummy_inputs.push(OpenAnonBlindAssetRecord {
+            amount: 0,
+            asset_type: first_input.asset_type,
+            blind: BLSScalar::random(prng),
+            pub_key: keypair.pub_key(),
+            key_rand_factor: JubjubScalar::random(prng),
+            owner_memo: None,
+            mt_leaf_info: Some(dummy_leaf_info.clone()),
+        });
+        dummy_keypairs.push(keypair);
+    }
+
+    let mut dummy_outputs = vec![];
+    for _ in n_outputs..max_outputs {
+        let pub_key = AXfrKeyPair::generate(prng).pub_key();
+        let enc_key = XPublicKey::from(&XSecretKey::new(prng));
+        let output = OpenAnonBlindAssetRecordBuilder::new()
+            .amount(0)
+            .asset_type(first_input.asset_type)
+            .pub_key(pub_key)
+            .finalize(prng, &enc_key)
+            .c(d!())?
+            .build()
+            .c(d!())?;
+        dummy_outputs.push(output);
+    }
+    Ok((dummy_inputs, dummy_keypairs, dummy_outputs))
+}
+
 /// Verifies an anonymous transfer structure AXfrBody.
 /// * `params` - Verifier parameters
 /// * `body` - Transfer structure to verify
@@ -143,6 +426,66 @@ pub fn verify_anon_xfr_body(
     body: &AXfrBody,
     merkle_root: &BLSScalar,
 ) -> Result<()> {
+    let pub_inputs = anon_xfr_body_pub_inputs(body, merkle_root).c(d!())?;
+    verify_xfr(params, &pub_inputs, &body.proof.snark_proof)
+        .c(d!(ZeiError::AXfrVerificationError))
+}
+
+/// Batch verifies anonymous transfer structures AXfrBody sharing the same verifier parameters.
+/// The PLONK proofs are combined via random linear combination, so that a single pairing check is performed.
+/// * `prng` - pseudo-random generator
+/// * `params` - Verifier parameters
+/// * `bodies` - Transfer structures to verify
+/// * `merkle_roots` - the i-th one is the accumulator state `bodies[i]` is verified against
+/// Returns ZeiError::AXfrVerificationError if any of the bodies is invalid.
+pub fn batch_verify_anon_xfr_bodies<R: CryptoRng + RngCore>(
+    prng: &mut R,
+    params: &NodeParams,
+    bodies: &[&AXfrBody],
+    merkle_roots: &[&BLSScalar],
+) -> Result<()> {
+    if bodies.len() != merkle_roots.len() {
+        return Err(eg!(ZeiError::ParameterError));
+    }
+    let pub_inputs = bodies
+        .iter()
+        .zip(merkle_roots.iter())
+        .map(|(body, merkle_root)| anon_xfr_body_pub_inputs(body, merkle_root))
+        .collect::<Result<Vec<AMultiXfrPubInputs>>>()
+        .c(d!())?;
+    let proofs = bodies
+        .iter()
+        .map(|body| &body.proof.snark_proof)
+        .collect_vec();
+    batch_verify_xfr(prng, params, &pub_inputs, &proofs)
+        .c(d!(ZeiError::AXfrVerificationError))
+}
+
+/// Compute the public inputs of the circuit for a public output.
+/// Returns ZeiError::AXfrVerificationError if the amount or the asset type of `record` is confidential.
+fn public_output_pub_inputs(record: &BlindAssetRecord) -> Result<PublicOutput> {
+    match (&record.amount, &record.asset_type) {
+        (
+            XfrAmount::NonConfidential(amount),
+            XfrAssetType::NonConfidential(asset_type),
+        ) => {
+            let mut hasher = Sha512::new();
+            hasher.update(PUBLIC_OUTPUT_OWNER_DOMAIN);
+            hasher.update(record.public_key.as_bytes());
+            Ok(PublicOutput {
+                amount: *amount,
+                asset_type: asset_type.as_scalar(),
+                owner: BLSScalar::from_hash(hasher),
+            })
+        }
+        _ => Err(eg!(ZeiError::AXfrVerificationError)),
+    }
+}
+
+fn anon_xfr_body_pub_inputs(
+    body: &AXfrBody,
+    merkle_root: &BLSScalar,
+) -> Result<AMultiXfrPubInputs> {
     if *merkle_root != body.proof.merkle_root {
         return Err(eg!(ZeiError::AXfrVerificationError));
     }
@@ -151,13 +494,153 @@ pub fn verify_anon_xfr_body(
         .iter()
         .map(|output| output.amount_type_commitment)
         .collect();
-    let pub_inputs = AMultiXfrPubInputs {
+    if body.tracer_memos.len() != body.outputs.len() {
+        return Err(eg!(ZeiError::AXfrVerificationError));
+    }
+    Ok(AMultiXfrPubInputs {
         payers_inputs: body.inputs.clone(),
         payees_commitments,
+        payees_tracer_memos: body.tracer_memos.clone(),
         merkle_root: *merkle_root,
-    };
-    verify_xfr(params, &pub_inputs, &body.proof.snark_proof)
-        .c(d!(ZeiError::AXfrVerificationError))
+        public_outputs: body
+            .public_outputs
+            .iter()
+            .map(public_output_pub_inputs)
+            .collect::<Result<Vec<PublicOutput>>>()
+            .c(d!())?,
+        fee_amount: body.fee.amount,
+        fee_asset_type: body.fee.asset_type.as_scalar(),
+    })
+}
+
+/// Verifies an anonymous transfer structure AXfrBody against a history of accumulator states.
+/// * `params` - Verifier parameters
+/// * `body` - Transfer structure to verify
+/// * `roots` - accepted states of the accumulator. body.proof.merkle_root must be one of them, otherwise it returns ZeiError::AXfrVerification Error.
+pub fn verify_anon_xfr_body_with_root_history<H: RootHistory + ?Sized>(
+    params: &NodeParams,
+    body: &AXfrBody,
+    roots: &H,
+) -> Result<()> {
+    if !roots.is_known_root(&body.proof.merkle_root) {
+        return Err(eg!(ZeiError::AXfrVerificationError));
+    }
+    verify_anon_xfr_body(params, body, &body.proof.merkle_root).c(d!())
+}
+
+/// Verifies an anonymous transfer structure AXfrBody and records its input nullifiers as spent.
+/// * `params` - Verifier parameters
+/// * `body` - Transfer structure to verify
+/// * `roots` - accepted states of the accumulator
+/// * `nullifier_set` - nullifiers of the already spent records
+/// Returns ZeiError::AXfrDoubleSpendError if an input nullifier is repeated in `body` or already belongs to `nullifier_set`.
+/// `nullifier_set` is only updated if the whole body is valid.
+pub fn verify_and_apply_anon_xfr_body<H, N>(
+    params: &NodeParams,
+    body: &AXfrBody,
+    roots: &H,
+    nullifier_set: &mut N,
+) -> Result<()>
+where
+    H: RootHistory + ?Sized,
+    N: NullifierSet + ?Sized,
+{
+    let nullifiers: Vec<Nullifier> = body
+        .inputs
+        .iter()
+        .map(|(nullifier, _)| *nullifier)
+        .collect();
+    check_nullifiers(&nullifiers, nullifier_set).c(d!())?;
+    verify_anon_xfr_body_with_root_history(params, body, roots).c(d!())?;
+    nullifier_set.insert_batch(&nullifiers).c(d!())
+}
+
+/// Build an anonymous transfer note: the transfer body signed by the diversified key of every input.
+/// * `rng` - pseudo-random generator.
+/// * `params` - User parameters
+/// * `inputs` - Open source asset records
+/// * `outputs` - Description of output asset records.
+/// * `input_keypairs` - Key pairs owning `inputs`
+pub fn gen_anon_xfr_note<R: CryptoRng + RngCore>(
+    prng: &mut R,
+    params: &UserParams,
+    inputs: &[OpenAnonBlindAssetRecord],
+    outputs: &[OpenAnonBlindAssetRecord],
+    input_keypairs: &[AXfrKeyPair],
+) -> Result<AXfrNote> {
+    let (body, diversified_keypairs) =
+        gen_anon_xfr_body(prng, params, inputs, outputs, input_keypairs).c(d!())?;
+    sign_anon_xfr_body(body, &diversified_keypairs).c(d!())
+}
+
+/// Sign an anonymous transfer body with the diversified key pairs returned along with it.
+/// Returns ZeiError::ParameterError if the key pairs do not match the signing keys of `body.inputs`
+pub fn sign_anon_xfr_body(
+    body: AXfrBody,
+    diversified_keypairs: &[AXfrKeyPair],
+) -> Result<AXfrNote> {
+    if body.inputs.len() != diversified_keypairs.len() {
+        return Err(eg!(ZeiError::ParameterError));
+    }
+    for ((_, pub_key), keypair) in body.inputs.iter().zip(diversified_keypairs.iter()) {
+        if keypair.pub_key() != *pub_key {
+            return Err(eg!(ZeiError::ParameterError));
+        }
+    }
+    let msg = bincode::serialize(&body).c(d!(ZeiError::SerializationError))?;
+    let signatures = diversified_keypairs
+        .iter()
+        .map(|keypair| keypair.sign(&msg))
+        .collect();
+    Ok(AXfrNote { body, signatures })
+}
+
+/// Verifies an anonymous transfer note: the transfer body and the signature of every input signing key.
+/// * `params` - Verifier parameters
+/// * `note` - Transfer note to verify
+/// * `merkle_root` - candidate state of the accumulator. It must match note.body.proof.merkle_root.
+/// Warning: This function doesn't check that the input nullifiers are unspent
+pub fn verify_anon_xfr_note(
+    params: &NodeParams,
+    note: &AXfrNote,
+    merkle_root: &BLSScalar,
+) -> Result<()> {
+    verify_anon_xfr_note_signatures(note).c(d!())?;
+    verify_anon_xfr_body(params, &note.body, merkle_root).c(d!())
+}
+
+/// Verifies an anonymous transfer note and records its input nullifiers as spent.
+/// * `params` - Verifier parameters
+/// * `note` - Transfer note to verify
+/// * `roots` - accepted states of the accumulator
+/// * `nullifier_set` - nullifiers of the already spent records
+pub fn verify_and_apply_anon_xfr_note<H, N>(
+    params: &NodeParams,
+    note: &AXfrNote,
+    roots: &H,
+    nullifier_set: &mut N,
+) -> Result<()>
+where
+    H: RootHistory + ?Sized,
+    N: NullifierSet + ?Sized,
+{
+    verify_anon_xfr_note_signatures(note).c(d!())?;
+    verify_and_apply_anon_xfr_body(params, &note.body, roots, nullifier_set).c(d!())
+}
+
+/// Check that every input signing key signed the note body
+fn verify_anon_xfr_note_signatures(note: &AXfrNote) -> Result<()> {
+    if note.signatures.len() != note.body.inputs.len() {
+        return Err(eg!(ZeiError::SignatureError));
+    }
+    let msg = bincode::serialize(&note.body).c(d!(ZeiError::SerializationError))?;
+    for ((_, pub_key), signature) in note.body.inputs.iter().zip(note.signatures.iter())
+    {
+        pub_key
+            .verify(&msg, signature.clone())
+            .c(d!(ZeiError::SignatureError))?;
+    }
+    Ok(())
 }
 
 /// Check that inputs have mt witness and keypair matched pubkey
@@ -175,10 +658,11 @@ fn check_inputs(
     }
     Ok(())
 }
-/// Check that for each asset type total input amount == total output amount
+/// Check that for each asset type total input amount == total output amount + total public amount
 fn check_asset_amount(
     inputs: &[OpenAnonBlindAssetRecord],
     outputs: &[OpenAnonBlindAssetRecord],
+    public_amounts: &[(u64, AssetType)],
 ) -> Result<()> {
     let mut balances = HashMap::new();
 
@@ -197,6 +681,9 @@ fn check_asset_amount(
             balances.insert(record.asset_type, -(record.amount as i128));
         }
     }
+    for (amount, asset_type) in public_amounts.iter() {
+        *balances.entry(*asset_type).or_insert(0i128) -= *amount as i128;
+    }
 
     for (_, &sum) in balances.iter() {
         if sum != 0i128 {
@@ -207,6 +694,22 @@ fn check_asset_amount(
     Ok(())
 }
 
+/// Check that nullifiers are pairwise distinct and none of them has been spent
+fn check_nullifiers<N: NullifierSet + ?Sized>(
+    nullifiers: &[Nullifier],
+    nullifier_set: &N,
+) -> Result<()> {
+    let mut seen = HashSet::new();
+    for nullifier in nullifiers {
+        if !seen.insert(nullifier.to_bytes())
+            || nullifier_set.contains(nullifier).c(d!())?
+        {
+            return Err(eg!(ZeiError::AXfrDoubleSpendError));
+        }
+    }
+    Ok(())
+}
+
 /// Check that the merkle roots in input asset records are consistent
 /// `inputs` is guaranteed to have at least one asset record
 fn check_roots(inputs: &[OpenAnonBlindAssetRecord]) -> Result<()> {
@@ -240,6 +743,30 @@ pub fn decrypt_memo(
     dec_key: &XSecretKey,
     key_pair: &AXfrKeyPair,
     abar: &AnonBlindAssetRecord,
+) -> Result<(u64, AssetType, BLSScalar, JubjubScalar)> {
+    decrypt_memo_for_pub_key(memo, dec_key, &key_pair.pub_key(), abar).c(d!())
+}
+
+/// Decrypts the owner memo of an anonymous record with a viewing key
+/// * `memo` - Owner memo to decrypt
+/// * `viewing_key` - Viewing key of the record owner
+/// * `abar` - Associated anonymous blind asset record to check memo info against.
+/// Return Error if memo info does not match abar's commitment or public key
+/// Return Ok(amount, asset_type, blinding, key_rand_factor) otherwise
+pub fn decrypt_memo_with_viewing_key(
+    memo: &OwnerMemo,
+    viewing_key: &AXfrViewingKey,
+    abar: &AnonBlindAssetRecord,
+) -> Result<(u64, AssetType, BLSScalar, JubjubScalar)> {
+    decrypt_memo_for_pub_key(memo, &viewing_key.dec_key, &viewing_key.pub_key, abar)
+        .c(d!())
+}
+
+fn decrypt_memo_for_pub_key(
+    memo: &OwnerMemo,
+    dec_key: &XSecretKey,
+    pub_key: &AXfrPubKey,
+    abar: &AnonBlindAssetRecord,
 ) -> Result<(u64, AssetType, BLSScalar, JubjubScalar)> {
     let plaintext = hybrid_decrypt_with_x25519_secret_key(&memo.lock, dec_key);
     if plaintext.len() != 8 + ASSET_TYPE_LENGTH + BLS_SCALAR_LEN + JUBJUB_SCALAR_LEN {
@@ -265,14 +792,93 @@ pub fn decrypt_memo(
         )
         .c(d!())?;
     // verify abar's public key
-    if key_pair.randomize(&rand).pub_key() != abar.public_key {
+    if pub_key.randomize(&rand) != abar.public_key {
         return Err(eg!(ZeiError::InconsistentStructureError));
     }
 
     Ok((amount, asset_type, blind, rand))
 }
 
-fn nullifier(
+/// Scan the outputs of an anonymous transfer for the records opened by `viewing_key`.
+/// Return the opened records together with their position in `body.outputs`
+pub fn scan_anon_xfr_body(
+    body: &AXfrBody,
+    viewing_key: &AXfrViewingKey,
+) -> Vec<(usize, OpenAnonBlindAssetRecord)> {
+    body.outputs
+        .iter()
+        .zip(body.owner_memos.iter())
+        .enumerate()
+        .filter_map(|(i, (output, memo))| {
+            OpenAnonBlindAssetRecordBuilder::from_abar_with_viewing_key(
+                output,
+                memo.clone(),
+                viewing_key,
+            )
+            .and_then(|builder| builder.build())
+            .ok()
+            .map(|oabar| (i, oabar))
+        })
+        .collect()
+}
+
+/// Amount, asset type and owner public key of a traced anonymous output
+pub type AnonRecordData = (u64, AssetType, AXfrPubKey);
+
+/// Scan an anonymous transfer for the outputs traced by `tracer_keypair` and decrypt their amount and asset type.
+/// The asset type is matched against a known list of possible asset types `candidate_asset_types`.
+/// Return Vector of AnonRecordData = (amount, asset_type, public key)
+pub fn trace_anon_xfr_body(
+    body: &AXfrBody,
+    tracer_keypair: &AXfrTracerKeyPair,
+    candidate_asset_types: &[AssetType],
+) -> Result<Vec<AnonRecordData>> {
+    let mut result = vec![];
+    for (output, memo) in body.outputs.iter().zip(body.tracer_memos.iter()) {
+        if let Some(memo) = memo {
+            if memo.enc_key != tracer_keypair.enc_key {
+                continue;
+            }
+            let (amount, asset_type) = decrypt_tracer_memo(
+                memo,
+                &tracer_keypair.dec_key,
+                candidate_asset_types,
+            )
+            .c(d!())?;
+            result.push((amount, asset_type, output.public_key.clone()));
+        }
+    }
+    Ok(result)
+}
+
+/// Decrypts the amount and asset type locked in an anonymous transfer tracer memo
+/// * `memo` - Tracer memo to decrypt
+/// * `dec_key` - Asset tracer decryption key
+/// * `candidate_asset_types` - Asset types the decrypted asset type is matched against
+/// Returns ZeiError::BogusAssetTracerMemo if the memo does not decrypt to an amount and an asset type,
+/// and ZeiError::AssetTracingExtractionError if the asset type is not in `candidate_asset_types`
+pub fn decrypt_tracer_memo(
+    memo: &AXfrTracerMemo,
+    dec_key: &AXfrTracerDecKey,
+    candidate_asset_types: &[AssetType],
+) -> Result<(u64, AssetType)> {
+    let plaintext = elgamal_hybrid_decrypt(dec_key, &memo.lock);
+    if plaintext.len() != 2 {
+        return Err(eg!(ZeiError::BogusAssetTracerMemo));
+    }
+    // amounts are 64 bits values
+    let amount_limbs = plaintext[0].get_little_endian_u64();
+    if amount_limbs.iter().skip(1).any(|&limb| limb != 0) {
+        return Err(eg!(ZeiError::BogusAssetTracerMemo));
+    }
+    let asset_type = candidate_asset_types
+        .iter()
+        .find(|asset_type| asset_type.as_scalar::<BLSScalar>() == plaintext[1])
+        .c(d!(ZeiError::AssetTracingExtractionError))?;
+    Ok((amount_limbs[0], *asset_type))
+}
+
+pub(crate) fn nullifier(
     key_pair: &AXfrKeyPair,
     amount: u64,
     asset_type: &AssetType,
@@ -295,14 +901,25 @@ fn nullifier(
 
 #[cfg(test)]
 mod tests {
-    use crate::anon_xfr::keys::AXfrKeyPair;
+    use crate::anon_xfr::accumulator::{MemoryMTStore, MerkleAccumulator};
+    use crate::anon_xfr::keys::{AXfrKeyPair, AXfrTracerKeyPair};
+    use crate::anon_xfr::nullifier_set::{MemoryNullifierSet, NullifierSet};
     use crate::anon_xfr::structs::{
-        AnonBlindAssetRecord, MTLeafInfo, MTNode, MTPath, OpenAnonBlindAssetRecord,
-        OpenAnonBlindAssetRecordBuilder,
+        AXfrFee, AnonBlindAssetRecord, MTLeafInfo, MTNode, MTPath,
+        OpenAnonBlindAssetRecord, OpenAnonBlindAssetRecordBuilder,
+    };
+    use crate::anon_xfr::{
+        batch_verify_anon_xfr_bodies, gen_anon_xfr_body, gen_anon_xfr_body_with_fee,
+        gen_anon_xfr_body_with_options, gen_anon_xfr_body_with_tracing,
+        gen_anon_xfr_note, scan_anon_xfr_body, sign_anon_xfr_body, trace_anon_xfr_body,
+        verify_and_apply_anon_xfr_body, verify_and_apply_anon_xfr_note,
+        verify_anon_xfr_body, verify_anon_xfr_body_with_root_history,
+        verify_anon_xfr_note, AXfrBodyOptions,
     };
-    use crate::anon_xfr::{gen_anon_xfr_body, verify_anon_xfr_body};
     use crate::setup::{NodeParams, UserParams, DEFAULT_BP_NUM_GENS};
-    use crate::xfr::structs::AssetType;
+    use crate::xfr::asset_record::open_blind_asset_record;
+    use crate::xfr::sig::XfrKeyPair;
+    use crate::xfr::structs::{AssetType, XfrAmount, XfrAssetType};
     use algebra::bls12_381::BLSScalar;
     use algebra::groups::{One, Scalar, ScalarArithmetic, Zero};
     use crypto::basics::hash::rescue::RescueInstance;
@@ -659,6 +1276,809 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_anon_xfr_with_accumulator() {
+        let mut prng = ChaChaRng::from_seed([0u8; 32]);
+        let user_params =
+            UserParams::from_file_if_exists(1, 1, Some(1), DEFAULT_BP_NUM_GENS, None)
+                .unwrap();
+        let amount = 10u64;
+        let asset_type = AssetType::from_identical_byte(0);
+
+        // populate the accumulator with the input record and some other record
+        let mut acc = MerkleAccumulator::new(MemoryMTStore::new(), 1).unwrap();
+        let (other_oabar, _, _, _) = gen_oabar_and_keys(&mut prng, 5, asset_type);
+        acc.add_abar(&AnonBlindAssetRecord::from_oabar(&other_oabar))
+            .unwrap();
+        let (oabar, keypair_in, dec_key_in, _) =
+            gen_oabar_and_keys(&mut prng, amount, asset_type);
+        let abar = AnonBlindAssetRecord::from_oabar(&oabar);
+        let uid = acc.add_abar(&abar).unwrap();
+        assert_eq!(uid, 1);
+
+        let oabar_in = OpenAnonBlindAssetRecordBuilder::from_abar(
+            &abar,
+            oabar.get_owner_memo().unwrap(),
+            &keypair_in,
+            &dec_key_in,
+        )
+        .unwrap()
+        .mt_leaf_info(acc.get_leaf_info(uid).unwrap())
+        .build()
+        .unwrap();
+        let (oabar_out, _, _, _) = gen_oabar_and_keys(&mut prng, amount, asset_type);
+
+        let (body, _) = gen_anon_xfr_body(
+            &mut prng,
+            &user_params,
+            &[oabar_in],
+            &[oabar_out],
+            &[keypair_in],
+        )
+        .unwrap();
+        let verifier_params = NodeParams::from(user_params);
+        let merkle_root = acc.get_root().unwrap();
+        assert!(verify_anon_xfr_body(&verifier_params, &body, &merkle_root).is_ok());
+    }
+
+    #[test]
+    fn test_anon_xfr_with_root_history() {
+        let mut prng = ChaChaRng::from_seed([0u8; 32]);
+        let user_params =
+            UserParams::from_file_if_exists(1, 1, Some(1), DEFAULT_BP_NUM_GENS, None)
+                .unwrap();
+        let amount = 10u64;
+        let asset_type = AssetType::from_identical_byte(0);
+
+        // populate the accumulator with the input record
+        let mut acc =
+            MerkleAccumulator::with_root_history(MemoryMTStore::new(), 1, 2).unwrap();
+        let (oabar, keypair_in, dec_key_in, _) =
+            gen_oabar_and_keys(&mut prng, amount, asset_type);
+        let abar = AnonBlindAssetRecord::from_oabar(&oabar);
+        let uid = acc.add_abar(&abar).unwrap();
+        assert_eq!(uid, 0);
+
+        let oabar_in = OpenAnonBlindAssetRecordBuilder::from_abar(
+            &abar,
+            oabar.get_owner_memo().unwrap(),
+            &keypair_in,
+            &dec_key_in,
+        )
+        .unwrap()
+        .mt_leaf_info(acc.get_leaf_info(uid).unwrap())
+        .build()
+        .unwrap();
+        let (oabar_out, _, _, _) = gen_oabar_and_keys(&mut prng, amount, asset_type);
+
+        let (body, _) = gen_anon_xfr_body(
+            &mut prng,
+            &user_params,
+            &[oabar_in],
+            &[oabar_out],
+            &[keypair_in],
+        )
+        .unwrap();
+        let verifier_params = NodeParams::from(user_params);
+        let merkle_root = acc.get_root().unwrap();
+        assert!(verify_anon_xfr_body(&verifier_params, &body, &merkle_root).is_ok());
+
+        // the proof remains valid while its root is in the accumulator's history
+        let (new_oabar, _, _, _) = gen_oabar_and_keys(&mut prng, 1, asset_type);
+        acc.add_abar(&AnonBlindAssetRecord::from_oabar(&new_oabar))
+            .unwrap();
+        assert!(
+            verify_anon_xfr_body(&verifier_params, &body, &acc.get_root().unwrap())
+                .is_err()
+        );
+        assert!(
+            verify_anon_xfr_body_with_root_history(&verifier_params, &body, &acc)
+                .is_ok()
+        );
+
+        // spending the input records its nullifier, a second spending is rejected
+        let mut nullifier_set = MemoryNullifierSet::new();
+        assert!(verify_and_apply_anon_xfr_body(
+            &verifier_params,
+            &body,
+            &acc,
+            &mut nullifier_set
+        )
+        .is_ok());
+        assert!(nullifier_set.contains(&body.inputs[0].0).unwrap());
+        err_eq!(
+            ZeiError::AXfrDoubleSpendError,
+            verify_and_apply_anon_xfr_body(
+                &verifier_params,
+                &body,
+                &acc,
+                &mut nullifier_set
+            )
+            .unwrap_err()
+        );
+
+        acc.add_abar(&AnonBlindAssetRecord::from_oabar(&new_oabar))
+            .unwrap();
+        assert!(
+            verify_anon_xfr_body_with_root_history(&verifier_params, &body, &acc)
+                .is_err()
+        );
+        // an invalid body does not update the nullifier set
+        let mut nullifier_set = MemoryNullifierSet::new();
+        assert!(verify_and_apply_anon_xfr_body(
+            &verifier_params,
+            &body,
+            &acc,
+            &mut nullifier_set
+        )
+        .is_err());
+        assert!(nullifier_set.is_empty());
+        // repeated nullifiers inside a body are rejected
+        let mut dup_body = body.clone();
+        dup_body.inputs.push(dup_body.inputs[0].clone());
+        err_eq!(
+            ZeiError::AXfrDoubleSpendError,
+            verify_and_apply_anon_xfr_body(
+                &verifier_params,
+                &dup_body,
+                &acc,
+                &mut nullifier_set
+            )
+            .unwrap_err()
+        );
+    }
+
+    #[test]
+    fn test_anon_xfr_with_fee() {
+        let mut prng = ChaChaRng::from_seed([0u8; 32]);
+        let user_params =
+            UserParams::from_file_if_exists(2, 1, Some(1), DEFAULT_BP_NUM_GENS, None)
+                .unwrap();
+        let asset_type = AssetType::from_identical_byte(0);
+        let fee_asset_type = AssetType::from_identical_byte(1);
+
+        let mut acc = MerkleAccumulator::new(MemoryMTStore::new(), 1).unwrap();
+        let mut inputs = vec![];
+        let mut keypairs = vec![];
+        for (amount, asset_type) in [(10u64, asset_type), (3u64, fee_asset_type)].iter()
+        {
+            let (oabar, keypair, dec_key, _) =
+                gen_oabar_and_keys(&mut prng, *amount, *asset_type);
+            let abar = AnonBlindAssetRecord::from_oabar(&oabar);
+            let uid = acc.add_abar(&abar).unwrap();
+            inputs.push((abar, oabar.get_owner_memo().unwrap(), uid, dec_key));
+            keypairs.push(keypair);
+        }
+        let inputs = inputs
+            .iter()
+            .zip(keypairs.iter())
+            .map(|((abar, owner_memo, uid, dec_key), keypair)| {
+                OpenAnonBlindAssetRecordBuilder::from_abar(
+                    abar,
+                    owner_memo.clone(),
+                    keypair,
+                    dec_key,
+                )
+                .unwrap()
+                .mt_leaf_info(acc.get_leaf_info(*uid).unwrap())
+                .build()
+                .unwrap()
+            })
+            .collect_vec();
+        let (oabar_out, _, _, _) = gen_oabar_and_keys(&mut prng, 10, asset_type);
+        let fee = AXfrFee {
+            amount: 3,
+            asset_type: fee_asset_type,
+        };
+
+        // the outputs and the fee must balance the inputs
+        let mut bad_fee = fee;
+        bad_fee.amount = 2;
+        err_eq!(
+            ZeiError::XfrCreationAssetAmountError,
+            gen_anon_xfr_body_with_fee(
+                &mut prng,
+                &user_params,
+                &inputs,
+                &[oabar_out.clone()],
+                &keypairs,
+                &[None],
+                &bad_fee,
+            )
+            .unwrap_err()
+        );
+
+        let (body, _) = gen_anon_xfr_body_with_fee(
+            &mut prng,
+            &user_params,
+            &inputs,
+            &[oabar_out],
+            &keypairs,
+            &[None],
+            &fee,
+        )
+        .unwrap();
+        assert_eq!(body.fee, fee);
+        let verifier_params = NodeParams::from(user_params);
+        let merkle_root = acc.get_root().unwrap();
+        assert!(verify_anon_xfr_body(&verifier_params, &body, &merkle_root).is_ok());
+
+        // the fee is bound to the proof
+        let mut bad_body = body.clone();
+        bad_body.fee.amount = 2;
+        assert!(
+            verify_anon_xfr_body(&verifier_params, &bad_body, &merkle_root).is_err()
+        );
+        let mut bad_body = body;
+        bad_body.fee.asset_type = asset_type;
+        assert!(
+            verify_anon_xfr_body(&verifier_params, &bad_body, &merkle_root).is_err()
+        );
+    }
+
+    #[test]
+    fn test_anon_xfr_with_public_outputs() {
+        let mut prng = ChaChaRng::from_seed([0u8; 32]);
+        let user_params =
+            UserParams::new_with_public_outputs(1, 1, 1, Some(1), DEFAULT_BP_NUM_GENS);
+        let asset_type = AssetType::from_identical_byte(0);
+
+        let mut acc = MerkleAccumulator::new(MemoryMTStore::new(), 1).unwrap();
+        let (oabar, keypair_in, dec_key_in, _) =
+            gen_oabar_and_keys(&mut prng, 10, asset_type);
+        let abar = AnonBlindAssetRecord::from_oabar(&oabar);
+        let uid = acc.add_abar(&abar).unwrap();
+        let oabar_in = OpenAnonBlindAssetRecordBuilder::from_abar(
+            &abar,
+            oabar.get_owner_memo().unwrap(),
+            &keypair_in,
+            &dec_key_in,
+        )
+        .unwrap()
+        .mt_leaf_info(acc.get_leaf_info(uid).unwrap())
+        .build()
+        .unwrap();
+        let (oabar_change, _, _, _) = gen_oabar_and_keys(&mut prng, 5, asset_type);
+        let recipient = XfrKeyPair::generate(&mut prng);
+        let fee = AXfrFee {
+            amount: 1,
+            asset_type,
+        };
+
+        // the public outputs must balance the inputs
+        err_eq!(
+            ZeiError::XfrCreationAssetAmountError,
+            gen_anon_xfr_body_with_options(
+                &mut prng,
+                &user_params,
+                &[oabar_in.clone()],
+                &[oabar_change.clone()],
+                &[
+                    bincode::deserialize(&bincode::serialize(&keypair_in).unwrap())
+                        .unwrap()
+                ],
+                &AXfrBodyOptions {
+                    public_outputs: vec![(5, asset_type, recipient.pub_key)],
+                    fee,
+                    ..Default::default()
+                },
+            )
+            .unwrap_err()
+        );
+
+        let (body, _) = gen_anon_xfr_body_with_options(
+            &mut prng,
+            &user_params,
+            &[oabar_in],
+            &[oabar_change],
+            &[keypair_in],
+            &AXfrBodyOptions {
+                public_outputs: vec![(4, asset_type, recipient.pub_key)],
+                fee,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        // the public output is a non-confidential BAR owned by the recipient
+        let obar =
+            open_blind_asset_record(&body.public_outputs[0], &None, &recipient).unwrap();
+        assert_eq!(obar.get_amount(), &4);
+        assert_eq!(obar.get_asset_type(), &asset_type);
+
+        let verifier_params = NodeParams::from(user_params);
+        let merkle_root = acc.get_root().unwrap();
+        assert!(verify_anon_xfr_body(&verifier_params, &body, &merkle_root).is_ok());
+
+        // the amount, the asset type and the owner of a public output are bound to the proof
+        let mut bad_body = body.clone();
+        bad_body.public_outputs[0].amount = XfrAmount::NonConfidential(5);
+        assert!(
+            verify_anon_xfr_body(&verifier_params, &bad_body, &merkle_root).is_err()
+        );
+        let mut bad_body = body.clone();
+        bad_body.public_outputs[0].public_key = XfrKeyPair::generate(&mut prng).pub_key;
+        assert!(
+            verify_anon_xfr_body(&verifier_params, &bad_body, &merkle_root).is_err()
+        );
+        let mut bad_body = body.clone();
+        bad_body.public_outputs.clear();
+        assert!(
+            verify_anon_xfr_body(&verifier_params, &bad_body, &merkle_root).is_err()
+        );
+        // public outputs are non-confidential
+        let mut bad_body = body;
+        bad_body.public_outputs[0].asset_type =
+            XfrAssetType::Confidential(Default::default());
+        err_eq!(
+            ZeiError::AXfrVerificationError,
+            verify_anon_xfr_body(&verifier_params, &bad_body, &merkle_root).unwrap_err()
+        );
+    }
+
+    #[test]
+    fn test_batch_verify_anon_xfr_bodies() {
+        let mut prng = ChaChaRng::from_seed([0u8; 32]);
+        let user_params =
+            UserParams::from_file_if_exists(1, 1, Some(1), DEFAULT_BP_NUM_GENS, None)
+                .unwrap();
+        let asset_type = AssetType::from_identical_byte(0);
+
+        let mut acc = MerkleAccumulator::new(MemoryMTStore::new(), 1).unwrap();
+        let mut inputs = vec![];
+        for amount in 1..4u64 {
+            let (oabar, keypair, dec_key, _) =
+                gen_oabar_and_keys(&mut prng, amount, asset_type);
+            let abar = AnonBlindAssetRecord::from_oabar(&oabar);
+            let uid = acc.add_abar(&abar).unwrap();
+            inputs.push((abar, oabar.get_owner_memo().unwrap(), keypair, dec_key, uid));
+        }
+        let merkle_root = acc.get_root().unwrap();
+
+        let bodies = inputs
+            .into_iter()
+            .map(|(abar, owner_memo, keypair, dec_key, uid)| {
+                let oabar_in = OpenAnonBlindAssetRecordBuilder::from_abar(
+                    &abar, owner_memo, &keypair, &dec_key,
+                )
+                .unwrap()
+                .mt_leaf_info(acc.get_leaf_info(uid).unwrap())
+                .build()
+                .unwrap();
+                let (oabar_out, _, _, _) =
+                    gen_oabar_and_keys(&mut prng, oabar_in.get_amount(), asset_type);
+                gen_anon_xfr_body(
+                    &mut prng,
+                    &user_params,
+                    &[oabar_in],
+                    &[oabar_out],
+                    &[keypair],
+                )
+                .unwrap()
+                .0
+            })
+            .collect_vec();
+        let verifier_params = NodeParams::from(user_params);
+        let bodies_ref = bodies.iter().collect_vec();
+        let roots = vec![&merkle_root; bodies.len()];
+        assert!(batch_verify_anon_xfr_bodies(
+            &mut prng,
+            &verifier_params,
+            &bodies_ref,
+            &roots
+        )
+        .is_ok());
+        assert!(
+            batch_verify_anon_xfr_bodies(&mut prng, &verifier_params, &[], &[]).is_ok()
+        );
+
+        // a single invalid body makes the batch fail
+        let mut bad_body = bodies[1].clone();
+        bad_body.outputs[0] = bodies[2].outputs[0].clone();
+        assert!(
+            verify_anon_xfr_body(&verifier_params, &bad_body, &merkle_root).is_err()
+        );
+        let bad_bodies_ref = vec![&bodies[0], &bad_body, &bodies[2]];
+        err_eq!(
+            ZeiError::AXfrVerificationError,
+            batch_verify_anon_xfr_bodies(
+                &mut prng,
+                &verifier_params,
+                &bad_bodies_ref,
+                &roots
+            )
+            .unwrap_err()
+        );
+
+        // every body is checked against its own root
+        let wrong_root = merkle_root.add(&BLSScalar::one());
+        let wrong_roots = vec![&merkle_root, &merkle_root, &wrong_root];
+        err_eq!(
+            ZeiError::AXfrVerificationError,
+            batch_verify_anon_xfr_bodies(
+                &mut prng,
+                &verifier_params,
+                &bodies_ref,
+                &wrong_roots
+            )
+            .unwrap_err()
+        );
+        err_eq!(
+            ZeiError::ParameterError,
+            batch_verify_anon_xfr_bodies(
+                &mut prng,
+                &verifier_params,
+                &bodies_ref,
+                &roots[..2]
+            )
+            .unwrap_err()
+        );
+    }
+
+    #[test]
+    fn test_anon_xfr_with_tracing() {
+        let mut prng = ChaChaRng::from_seed([0u8; 32]);
+        // the first output is traced, the second is not
+        let user_params = UserParams::new_with_tracing(
+            1,
+            &[true, false],
+            Some(1),
+            DEFAULT_BP_NUM_GENS,
+        );
+        let tracer_keypair = AXfrTracerKeyPair::generate(&mut prng);
+        let other_tracer_keypair = AXfrTracerKeyPair::generate(&mut prng);
+        let asset_type = AssetType::from_identical_byte(0);
+        let other_asset_type = AssetType::from_identical_byte(1);
+
+        let mut acc = MerkleAccumulator::new(MemoryMTStore::new(), 1).unwrap();
+        let (oabar, keypair_in, dec_key_in, _) =
+            gen_oabar_and_keys(&mut prng, 30, asset_type);
+        let abar = AnonBlindAssetRecord::from_oabar(&oabar);
+        let uid = acc.add_abar(&abar).unwrap();
+        let oabar_in = OpenAnonBlindAssetRecordBuilder::from_abar(
+            &abar,
+            oabar.get_owner_memo().unwrap(),
+            &keypair_in,
+            &dec_key_in,
+        )
+        .unwrap()
+        .mt_leaf_info(acc.get_leaf_info(uid).unwrap())
+        .build()
+        .unwrap();
+        let (oabar_out1, _, _, _) = gen_oabar_and_keys(&mut prng, 10, asset_type);
+        let (oabar_out2, _, _, _) = gen_oabar_and_keys(&mut prng, 20, asset_type);
+        let outputs = [oabar_out1, oabar_out2];
+        let inputs = [oabar_in];
+        let keypairs = [keypair_in];
+
+        // tracing keys must match the outputs
+        assert!(gen_anon_xfr_body_with_tracing(
+            &mut prng,
+            &user_params,
+            &inputs,
+            &outputs,
+            &keypairs,
+            &[Some(tracer_keypair.enc_key.clone())],
+        )
+        .is_err());
+        let (body, _) = gen_anon_xfr_body_with_tracing(
+            &mut prng,
+            &user_params,
+            &inputs,
+            &outputs,
+            &keypairs,
+            &[Some(tracer_keypair.enc_key.clone()), None],
+        )
+        .unwrap();
+
+        let verifier_params = NodeParams::from(user_params);
+        let merkle_root = acc.get_root().unwrap();
+        assert!(verify_anon_xfr_body(&verifier_params, &body, &merkle_root).is_ok());
+
+        // tracer scope
+        let records =
+            trace_anon_xfr_body(&body, &tracer_keypair, &[other_asset_type, asset_type])
+                .unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].0, 10);
+        assert_eq!(records[0].1, asset_type);
+        assert_eq!(records[0].2, body.outputs[0].public_key);
+        assert!(
+            trace_anon_xfr_body(&body, &tracer_keypair, &[other_asset_type]).is_err()
+        );
+        assert!(
+            trace_anon_xfr_body(&body, &other_tracer_keypair, &[asset_type])
+                .unwrap()
+                .is_empty()
+        );
+
+        // a tampered tracer memo is rejected
+        let mut bad_body = body;
+        bad_body.tracer_memos[0] = bad_body.tracer_memos[0].clone().map(|mut memo| {
+            memo.lock.symm_ctxts[0].add_assign(&BLSScalar::one());
+            memo
+        });
+        assert!(
+            verify_anon_xfr_body(&verifier_params, &bad_body, &merkle_root).is_err()
+        );
+    }
+
+    #[test]
+    fn test_anon_xfr_note() {
+        let mut prng = ChaChaRng::from_seed([0u8; 32]);
+        let user_params =
+            UserParams::from_file_if_exists(1, 1, Some(1), DEFAULT_BP_NUM_GENS, None)
+                .unwrap();
+        let asset_type = AssetType::from_identical_byte(0);
+
+        let mut acc = MerkleAccumulator::new(MemoryMTStore::new(), 1).unwrap();
+        let (oabar, keypair_in, dec_key_in, _) =
+            gen_oabar_and_keys(&mut prng, 10, asset_type);
+        let abar = AnonBlindAssetRecord::from_oabar(&oabar);
+        let uid = acc.add_abar(&abar).unwrap();
+        let oabar_in = OpenAnonBlindAssetRecordBuilder::from_abar(
+            &abar,
+            oabar.get_owner_memo().unwrap(),
+            &keypair_in,
+            &dec_key_in,
+        )
+        .unwrap()
+        .mt_leaf_info(acc.get_leaf_info(uid).unwrap())
+        .build()
+        .unwrap();
+        let (oabar_out, _, _, _) = gen_oabar_and_keys(&mut prng, 10, asset_type);
+        let (_, _, _, other_enc_key) = gen_oabar_and_keys(&mut prng, 10, asset_type);
+        let inputs = [oabar_in];
+        let outputs = [oabar_out];
+        let keypairs = [keypair_in];
+
+        let note =
+            gen_anon_xfr_note(&mut prng, &user_params, &inputs, &outputs, &keypairs)
+                .unwrap();
+        assert_eq!(note.signatures.len(), 1);
+
+        // the body can only be signed by its diversified input keys
+        let (body, diversified_keypairs) =
+            gen_anon_xfr_body(&mut prng, &user_params, &inputs, &outputs, &keypairs)
+                .unwrap();
+        assert!(sign_anon_xfr_body(body.clone(), &keypairs).is_err());
+        assert!(sign_anon_xfr_body(body.clone(), &[]).is_err());
+        let note2 = sign_anon_xfr_body(body, &diversified_keypairs).unwrap();
+
+        let verifier_params = NodeParams::from(user_params);
+        let merkle_root = acc.get_root().unwrap();
+        assert!(verify_anon_xfr_note(&verifier_params, &note, &merkle_root).is_ok());
+        assert!(verify_anon_xfr_note(&verifier_params, &note2, &merkle_root).is_ok());
+
+        // the owner memos are not bound by the proof but they are signed
+        let mut bad_note = note.clone();
+        bad_note.body.owner_memos[0] = OpenAnonBlindAssetRecordBuilder::new()
+            .amount(10)
+            .asset_type(asset_type)
+            .pub_key(keypairs[0].pub_key())
+            .finalize(&mut prng, &other_enc_key)
+            .unwrap()
+            .build()
+            .unwrap()
+            .get_owner_memo()
+            .unwrap();
+        assert!(
+            verify_anon_xfr_body(&verifier_params, &bad_note.body, &merkle_root).is_ok()
+        );
+        let err =
+            verify_anon_xfr_note(&verifier_params, &bad_note, &merkle_root).unwrap_err();
+        err_eq!(ZeiError::SignatureError, err);
+
+        // signatures cannot be dropped nor reused in another note
+        let mut bad_note = note.clone();
+        bad_note.signatures = vec![];
+        assert!(
+            verify_anon_xfr_note(&verifier_params, &bad_note, &merkle_root).is_err()
+        );
+        let mut bad_note = note.clone();
+        bad_note.signatures = note2.signatures.clone();
+        assert!(
+            verify_anon_xfr_note(&verifier_params, &bad_note, &merkle_root).is_err()
+        );
+
+        // the notes spend the same record
+        let mut nullifier_set = MemoryNullifierSet::new();
+        assert!(verify_and_apply_anon_xfr_note(
+            &verifier_params,
+            &note,
+            &acc,
+            &mut nullifier_set
+        )
+        .is_ok());
+        let err = verify_and_apply_anon_xfr_note(
+            &verifier_params,
+            &note2,
+            &acc,
+            &mut nullifier_set,
+        )
+        .unwrap_err();
+        err_eq!(ZeiError::AXfrDoubleSpendError, err);
+    }
+
+    #[test]
+    fn test_anon_xfr_with_padding() {
+        let mut prng = ChaChaRng::from_seed([0u8; 32]);
+        let user_params =
+            UserParams::new_with_padding(2, 3, Some(1), DEFAULT_BP_NUM_GENS);
+        let asset_type = AssetType::from_identical_byte(0);
+
+        let mut acc = MerkleAccumulator::new(MemoryMTStore::new(), 1).unwrap();
+        let mut inputs = vec![];
+        let mut keypairs = vec![];
+        for amount in [10u64, 20].iter() {
+            let (oabar, keypair, dec_key, _) =
+                gen_oabar_and_keys(&mut prng, *amount, asset_type);
+            let abar = AnonBlindAssetRecord::from_oabar(&oabar);
+            let uid = acc.add_abar(&abar).unwrap();
+            inputs.push((abar, oabar.get_owner_memo().unwrap(), uid, dec_key));
+            keypairs.push(keypair);
+        }
+        let inputs = inputs
+            .into_iter()
+            .zip(keypairs.iter())
+            .map(|((abar, memo, uid, dec_key), keypair)| {
+                OpenAnonBlindAssetRecordBuilder::from_abar(
+                    &abar, memo, keypair, &dec_key,
+                )
+                .unwrap()
+                .mt_leaf_info(acc.get_leaf_info(uid).unwrap())
+                .build()
+                .unwrap()
+            })
+            .collect_vec();
+        let merkle_root = acc.get_root().unwrap();
+
+        // 1 input and 1 output are padded to 2 inputs and 3 outputs
+        let (oabar_out, _, _, _) = gen_oabar_and_keys(&mut prng, 10, asset_type);
+        let keypair_in =
+            bincode::deserialize(&bincode::serialize(&keypairs[0]).unwrap()).unwrap();
+        let (body1, keys1) = gen_anon_xfr_body(
+            &mut prng,
+            &user_params,
+            &inputs[..1],
+            &[oabar_out],
+            &[keypair_in],
+        )
+        .unwrap();
+        assert_eq!(body1.inputs.len(), 2);
+        assert_eq!(body1.outputs.len(), 3);
+        assert_eq!(body1.owner_memos.len(), 3);
+        assert_eq!(body1.tracer_memos.len(), 3);
+        assert_eq!(keys1.len(), 2);
+
+        // 2 inputs and 2 outputs are padded to 2 inputs and 3 outputs
+        let (oabar_out1, _, _, _) = gen_oabar_and_keys(&mut prng, 5, asset_type);
+        let (oabar_out2, _, _, _) = gen_oabar_and_keys(&mut prng, 25, asset_type);
+        let (body2, _) = gen_anon_xfr_body(
+            &mut prng,
+            &user_params,
+            &inputs,
+            &[oabar_out1, oabar_out2],
+            &keypairs,
+        )
+        .unwrap();
+        assert_eq!(body2.inputs.len(), 2);
+        assert_eq!(body2.outputs.len(), 3);
+
+        // transfers larger than the circuit are rejected
+        let mut too_many_outputs = vec![gen_oabar_and_keys(&mut prng, 30, asset_type).0];
+        for _ in 0..3 {
+            too_many_outputs.push(gen_oabar_and_keys(&mut prng, 0, asset_type).0);
+        }
+        assert!(gen_anon_xfr_body(
+            &mut prng,
+            &user_params,
+            &inputs,
+            &too_many_outputs,
+            &keypairs,
+        )
+        .is_err());
+
+        // both transfers share the same verifier parameters
+        let verifier_params = NodeParams::from(user_params);
+        assert!(verify_anon_xfr_body(&verifier_params, &body1, &merkle_root).is_ok());
+        assert!(verify_anon_xfr_body(&verifier_params, &body2, &merkle_root).is_ok());
+        let mut nullifier_set = MemoryNullifierSet::new();
+        assert!(verify_and_apply_anon_xfr_body(
+            &verifier_params,
+            &body1,
+            &acc,
+            &mut nullifier_set
+        )
+        .is_ok());
+        assert_eq!(nullifier_set.len(), 2);
+    }
+
+    #[test]
+    fn test_scan_anon_xfr_body() {
+        let mut prng = ChaChaRng::from_seed([0u8; 32]);
+        let user_params =
+            UserParams::from_file_if_exists(1, 2, Some(1), DEFAULT_BP_NUM_GENS, None)
+                .unwrap();
+        let asset_type = AssetType::from_identical_byte(0);
+
+        let mut acc = MerkleAccumulator::new(MemoryMTStore::new(), 1).unwrap();
+        let (oabar, keypair_in, dec_key_in, _) =
+            gen_oabar_and_keys(&mut prng, 30, asset_type);
+        let abar = AnonBlindAssetRecord::from_oabar(&oabar);
+        let uid = acc.add_abar(&abar).unwrap();
+        let oabar_in = OpenAnonBlindAssetRecordBuilder::from_abar(
+            &abar,
+            oabar.get_owner_memo().unwrap(),
+            &keypair_in,
+            &dec_key_in,
+        )
+        .unwrap()
+        .mt_leaf_info(acc.get_leaf_info(uid).unwrap())
+        .build()
+        .unwrap();
+        // the owner memos of the outputs are encrypted to the viewing keys of their owners
+        let keypair_out1 = AXfrKeyPair::generate(&mut prng);
+        let keypair_out2 = AXfrKeyPair::generate(&mut prng);
+        let viewing_key1 = keypair_out1.viewing_key();
+        let viewing_key2 = keypair_out2.viewing_key();
+        let oabar_out1 = OpenAnonBlindAssetRecordBuilder::new()
+            .amount(10)
+            .asset_type(asset_type)
+            .pub_key(keypair_out1.pub_key())
+            .finalize(&mut prng, &viewing_key1.enc_key())
+            .unwrap()
+            .build()
+            .unwrap();
+        let oabar_out2 = OpenAnonBlindAssetRecordBuilder::new()
+            .amount(20)
+            .asset_type(asset_type)
+            .pub_key(keypair_out2.pub_key())
+            .finalize(&mut prng, &viewing_key2.enc_key())
+            .unwrap()
+            .build()
+            .unwrap();
+
+        let (body, _) = gen_anon_xfr_body(
+            &mut prng,
+            &user_params,
+            &[oabar_in],
+            &[oabar_out1, oabar_out2],
+            &[keypair_in],
+        )
+        .unwrap();
+
+        // the viewing key opens the records addressed to its key pair only
+        assert!(keypair_out2.viewing_key() == viewing_key2);
+        let records = scan_anon_xfr_body(&body, &viewing_key2);
+        assert_eq!(records.len(), 1);
+        let (i, oabar) = &records[0];
+        assert_eq!(*i, 1);
+        assert_eq!(oabar.get_amount(), 20);
+        assert_eq!(oabar.get_asset_type(), asset_type);
+        assert_eq!(oabar.pub_key_ref(), &keypair_out2.pub_key());
+        assert_eq!(AnonBlindAssetRecord::from_oabar(oabar), body.outputs[1]);
+
+        // the opened record is the one opened with the spending key pair
+        let oabar_with_keypair = OpenAnonBlindAssetRecordBuilder::from_abar(
+            &body.outputs[1],
+            body.owner_memos[1].clone(),
+            &keypair_out2,
+            &viewing_key2.dec_key,
+        )
+        .unwrap()
+        .build()
+        .unwrap();
+        assert_eq!(oabar, &oabar_with_keypair);
+
+        // the viewing key of an unrelated key pair opens nothing
+        let other_viewing_key = AXfrKeyPair::generate(&mut prng).viewing_key();
+        assert!(scan_anon_xfr_body(&body, &other_viewing_key).is_empty());
+        let records = scan_anon_xfr_body(&body, &viewing_key1);
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].0, 0);
+        assert_eq!(records[0].1.get_amount(), 10);
+    }
+
     fn gen_keys<R: CryptoRng + RngCore>(
         prng: &mut R,
         n: usize,
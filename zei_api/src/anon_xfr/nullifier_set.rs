@@ -0,0 +1,173 @@
+use crate::anon_xfr::structs::Nullifier;
+use algebra::bls12_381::{BLSScalar, BLS_SCALAR_LEN};
+use algebra::groups::Scalar;
+use ruc::*;
+use std::collections::HashSet;
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Write};
+use std::path::Path;
+use utils::errors::ZeiError;
+
+/// Set of the nullifiers of the spent anonymous blind asset records
+pub trait NullifierSet {
+    /// Return true if `nullifier` has already been recorded
+    fn contains(&self, nullifier: &Nullifier) -> Result<bool>;
+
+    /// Record all `nullifiers`: either all of them are recorded or none is
+    fn insert_batch(&mut self, nullifiers: &[Nullifier]) -> Result<()>;
+}
+
+/// In-memory nullifier set
+#[derive(Debug, Default, Clone)]
+pub struct MemoryNullifierSet {
+    // BLSScalar is not hashable, nullifiers are indexed by their byte representation
+    set: HashSet<Vec<u8>>,
+}
+
+impl MemoryNullifierSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Return the number of recorded nullifiers
+    pub fn len(&self) -> usize {
+        self.set.len()
+    }
+
+    /// Return true if no nullifier has been recorded
+    pub fn is_empty(&self) -> bool {
+        self.set.is_empty()
+    }
+}
+
+impl NullifierSet for MemoryNullifierSet {
+    fn contains(&self, nullifier: &Nullifier) -> Result<bool> {
+        Ok(self.set.contains(&nullifier.to_bytes()))
+    }
+
+    fn insert_batch(&mut self, nullifiers: &[Nullifier]) -> Result<()> {
+        for nullifier in nullifiers {
+            self.set.insert(nullifier.to_bytes());
+        }
+        Ok(())
+    }
+}
+
+/// File-backed nullifier set.
+/// Each batch is appended to the file with a single write as `n (u32) || nullifier_1 || ... || nullifier_n`.
+/// A trailing incomplete batch (e.g. after a crash) is discarded when the file is opened.
+pub struct FileNullifierSet {
+    cache: MemoryNullifierSet,
+    file: File,
+}
+
+impl FileNullifierSet {
+    /// Open the set saved at `path`, creating an empty one if the file does not exist
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let mut file = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .create(true)
+            .open(path)
+            .c(d!(ZeiError::ParameterError))?;
+        let mut contents = vec![];
+        file.read_to_end(&mut contents)
+            .c(d!(ZeiError::ParameterError))?;
+
+        let mut cache = MemoryNullifierSet::new();
+        let mut i = 0;
+        while i + 4 <= contents.len() {
+            let n = utils::u8_le_slice_to_u32(&contents[i..i + 4]) as usize;
+            let end = i + 4 + n * BLS_SCALAR_LEN;
+            if end > contents.len() {
+                break;
+            }
+            let nullifiers = contents[i + 4..end]
+                .chunks(BLS_SCALAR_LEN)
+                .map(|bytes| {
+                    BLSScalar::from_bytes(bytes).c(d!(ZeiError::DeserializationError))
+                })
+                .collect::<Result<Vec<Nullifier>>>()?;
+            cache.insert_batch(&nullifiers).c(d!())?;
+            i = end;
+        }
+        if i != contents.len() {
+            file.set_len(i as u64).c(d!(ZeiError::ParameterError))?;
+        }
+        Ok(FileNullifierSet { cache, file })
+    }
+}
+
+impl NullifierSet for FileNullifierSet {
+    fn contains(&self, nullifier: &Nullifier) -> Result<bool> {
+        self.cache.contains(nullifier)
+    }
+
+    fn insert_batch(&mut self, nullifiers: &[Nullifier]) -> Result<()> {
+        let mut batch = Vec::with_capacity(4 + nullifiers.len() * BLS_SCALAR_LEN);
+        batch.extend_from_slice(&(nullifiers.len() as u32).to_le_bytes());
+        for nullifier in nullifiers {
+            batch.extend_from_slice(&nullifier.to_bytes());
+        }
+        self.file
+            .write_all(&batch)
+            .c(d!(ZeiError::SerializationError))?;
+        self.file.sync_data().c(d!(ZeiError::SerializationError))?;
+        self.cache.insert_batch(nullifiers)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::anon_xfr::nullifier_set::{
+        FileNullifierSet, MemoryNullifierSet, NullifierSet,
+    };
+    use algebra::bls12_381::BLSScalar;
+    use algebra::groups::Scalar;
+    use rand_chacha::ChaChaRng;
+    use rand_core::SeedableRng;
+    use std::io::Write;
+
+    #[test]
+    fn test_file_nullifier_set() {
+        let mut prng = ChaChaRng::from_seed([0u8; 32]);
+        let path = std::env::temp_dir().join("zei_test_file_nullifier_set.bin");
+        let _ = std::fs::remove_file(&path);
+
+        let nullifiers: Vec<BLSScalar> =
+            (0..5).map(|_| BLSScalar::random(&mut prng)).collect();
+        {
+            let mut set = FileNullifierSet::open(&path).unwrap();
+            set.insert_batch(&nullifiers[0..2]).unwrap();
+            set.insert_batch(&nullifiers[2..3]).unwrap();
+            assert!(set.contains(&nullifiers[2]).unwrap());
+            assert!(!set.contains(&nullifiers[3]).unwrap());
+        }
+        // simulate an interrupted write of a batch
+        {
+            let mut file = std::fs::OpenOptions::new()
+                .append(true)
+                .open(&path)
+                .unwrap();
+            file.write_all(&2u32.to_le_bytes()).unwrap();
+            file.write_all(&nullifiers[3].to_bytes()).unwrap();
+        }
+        let mut set = FileNullifierSet::open(&path).unwrap();
+        for nullifier in nullifiers.iter().take(3) {
+            assert!(set.contains(nullifier).unwrap());
+        }
+        assert!(!set.contains(&nullifiers[3]).unwrap());
+        set.insert_batch(&nullifiers[3..]).unwrap();
+
+        let set = FileNullifierSet::open(&path).unwrap();
+        let mut mem_set = MemoryNullifierSet::new();
+        mem_set.insert_batch(&nullifiers).unwrap();
+        assert_eq!(mem_set.len(), 5);
+        for nullifier in nullifiers.iter() {
+            assert!(set.contains(nullifier).unwrap());
+            assert!(mem_set.contains(nullifier).unwrap());
+        }
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
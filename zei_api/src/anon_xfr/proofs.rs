@@ -1,13 +1,15 @@
 use crate::anon_xfr::circuits::{
-    build_eq_committed_vals_cs, build_multi_xfr_cs, AMultiXfrPubInputs, AMultiXfrWitness,
+    build_anon_issuance_cs, build_eq_committed_vals_cs, build_multi_xfr_cs,
+    AMultiXfrPubInputs, AMultiXfrWitness,
 };
 use crate::setup::{NodeParams, UserParams};
 use algebra::bls12_381::BLSScalar;
+use algebra::groups::{One, Zero};
 use algebra::jubjub::JubjubPoint;
 use crypto::basics::commitments::pedersen::PedersenGens;
 use merlin::Transcript;
 use poly_iops::commitments::kzg_poly_com::KZGCommitmentSchemeBLS;
-use poly_iops::plonk::protocol::prover::{prover, verifier, PlonkPf};
+use poly_iops::plonk::protocol::prover::{batch_verifier, prover, verifier, PlonkPf};
 use rand_core::{CryptoRng, RngCore};
 use ruc::*;
 use utils::errors::ZeiError;
@@ -16,6 +18,7 @@ const ANON_XFR_TRANSCRIPT: &[u8] = b"Anon Xfr";
 const N_INPUTS_TRANSCRIPT: &[u8] = b"Number of input ABARs";
 const N_OUTPUTS_TRANSCRIPT: &[u8] = b"Number of output ABARs";
 const EQ_COMM_TRANSCRIPT: &[u8] = b"Equal committed values proof";
+const ANON_ISSUANCE_TRANSCRIPT: &[u8] = b"Anon issuance proof";
 
 pub(crate) type AXfrPlonkPf = PlonkPf<KZGCommitmentSchemeBLS>;
 
@@ -61,13 +64,7 @@ pub(crate) fn verify_xfr(
     pub_inputs: &AMultiXfrPubInputs,
     proof: &AXfrPlonkPf,
 ) -> Result<()> {
-    let mut transcript = Transcript::new(ANON_XFR_TRANSCRIPT);
-    transcript.append_u64(N_INPUTS_TRANSCRIPT, pub_inputs.payers_inputs.len() as u64);
-    transcript.append_u64(
-        N_OUTPUTS_TRANSCRIPT,
-        pub_inputs.payees_commitments.len() as u64,
-    );
-    let online_inputs = pub_inputs.to_vec();
+    let (mut transcript, online_inputs) = xfr_verifier_inputs(pub_inputs);
     verifier(
         &mut transcript,
         &params.pcs,
@@ -79,6 +76,48 @@ pub(crate) fn verify_xfr(
     .c(d!(ZeiError::ZKProofVerificationError))
 }
 
+/// I batch verify the plonk proofs for multi-input/output anonymous transactions.
+/// * `prng` - pseudo-random generator used to combine the proofs.
+/// * `params` - System parameters including KZG params and the constraint system
+/// * `pub_inputs` - the public inputs of each transaction.
+/// * `proofs` - the proofs, in the same order as `pub_inputs`
+pub(crate) fn batch_verify_xfr<R: CryptoRng + RngCore>(
+    prng: &mut R,
+    params: &NodeParams,
+    pub_inputs: &[AMultiXfrPubInputs],
+    proofs: &[&AXfrPlonkPf],
+) -> Result<()> {
+    let (mut transcripts, online_inputs): (Vec<Transcript>, Vec<Vec<BLSScalar>>) =
+        pub_inputs.iter().map(xfr_verifier_inputs).unzip();
+    let online_inputs_ref = online_inputs
+        .iter()
+        .map(|inputs| inputs.as_slice())
+        .collect::<Vec<&[BLSScalar]>>();
+    batch_verifier(
+        prng,
+        &mut transcripts,
+        &params.pcs,
+        &params.cs,
+        &params.verifier_params,
+        &online_inputs_ref,
+        proofs,
+    )
+    .c(d!(ZeiError::ZKProofVerificationError))
+}
+
+/// I return the verifier transcript and the online inputs of a multi-input/output anonymous
+/// transaction proof.
+/// * `pub_inputs` - the public inputs of the transaction.
+fn xfr_verifier_inputs(pub_inputs: &AMultiXfrPubInputs) -> (Transcript, Vec<BLSScalar>) {
+    let mut transcript = Transcript::new(ANON_XFR_TRANSCRIPT);
+    transcript.append_u64(N_INPUTS_TRANSCRIPT, pub_inputs.payers_inputs.len() as u64);
+    transcript.append_u64(
+        N_OUTPUTS_TRANSCRIPT,
+        pub_inputs.payees_commitments.len() as u64,
+    );
+    (transcript, pub_inputs.to_vec())
+}
+
 /// I generates the plonk proof for equality of values in a Pedersen commitment and a Rescue commitment.
 /// * `rng` - pseudo-random generator.
 /// * `params` - System params
@@ -138,6 +177,68 @@ pub(crate) fn verify_eq_committed_vals(
     .c(d!(ZeiError::ZKProofVerificationError))
 }
 
+/// I generate the plonk proof that a Rescue commitment hides `amount` units of a public asset type.
+/// * `rng` - pseudo-random generator.
+/// * `params` - System params
+/// * `amount` - issued amount
+/// * `asset_type` - issued asset type
+/// * `blind` - blinding factor for the Rescue commitment
+/// * `amount_is_public` - whether the amount is a public input of the proof
+/// * Return the plonk proof if the witness is valid, return an error otherwise.
+pub(crate) fn prove_anon_issuance<R: CryptoRng + RngCore>(
+    rng: &mut R,
+    params: &UserParams,
+    amount: BLSScalar,
+    asset_type: BLSScalar,
+    blind: BLSScalar,
+    amount_is_public: bool,
+) -> Result<AXfrPlonkPf> {
+    let mut transcript = Transcript::new(ANON_ISSUANCE_TRANSCRIPT);
+    let (mut cs, _) =
+        build_anon_issuance_cs(amount, asset_type, blind, amount_is_public);
+    let witness = cs.get_and_clear_witness();
+
+    prover(
+        rng,
+        &mut transcript,
+        &params.pcs,
+        &params.cs,
+        &params.prover_params,
+        &witness,
+    )
+    .c(d!(ZeiError::AXfrProofError))
+}
+
+/// I verify the plonk proof that a Rescue commitment hides a public asset type.
+/// * `params` - System parameters including KZG params and the constraint system
+/// * `hash_comm` - the Rescue commitment
+/// * `asset_type` - the issued asset type
+/// * `amount` - the issued amount if public, None otherwise
+/// * `proof` - the proof
+/// * Returns Ok() if the verification succeeds, returns an error otherwise.
+pub(crate) fn verify_anon_issuance(
+    params: &NodeParams,
+    hash_comm: BLSScalar,
+    asset_type: BLSScalar,
+    amount: Option<BLSScalar>,
+    proof: &AXfrPlonkPf,
+) -> Result<()> {
+    let mut transcript = Transcript::new(ANON_ISSUANCE_TRANSCRIPT);
+    let online_inputs = match amount {
+        Some(amount) => vec![hash_comm, asset_type, BLSScalar::one(), amount],
+        None => vec![hash_comm, asset_type, BLSScalar::zero(), BLSScalar::zero()],
+    };
+    verifier(
+        &mut transcript,
+        &params.pcs,
+        &params.cs,
+        &params.verifier_params,
+        &online_inputs,
+        proof,
+    )
+    .c(d!(ZeiError::ZKProofVerificationError))
+}
+
 #[cfg(test)]
 mod tests {
     use crate::anon_xfr::circuits::tests::new_multi_xfr_witness_for_test;
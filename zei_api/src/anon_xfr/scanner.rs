@@ -0,0 +1,367 @@
+use crate::anon_xfr::bar_to_from_abar::{AbarToBarBody, BarToAbarBody};
+use crate::anon_xfr::issuance::AnonIssuanceBody;
+use crate::anon_xfr::keys::AXfrKeyPair;
+use crate::anon_xfr::nullifier;
+use crate::anon_xfr::structs::{
+    AXfrBody, AnonBlindAssetRecord, Nullifier, OpenAnonBlindAssetRecord,
+    OpenAnonBlindAssetRecordBuilder,
+};
+use crate::xfr::structs::{AssetType, OwnerMemo};
+use algebra::groups::Scalar;
+use crypto::basics::hybrid_encryption::XSecretKey;
+use ruc::*;
+use std::collections::{BTreeMap, HashMap};
+use utils::errors::ZeiError;
+
+/// Anonymous record discovered by the scanner
+struct OwnedRecord {
+    oabar: OpenAnonBlindAssetRecord,
+    key_index: usize,
+}
+
+/// Stateful wallet scanner. It consumes the bodies accepted by the ledger, in ledger order,
+/// and keeps track of the unspent anonymous records owned by a set of keys.
+/// The scanner assumes the bodies were verified. Each body is scanned with the uid its first
+/// output got in the ledger accumulator, so that outputs at positions already scanned are
+/// ignored and re-scanning a body neither duplicates records nor shifts the uids.
+pub struct AnonWalletScanner {
+    keys: Vec<(AXfrKeyPair, XSecretKey)>,
+    next_uid: u64,
+    records: BTreeMap<u64, OwnedRecord>,
+    // BLSScalar is not hashable, nullifiers are indexed by their byte representation
+    nullifiers: HashMap<Vec<u8>, u64>,
+}
+
+impl AnonWalletScanner {
+    /// Create a scanner whose first scanned output is the record with uid `next_uid`
+    pub fn new(next_uid: u64) -> Self {
+        AnonWalletScanner {
+            keys: vec![],
+            next_uid,
+            records: BTreeMap::new(),
+            nullifiers: HashMap::new(),
+        }
+    }
+
+    /// Add a spending key pair and the key its owner memos are encrypted to.
+    /// Only the bodies scanned after the key is added are trial-decrypted with it.
+    pub fn add_key(&mut self, key_pair: AXfrKeyPair, dec_key: XSecretKey) {
+        self.keys.push((key_pair, dec_key));
+    }
+
+    /// Return the uid the next scanned output will get
+    pub fn next_uid(&self) -> u64 {
+        self.next_uid
+    }
+
+    /// Scan an anonymous transfer whose first output has uid `first_uid`: spent records are
+    /// removed and owned outputs are recorded.
+    /// Returns ZeiError::ParameterError if outputs before `first_uid` were not scanned
+    pub fn scan_anon_xfr_body(&mut self, body: &AXfrBody, first_uid: u64) -> Result<()> {
+        self.check_position(first_uid).c(d!())?;
+        for (nullifier, _) in body.inputs.iter() {
+            self.spend(nullifier);
+        }
+        for (i, (output, memo)) in
+            body.outputs.iter().zip(body.owner_memos.iter()).enumerate()
+        {
+            self.scan_output(first_uid + i as u64, output, memo);
+        }
+        Ok(())
+    }
+
+    /// Scan a BAR to ABAR conversion whose output has uid `uid`: the output is recorded if
+    /// owned.
+    /// Returns ZeiError::ParameterError if outputs before `uid` were not scanned
+    pub fn scan_bar_to_abar_body(
+        &mut self,
+        body: &BarToAbarBody,
+        uid: u64,
+    ) -> Result<()> {
+        self.check_position(uid).c(d!())?;
+        self.scan_output(uid, &body.output, &body.memo);
+        Ok(())
+    }
+
+    /// Scan an anonymous issuance whose output has uid `uid`: the issued record is recorded if
+    /// owned.
+    /// Returns ZeiError::ParameterError if outputs before `uid` were not scanned
+    pub fn scan_anon_issuance_body(
+        &mut self,
+        body: &AnonIssuanceBody,
+        uid: u64,
+    ) -> Result<()> {
+        self.check_position(uid).c(d!())?;
+        self.scan_output(uid, &body.output, &body.memo);
+        Ok(())
+    }
+
+    /// Scan an ABAR to BAR conversion: the spent record is removed
+    pub fn scan_abar_to_bar_body(&mut self, body: &AbarToBarBody) {
+        self.spend(&body.input.0);
+    }
+
+    /// Return the unspent record with uid `uid`, if owned
+    pub fn get_record(&self, uid: u64) -> Option<&OpenAnonBlindAssetRecord> {
+        self.records.get(&uid).map(|record| &record.oabar)
+    }
+
+    /// Return the key pair spending the unspent record with uid `uid`, if owned
+    pub fn get_key_pair(&self, uid: u64) -> Option<&AXfrKeyPair> {
+        self.records
+            .get(&uid)
+            .map(|record| &self.keys[record.key_index].0)
+    }
+
+    /// Return the unspent owned records ordered by uid
+    pub fn records(&self) -> impl Iterator<Item = (u64, &OpenAnonBlindAssetRecord)> {
+        self.records
+            .iter()
+            .map(|(uid, record)| (*uid, &record.oabar))
+    }
+
+    /// Return the spendable amount of `asset_type`
+    pub fn balance(&self, asset_type: &AssetType) -> u128 {
+        self.records
+            .values()
+            .filter(|record| record.oabar.asset_type == *asset_type)
+            .map(|record| record.oabar.amount as u128)
+            .sum()
+    }
+
+    /// Return the spendable amount of every owned asset type
+    pub fn balances(&self) -> HashMap<AssetType, u128> {
+        let mut balances = HashMap::new();
+        for record in self.records.values() {
+            *balances.entry(record.oabar.asset_type).or_insert(0u128) +=
+                record.oabar.amount as u128;
+        }
+        balances
+    }
+
+    fn spend(&mut self, nullifier: &Nullifier) {
+        if let Some(uid) = self.nullifiers.remove(&nullifier.to_bytes()) {
+            self.records.remove(&uid);
+        }
+    }
+
+    fn check_position(&self, first_uid: u64) -> Result<()> {
+        if first_uid > self.next_uid {
+            return Err(eg!(ZeiError::ParameterError));
+        }
+        Ok(())
+    }
+
+    fn scan_output(
+        &mut self,
+        uid: u64,
+        output: &AnonBlindAssetRecord,
+        memo: &OwnerMemo,
+    ) {
+        // the output at this position was already scanned
+        if uid < self.next_uid {
+            return;
+        }
+        self.next_uid = uid + 1;
+        for (key_index, (key_pair, dec_key)) in self.keys.iter().enumerate() {
+            if let Ok(oabar) = OpenAnonBlindAssetRecordBuilder::from_abar(
+                output,
+                memo.clone(),
+                key_pair,
+                dec_key,
+            )
+            .and_then(|builder| builder.build())
+            {
+                let nullifier = nullifier(
+                    &key_pair.randomize(&oabar.key_rand_factor),
+                    oabar.amount,
+                    &oabar.asset_type,
+                    uid,
+                );
+                self.nullifiers.insert(nullifier.to_bytes(), uid);
+                self.records.insert(uid, OwnedRecord { oabar, key_index });
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::anon_xfr::accumulator::{MemoryMTStore, MerkleAccumulator};
+    use crate::anon_xfr::bar_to_from_abar::gen_bar_to_abar_body;
+    use crate::anon_xfr::gen_anon_xfr_body;
+    use crate::anon_xfr::keys::AXfrKeyPair;
+    use crate::anon_xfr::scanner::AnonWalletScanner;
+    use crate::anon_xfr::structs::OpenAnonBlindAssetRecordBuilder;
+    use crate::setup::{UserParams, DEFAULT_BP_NUM_GENS};
+    use crate::xfr::asset_record::{
+        build_blind_asset_record, open_blind_asset_record, AssetRecordType,
+    };
+    use crate::xfr::sig::XfrKeyPair;
+    use crate::xfr::structs::{AssetRecordTemplate, AssetType};
+    use crypto::basics::commitments::ristretto_pedersen::RistrettoPedersenGens;
+    use crypto::basics::hybrid_encryption::{XPublicKey, XSecretKey};
+    use rand_chacha::ChaChaRng;
+    use rand_core::SeedableRng;
+    use utils::errors::ZeiError;
+
+    #[test]
+    fn test_wallet_scanner() {
+        let mut prng = ChaChaRng::from_seed([0u8; 32]);
+        let pc_gens = RistrettoPedersenGens::default();
+        let asset_type = AssetType::from_identical_byte(1);
+        let other_asset_type = AssetType::from_identical_byte(2);
+
+        let mut scanner = AnonWalletScanner::new(0);
+        let mut acc = MerkleAccumulator::new(MemoryMTStore::new(), 1).unwrap();
+        let abar_keypair = AXfrKeyPair::generate(&mut prng);
+        let dec_key = XSecretKey::new(&mut prng);
+        let enc_key = XPublicKey::from(&dec_key);
+        let abar_keypair2 = AXfrKeyPair::generate(&mut prng);
+        let dec_key2 = XSecretKey::new(&mut prng);
+        let enc_key2 = XPublicKey::from(&dec_key2);
+        let other_keypair = AXfrKeyPair::generate(&mut prng);
+        let other_enc_key = XPublicKey::from(&XSecretKey::new(&mut prng));
+        scanner.add_key(
+            bincode::deserialize(&bincode::serialize(&abar_keypair).unwrap()).unwrap(),
+            dec_key.clone(),
+        );
+        scanner.add_key(
+            bincode::deserialize(&bincode::serialize(&abar_keypair2).unwrap()).unwrap(),
+            dec_key2.clone(),
+        );
+        let (dec_key_copy, dec_key2_copy) = (dec_key, dec_key2);
+
+        // convert two BARs into owned ABARs and one into a foreign ABAR
+        let params = UserParams::eq_committed_vals_params();
+        let bar_keypair = XfrKeyPair::generate(&mut prng);
+        let conversions = [
+            (10u64, asset_type, &abar_keypair, &enc_key),
+            (5u64, other_asset_type, &other_keypair, &other_enc_key),
+            (7u64, other_asset_type, &abar_keypair2, &enc_key2),
+        ];
+        for (amount, asset_type, keypair, enc_key) in conversions.iter() {
+            let ar = AssetRecordTemplate::with_no_asset_tracing(
+                *amount,
+                *asset_type,
+                AssetRecordType::ConfidentialAmount_ConfidentialAssetType,
+                bar_keypair.pub_key,
+            );
+            let (bar, _, memo) =
+                build_blind_asset_record(&mut prng, &pc_gens, &ar, vec![]);
+            let obar = open_blind_asset_record(&bar, &memo, &bar_keypair).unwrap();
+            let body = gen_bar_to_abar_body(&mut prng, &params, &obar, keypair, enc_key)
+                .unwrap();
+            let uid = acc.add_abar(&body.output).unwrap();
+            assert_eq!(uid, scanner.next_uid());
+            scanner.scan_bar_to_abar_body(&body, uid).unwrap();
+        }
+        assert_eq!(scanner.next_uid(), 3);
+        assert_eq!(
+            scanner.records().map(|(uid, _)| uid).collect::<Vec<_>>(),
+            vec![0, 2]
+        );
+        assert!(scanner.get_record(1).is_none());
+        assert_eq!(scanner.get_key_pair(2), Some(&abar_keypair2));
+        assert_eq!(scanner.balance(&asset_type), 10);
+        assert_eq!(scanner.balance(&other_asset_type), 7);
+
+        // spend record 0: 4 back to the wallet, 6 to someone else
+        let mut oabar_in = scanner.get_record(0).unwrap().clone();
+        oabar_in.update_mt_leaf_info(acc.get_leaf_info(0).unwrap());
+        let oabar_out1 = OpenAnonBlindAssetRecordBuilder::new()
+            .amount(4)
+            .asset_type(asset_type)
+            .pub_key(abar_keypair.pub_key())
+            .finalize(&mut prng, &enc_key)
+            .unwrap()
+            .build()
+            .unwrap();
+        let oabar_out2 = OpenAnonBlindAssetRecordBuilder::new()
+            .amount(6)
+            .asset_type(asset_type)
+            .pub_key(other_keypair.pub_key())
+            .finalize(&mut prng, &other_enc_key)
+            .unwrap()
+            .build()
+            .unwrap();
+        let user_params =
+            UserParams::from_file_if_exists(1, 2, Some(1), DEFAULT_BP_NUM_GENS, None)
+                .unwrap();
+        let (body, _) = gen_anon_xfr_body(
+            &mut prng,
+            &user_params,
+            &[oabar_in],
+            &[oabar_out1, oabar_out2],
+            &[abar_keypair],
+        )
+        .unwrap();
+        // the outputs of the transfer are appended after the converted records
+        let first_uid = acc.size().unwrap();
+        scanner.scan_anon_xfr_body(&body, first_uid).unwrap();
+
+        assert_eq!(scanner.next_uid(), 5);
+        assert_eq!(
+            scanner.records().map(|(uid, _)| uid).collect::<Vec<_>>(),
+            vec![2, 3]
+        );
+        assert_eq!(scanner.get_record(3).unwrap().get_amount(), 4);
+        let balances = scanner.balances();
+        assert_eq!(balances.len(), 2);
+        assert_eq!(balances[&asset_type], 4);
+        assert_eq!(balances[&other_asset_type], 7);
+
+        // scanning the same transfer again does not duplicate records or balances
+        scanner.scan_anon_xfr_body(&body, first_uid).unwrap();
+        assert_eq!(scanner.next_uid(), 5);
+        assert_eq!(
+            scanner.records().map(|(uid, _)| uid).collect::<Vec<_>>(),
+            vec![2, 3]
+        );
+        assert_eq!(scanner.balances(), balances);
+
+        // a scanner with the same keys resuming at the transfer finds the same outputs
+        let mut scanner2 = AnonWalletScanner::new(3);
+        scanner2.add_key(
+            bincode::deserialize(
+                &bincode::serialize(scanner.get_key_pair(3).unwrap()).unwrap(),
+            )
+            .unwrap(),
+            dec_key_copy,
+        );
+        scanner2.add_key(
+            bincode::deserialize(&bincode::serialize(&abar_keypair2).unwrap()).unwrap(),
+            dec_key2_copy,
+        );
+        scanner2.scan_anon_xfr_body(&body, first_uid).unwrap();
+        scanner2.scan_anon_xfr_body(&body, first_uid).unwrap();
+        assert_eq!(scanner2.next_uid(), 5);
+        assert_eq!(
+            scanner2.records().map(|(uid, _)| uid).collect::<Vec<_>>(),
+            vec![3]
+        );
+        assert_eq!(scanner2.get_record(3), scanner.get_record(3));
+        assert_eq!(scanner2.balance(&asset_type), 4);
+        assert_eq!(scanner2.balance(&other_asset_type), 0);
+
+        // outputs sharing a commitment get their own uids, as in the accumulator
+        let mut copies = body.clone();
+        copies.outputs[1] = copies.outputs[0].clone();
+        copies.owner_memos[1] = copies.owner_memos[0].clone();
+        scanner.scan_anon_xfr_body(&copies, 5).unwrap();
+        assert_eq!(scanner.next_uid(), 7);
+        assert_eq!(
+            scanner.records().map(|(uid, _)| uid).collect::<Vec<_>>(),
+            vec![2, 3, 5, 6]
+        );
+        assert_eq!(scanner.balance(&asset_type), 12);
+
+        // outputs cannot be skipped
+        err_eq!(
+            ZeiError::ParameterError,
+            scanner.scan_anon_xfr_body(&body, 8).unwrap_err()
+        );
+        assert_eq!(scanner.next_uid(), 7);
+    }
+}
@@ -1,10 +1,13 @@
-use crate::anon_xfr::decrypt_memo;
-use crate::anon_xfr::keys::{AXfrKeyPair, AXfrPubKey};
-use crate::xfr::structs::{AssetType, OwnerMemo};
-use algebra::bls12_381::{BLSScalar, Bls12381};
+use crate::anon_xfr::keys::{
+    AXfrKeyPair, AXfrPubKey, AXfrSignature, AXfrTracerEncKey, AXfrViewingKey,
+};
+use crate::anon_xfr::{decrypt_memo, decrypt_memo_with_viewing_key};
+use crate::xfr::structs::{AssetType, BlindAssetRecord, OwnerMemo};
+use algebra::bls12_381::{BLSScalar, Bls12381, BLS_SCALAR_LEN};
 use algebra::groups::{Scalar, Zero};
-use algebra::jubjub::JubjubScalar;
+use algebra::jubjub::{JubjubPoint, JubjubScalar};
 use crypto::basics::commitments::rescue;
+use crypto::basics::elgamal::ElGamalHybridCiphertext;
 use crypto::basics::hybrid_encryption::{
     hybrid_encrypt_with_x25519_key, XPublicKey, XSecretKey,
 };
@@ -18,6 +21,8 @@ pub type Nullifier = BLSScalar;
 pub type Commitment = BLSScalar;
 pub type BlindFactor = BLSScalar;
 
+pub const MT_NODE_LENGTH: usize = 2 * BLS_SCALAR_LEN + 2;
+
 /// A Merkle tree node which consists of the following:
 /// * `siblings1` - the 1st sibling of the tree node
 /// * `siblings2` - the 2nd sibling of the tree node
@@ -40,6 +45,33 @@ pub struct AXfrBody {
     pub outputs: Vec<AnonBlindAssetRecord>,
     pub proof: AXfrProof,
     pub owner_memos: Vec<OwnerMemo>,
+    pub tracer_memos: Vec<Option<AXfrTracerMemo>>,
+    // non-confidential records paid by the transfer, their owners are bound to the proof
+    pub public_outputs: Vec<BlindAssetRecord>,
+    pub fee: AXfrFee,
+}
+
+/// Public fee paid by an anonymous transfer: the inputs of the fee asset type exceed the
+/// outputs of that type by the fee amount
+#[derive(Debug, Default, PartialEq, Eq, Serialize, Deserialize, Clone, Copy)]
+pub struct AXfrFee {
+    pub amount: u64,
+    pub asset_type: AssetType,
+}
+
+/// Anonymous transfer note: transfer body signed by the signing key of every input
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub struct AXfrNote {
+    pub body: AXfrBody,
+    pub signatures: Vec<AXfrSignature>,
+}
+
+/// Amount and asset type of an output encrypted under an asset tracer key.
+/// The anonymous transfer proof guarantees that `lock` encrypts the values committed in the output.
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub struct AXfrTracerMemo {
+    pub enc_key: AXfrTracerEncKey,
+    pub lock: ElGamalHybridCiphertext<JubjubPoint, BLSScalar>,
 }
 
 /// Asset record to be published
@@ -84,7 +116,7 @@ impl Default for MTLeafInfo {
     }
 }
 
-#[derive(Debug, Default, PartialEq)]
+#[derive(Debug, Default, PartialEq, Clone, Serialize, Deserialize)]
 pub struct OpenAnonBlindAssetRecord {
     pub(crate) amount: u64,
     pub(crate) asset_type: AssetType,
@@ -238,6 +270,26 @@ impl OpenAnonBlindAssetRecordBuilder {
         Ok(builder)
     }
 
+    /// Builds an OpenAssetRecord from an BlindAssetRecord, owner memo and the owner viewing key
+    /// Return error if decrypted `owner_memo` is inconsistent with `record`
+    pub fn from_abar_with_viewing_key(
+        record: &AnonBlindAssetRecord,
+        owner_memo: OwnerMemo,
+        viewing_key: &AXfrViewingKey,
+    ) -> Result<Self> {
+        let (amount, asset_type, blind, key_rand) =
+            decrypt_memo_with_viewing_key(&owner_memo, viewing_key, record).c(d!())?;
+        let mut builder = OpenAnonBlindAssetRecordBuilder::new()
+            .pub_key(viewing_key.pub_key().clone())
+            .amount(amount)
+            .asset_type(asset_type);
+
+        builder.oabar.blind = blind;
+        builder.oabar.key_rand_factor = key_rand;
+        builder.oabar.owner_memo = Some(owner_memo);
+        Ok(builder)
+    }
+
     fn sanity_check(&self) -> Result<()> {
         // 1. check public key is non-default
         if self.oabar.pub_key == AXfrPubKey::default() {
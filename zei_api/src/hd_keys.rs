@@ -0,0 +1,354 @@
+//! Hierarchical deterministic derivation of the wallet keys.
+//!
+//! The wallet seed is computed from a mnemonic phrase as in BIP-39, and the tree of extended keys
+//! follows SLIP-10 for ed25519: only hardened derivation is supported, since none of the derived
+//! key types allows deriving child public keys from a parent public key.
+//! A `XfrKeyPair` is the SLIP-10 ed25519 key of its node. The secret scalars and keys of the other
+//! key types are hashes of a label of the key type and the node key, so that keys of different
+//! types derived at the same path are independent.
+
+use crate::anon_xfr::keys::{AXfrKeyPair, AXfrTracerKeyPair};
+use crate::xfr::sig::{XfrKeyPair, XfrPublicKey, XfrSecretKey};
+use crate::xfr::structs::{AssetTracerDecKeys, AssetTracerEncKeys, AssetTracerKeyPair};
+use algebra::bls12_381::{BLSScalar, BLSG1};
+use algebra::groups::{Group, Scalar};
+use algebra::jubjub::{JubjubPoint, JubjubScalar};
+use algebra::ristretto::{RistrettoPoint, RistrettoScalar};
+use crypto::basics::elgamal::elgamal_key_from_secret;
+use crypto::basics::hybrid_encryption::{XPublicKey, XSecretKey};
+use crypto::basics::signatures::schnorr;
+use digest::Digest;
+use hmac::{Hmac, Mac, NewMac};
+use pbkdf2::pbkdf2;
+use ruc::*;
+use sha2::Sha512;
+use utils::errors::ZeiError;
+use utils::serialization::ZeiFromToBytes;
+
+/// Offset of the hardened child indices
+pub const HARDENED_OFFSET: u32 = 1 << 31;
+
+const SEED_LEN: usize = 64;
+const MNEMONIC_ITERATIONS: u32 = 2048;
+const MNEMONIC_SALT: &[u8] = b"mnemonic";
+const MASTER_KEY_DOMAIN: &[u8] = b"ed25519 seed";
+const AXFR_KEY_PAIR_DOMAIN: &[u8] = b"Zei HD AXfrKeyPair";
+const AXFR_TRACER_KEY_PAIR_DOMAIN: &[u8] = b"Zei HD AXfrTracerKeyPair";
+const X_SECRET_KEY_DOMAIN: &[u8] = b"Zei HD XSecretKey";
+const ASSET_TRACER_RECORD_DATA_DOMAIN: &[u8] = b"Zei HD AssetTracerKeyPair record data";
+const ASSET_TRACER_ATTRS_DOMAIN: &[u8] = b"Zei HD AssetTracerKeyPair attributes";
+const ASSET_TRACER_LOCK_INFO_DOMAIN: &[u8] = b"Zei HD AssetTracerKeyPair lock info";
+
+/// Compute the wallet seed of `mnemonic` protected by `passphrase` as specified by BIP-39:
+/// PBKDF2-HMAC-SHA512 with 2048 iterations and salt "mnemonic" || passphrase.
+/// The phrase is used as given: it is neither checked against a word list nor normalized.
+pub fn mnemonic_to_seed(mnemonic: &str, passphrase: &str) -> [u8; SEED_LEN] {
+    let mut salt = MNEMONIC_SALT.to_vec();
+    salt.extend_from_slice(passphrase.as_bytes());
+    let mut seed = [0u8; SEED_LEN];
+    pbkdf2::<Hmac<Sha512>>(mnemonic.as_bytes(), &salt, MNEMONIC_ITERATIONS, &mut seed);
+    seed
+}
+
+/// Node of the key derivation tree: a 32-byte key and a 32-byte chain code
+#[derive(Clone, PartialEq, Eq)]
+pub struct ExtendedKey {
+    key: [u8; 32],
+    chain_code: [u8; 32],
+}
+
+impl ExtendedKey {
+    /// Compute the root of the tree from a seed of 16 to 64 bytes
+    pub fn from_seed(seed: &[u8]) -> Result<ExtendedKey> {
+        if seed.len() < 16 || seed.len() > SEED_LEN {
+            return Err(eg!(ZeiError::ParameterError));
+        }
+        Ok(ExtendedKey::from_hmac_output(hmac_sha512(
+            MASTER_KEY_DOMAIN,
+            &[seed],
+        )))
+    }
+
+    /// Compute the root of the tree from a BIP-39 mnemonic phrase and passphrase
+    pub fn from_mnemonic(mnemonic: &str, passphrase: &str) -> ExtendedKey {
+        // safe unwrap: the seed has a valid length
+        ExtendedKey::from_seed(&mnemonic_to_seed(mnemonic, passphrase)).unwrap()
+    }
+
+    /// Derive the hardened child number `index`, that is, the child at index `index + HARDENED_OFFSET`
+    pub fn derive_hardened(&self, index: u32) -> Result<ExtendedKey> {
+        if index >= HARDENED_OFFSET {
+            return Err(eg!(ZeiError::ParameterError));
+        }
+        let index_bytes = (index | HARDENED_OFFSET).to_be_bytes();
+        Ok(ExtendedKey::from_hmac_output(hmac_sha512(
+            &self.chain_code,
+            &[&[0u8], &self.key, &index_bytes],
+        )))
+    }
+
+    /// Derive the descendant at `path`, e.g. "m/44'/917'/0'".
+    /// Every index must be hardened, marked by a trailing `'`, `h` or `H`.
+    pub fn derive_path(&self, path: &str) -> Result<ExtendedKey> {
+        let mut components = path.split('/');
+        if components.next() != Some("m") {
+            return Err(eg!(ZeiError::ParameterError));
+        }
+        let mut node = self.clone();
+        for component in components {
+            let index = component
+                .strip_suffix(|c| c == '\'' || c == 'h' || c == 'H')
+                .c(d!(ZeiError::ParameterError))?
+                .parse::<u32>()
+                .c(d!(ZeiError::ParameterError))?;
+            node = node.derive_hardened(index).c(d!())?;
+        }
+        Ok(node)
+    }
+
+    /// Return the transfer key pair of this node
+    pub fn to_xfr_key_pair(&self) -> XfrKeyPair {
+        let sec_key = ed25519_dalek::SecretKey::from_bytes(&self.key).unwrap(); // safe unwrap
+        XfrKeyPair {
+            pub_key: XfrPublicKey((&sec_key).into()),
+            sec_key: XfrSecretKey(sec_key),
+        }
+    }
+
+    /// Return the anonymous transfer key pair of this node
+    pub fn to_anon_key_pair(&self) -> AXfrKeyPair {
+        let sec_key = JubjubScalar::from_hash(self.hash(AXFR_KEY_PAIR_DOMAIN));
+        AXfrKeyPair(schnorr::KeyPair::from_secret_scalar(sec_key))
+    }
+
+    /// Return the anonymous transfer asset tracer key pair of this node
+    pub fn to_anon_tracer_key_pair(&self) -> AXfrTracerKeyPair {
+        let (dec_key, enc_key) = elgamal_key_from_secret(
+            JubjubScalar::from_hash(self.hash(AXFR_TRACER_KEY_PAIR_DOMAIN)),
+            &JubjubPoint::get_base(),
+        );
+        AXfrTracerKeyPair { enc_key, dec_key }
+    }
+
+    /// Return the owner memo decryption key of this node
+    pub fn to_x_secret_key(&self) -> XSecretKey {
+        self.x_secret_key(X_SECRET_KEY_DOMAIN)
+    }
+
+    /// Return the asset tracer key pair of this node
+    pub fn to_asset_tracer_key_pair(&self) -> AssetTracerKeyPair {
+        let (record_data_dec_key, record_data_enc_key) = elgamal_key_from_secret(
+            RistrettoScalar::from_hash(self.hash(ASSET_TRACER_RECORD_DATA_DOMAIN)),
+            &RistrettoPoint::get_base(),
+        );
+        let (attrs_dec_key, attrs_enc_key) = elgamal_key_from_secret(
+            BLSScalar::from_hash(self.hash(ASSET_TRACER_ATTRS_DOMAIN)),
+            &BLSG1::get_base(),
+        );
+        let lock_info_dec_key = self.x_secret_key(ASSET_TRACER_LOCK_INFO_DOMAIN);
+        let lock_info_enc_key = XPublicKey::from(&lock_info_dec_key);
+        AssetTracerKeyPair {
+            enc_key: AssetTracerEncKeys {
+                record_data_enc_key,
+                attrs_enc_key,
+                lock_info_enc_key,
+            },
+            dec_key: AssetTracerDecKeys {
+                record_data_dec_key,
+                attrs_dec_key,
+                lock_info_dec_key,
+            },
+        }
+    }
+
+    fn from_hmac_output(output: [u8; 64]) -> ExtendedKey {
+        let mut key = [0u8; 32];
+        let mut chain_code = [0u8; 32];
+        key.copy_from_slice(&output[..32]);
+        chain_code.copy_from_slice(&output[32..]);
+        ExtendedKey { key, chain_code }
+    }
+
+    /// Hash of `domain` || key, from which the secret keys of a key type are derived
+    fn hash(&self, domain: &[u8]) -> Sha512 {
+        let mut hasher = Sha512::new();
+        hasher.update(domain);
+        hasher.update(&self.key);
+        hasher
+    }
+
+    fn x_secret_key(&self, domain: &[u8]) -> XSecretKey {
+        // safe unwrap: the key has the right length
+        XSecretKey::zei_from_bytes(&self.hash(domain).finalize()[..32]).unwrap()
+    }
+}
+
+/// HMAC-SHA512 (RFC 2104) of the concatenation of `data`
+fn hmac_sha512(key: &[u8], data: &[&[u8]]) -> [u8; 64] {
+    // safe unwrap: HMAC accepts keys of any length
+    let mut mac = Hmac::<Sha512>::new_varkey(key).unwrap();
+    for d in data {
+        mac.update(d);
+    }
+    let mut output = [0u8; 64];
+    output.copy_from_slice(&mac.finalize().into_bytes());
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::hd_keys::{hmac_sha512, mnemonic_to_seed, ExtendedKey, HARDENED_OFFSET};
+    use crate::xfr::structs::AssetTracerKeyPair;
+    use algebra::groups::Group;
+    use crypto::basics::hybrid_encryption::XPublicKey;
+    use utils::errors::ZeiError;
+    use utils::serialization::ZeiFromToBytes;
+
+    fn from_hex(s: &str) -> Vec<u8> {
+        (0..s.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_hmac_sha512() {
+        // RFC 4231, test case 2
+        let mac = hmac_sha512(b"Jefe", &[b"what do ya want ", b"for nothing?"]);
+        assert_eq!(
+            mac.to_vec(),
+            from_hex(
+                "164b7a7bfcf819e2e395fbe73b56e0a387bd64222e831fd610270cd7ea250554\
+                 9758bf75c05a994a6d034f65f8f0e6fdcaeab1a34d4a6b4b636e070a38bce737"
+            )
+        );
+    }
+
+    #[test]
+    fn test_mnemonic_to_seed() {
+        // BIP-39 test vector
+        let mnemonic =
+            "abandon abandon abandon abandon abandon abandon abandon abandon \
+                        abandon abandon abandon about";
+        assert_eq!(
+            mnemonic_to_seed(mnemonic, "TREZOR").to_vec(),
+            from_hex(
+                "c55257c360c07c72029aebc1b53c05ed0362ada38ead3e3e9efa3708e5349553\
+                 1f09a6987599d18264c1e1c92f2cf141630c7a3c4ab7c81b2f001698e7463b04"
+            )
+        );
+    }
+
+    #[test]
+    fn test_derivation_vectors() {
+        // SLIP-10 test vector 1 for ed25519
+        let root = ExtendedKey::from_seed(&from_hex("000102030405060708090a0b0c0d0e0f"))
+            .unwrap();
+        let vectors = [
+            (
+                "m",
+                "2b4be7f19ee27bbf30c667b642d5f4aa69fd169872f8fc3059c08ebae2eb19e7",
+                "90046a93de5380a72b5e45010748567d5ea02bbf6522f979e05c0d8d8ca9fffb",
+            ),
+            (
+                "m/0'",
+                "68e0fe46dfb67e368c75379acec591dad19df3cde26e63b93a8e704f1dade7a3",
+                "8b59aa11380b624e81507a27fedda59fea6d0b779a778918a2fd3590e16e9c69",
+            ),
+            (
+                "m/0'/1H",
+                "b1d0bad404bf35da785a64ca1ac54b2617211d2777696fbffaf208f746ae84f2",
+                "a320425f77d1b5c2505a6b1b27382b37368ee640e3557c315416801243552f14",
+            ),
+        ];
+        for (path, key, chain_code) in vectors.iter() {
+            let node = root.derive_path(path).unwrap();
+            assert_eq!(node.key.to_vec(), from_hex(key));
+            assert_eq!(node.chain_code.to_vec(), from_hex(chain_code));
+        }
+
+        let node = root.derive_hardened(0).unwrap().derive_hardened(1).unwrap();
+        assert!(node == root.derive_path("m/0h/1'").unwrap());
+        assert_eq!(
+            node.to_xfr_key_pair().pub_key.as_bytes().to_vec(),
+            from_hex("1932a5270f335bed617d5b935c80aedb1a35bd9fc1e31acafd5372c30f5c1187")
+        );
+        assert_eq!(
+            XPublicKey::from(&node.to_x_secret_key()).zei_to_bytes(),
+            from_hex("72ef421bb6b1a7ff1a550c774e0743ef40c280cc0672e08fa53002c33cc0fb26")
+        );
+        assert_eq!(
+            node.to_anon_key_pair().pub_key().zei_to_bytes(),
+            from_hex("ae1d753d4678f0d30e573fcc988a4b5c9b8e63e6ca32890a7d2fb51fa207b255")
+        );
+        assert_eq!(
+            node.to_anon_tracer_key_pair()
+                .enc_key
+                .get_point_ref()
+                .to_compressed_bytes(),
+            from_hex("dc5e34e6016352a1e9868babc7df583b206dee80e0a217ead1e91e29a3b7e544")
+        );
+        let enc_keys = node.to_asset_tracer_key_pair().enc_key;
+        assert_eq!(
+            enc_keys
+                .record_data_enc_key
+                .get_point_ref()
+                .to_compressed_bytes(),
+            from_hex("62264da945bf038234105e63d2aad3f771050efd46ed8d9a9b62f92b5409e542")
+        );
+        assert_eq!(
+            enc_keys.attrs_enc_key.get_point_ref().to_compressed_bytes(),
+            from_hex(
+                "8cec3a2dd2691c05ce8a25c0a0d9b8f3ec675f778abfac74c0d044d111f23c48\
+                 2354a118993f4be36e4f90dd7ef7a596"
+            )
+        );
+        assert_eq!(
+            enc_keys.lock_info_enc_key.zei_to_bytes(),
+            from_hex("3e1917271f38abdc691924b3f684ddd5981a40d4908c728e9523b3945d47be28")
+        );
+    }
+
+    #[test]
+    fn test_derived_keys() {
+        let root = ExtendedKey::from_mnemonic("zei wallet", "");
+        let node = root.derive_path("m/44'/917'/0'").unwrap();
+        let other = root.derive_path("m/44'/917'/1'").unwrap();
+
+        // keys are recovered from the mnemonic
+        let node2 = ExtendedKey::from_mnemonic("zei wallet", "")
+            .derive_path("m/44'/917'/0'")
+            .unwrap();
+        let (key_pair, key_pair2) = (node.to_xfr_key_pair(), node2.to_xfr_key_pair());
+        assert_eq!(key_pair.pub_key, key_pair2.pub_key);
+        assert_eq!(key_pair.sec_key, key_pair2.sec_key);
+        assert_eq!(node.to_anon_key_pair(), node2.to_anon_key_pair());
+        assert_eq!(
+            node.to_anon_tracer_key_pair(),
+            node2.to_anon_tracer_key_pair()
+        );
+        assert!(node.to_x_secret_key() == node2.to_x_secret_key());
+        assert!(node.to_asset_tracer_key_pair() == node2.to_asset_tracer_key_pair());
+
+        // the keys of different nodes are different
+        assert_ne!(key_pair.pub_key, other.to_xfr_key_pair().pub_key);
+        assert_ne!(node.to_anon_key_pair(), other.to_anon_key_pair());
+        assert!(node.to_x_secret_key() != other.to_x_secret_key());
+        let tracer_key_pair: AssetTracerKeyPair = node.to_asset_tracer_key_pair();
+        assert!(tracer_key_pair != other.to_asset_tracer_key_pair());
+
+        // a passphrase changes the whole tree
+        assert!(ExtendedKey::from_mnemonic("zei wallet", "passphrase") != root);
+
+        // only hardened derivation along well-formed paths is supported
+        for path in ["m/0", "0'", "m/x'", "m/2147483648'", "m//0'"].iter() {
+            let err = root.derive_path(path).unwrap_err();
+            err_eq!(ZeiError::ParameterError, err);
+        }
+        let err = root.derive_hardened(HARDENED_OFFSET).unwrap_err();
+        err_eq!(ZeiError::ParameterError, err);
+        let err = ExtendedKey::from_seed(&[0u8; 15]).unwrap_err();
+        err_eq!(ZeiError::ParameterError, err);
+        assert!(root.derive_path("m").unwrap() == root);
+    }
+}
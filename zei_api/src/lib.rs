@@ -20,6 +20,7 @@ extern crate serde_derive;
 
 pub mod anon_xfr;
 pub mod api;
+pub mod hd_keys;
 pub mod serialization;
 pub mod setup;
 pub mod xfr;
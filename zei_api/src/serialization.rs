@@ -1,5 +1,15 @@
+use crate::anon_xfr::keys::{
+    AXfrKeyPair, AXfrPubKey, AXfrSignature, AXfrViewingKey, AXFR_PUB_KEY_LENGTH,
+    AXFR_SIGNATURE_LENGTH,
+};
+use crate::anon_xfr::structs::{MTLeafInfo, MTNode, MTPath, MT_NODE_LENGTH};
 use crate::xfr::sig::{XfrPublicKey, XfrSecretKey, XfrSignature};
 use crate::xfr::structs::{AssetType, ASSET_TYPE_LENGTH};
+use algebra::bls12_381::{BLSScalar, BLS_SCALAR_LEN};
+use algebra::groups::Scalar;
+use algebra::jubjub::{JubjubScalar, JUBJUB_SCALAR_LEN};
+use crypto::basics::hybrid_encryption::XSecretKey;
+use crypto::basics::signatures::schnorr;
 use ed25519_dalek::ed25519::signature::Signature;
 use ed25519_dalek::{PublicKey, SecretKey};
 use ruc::*;
@@ -7,33 +17,137 @@ use serde::Serializer;
 use utils::errors::ZeiError;
 pub use utils::serialization::ZeiFromToBytes;
 
-/*
 impl ZeiFromToBytes for AXfrPubKey {
-  fn zei_to_bytes(&self) -> Vec<u8> {
-    self.0.zei_to_bytes()
-  }
+    fn zei_to_bytes(&self) -> Vec<u8> {
+        self.0.zei_to_bytes()
+    }
 
-  fn zei_from_bytes(bytes: &[u8]) -> Result<Self> {
-    let point = JubjubGroup::zei_from_bytes(bytes).c(d!())?;
-    Ok(AXfrPubKey(point))
-  }
+    fn zei_from_bytes(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() != AXFR_PUB_KEY_LENGTH {
+            return Err(eg!(ZeiError::DeserializationError));
+        }
+        let pub_key = schnorr::PublicKey::zei_from_bytes(bytes)
+            .c(d!(ZeiError::DeserializationError))?;
+        Ok(AXfrPubKey(pub_key))
+    }
 }
 
 serialize_deserialize!(AXfrPubKey);
 
-impl ZeiFromToBytes for AXfrSecKey {
-  fn zei_to_bytes(&self) -> Vec<u8> {
-    self.0.zei_to_bytes()
-  }
+// A key pair is encoded as its secret key, the public key is recomputed when decoding
+impl ZeiFromToBytes for AXfrKeyPair {
+    fn zei_to_bytes(&self) -> Vec<u8> {
+        self.get_secret_scalar().to_bytes()
+    }
 
-  fn zei_from_bytes(bytes: &[u8]) -> Result<Self> {
-    let scalar = JubjubScalar::zei_from_bytes(bytes).c(d!())?;
-    Ok(AXfrSecKey(scalar))
-  }
+    fn zei_from_bytes(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() != JUBJUB_SCALAR_LEN {
+            return Err(eg!(ZeiError::DeserializationError));
+        }
+        let scalar =
+            JubjubScalar::from_bytes(bytes).c(d!(ZeiError::DeserializationError))?;
+        Ok(AXfrKeyPair(schnorr::KeyPair::from_secret_scalar(scalar)))
+    }
 }
 
-serialize_deserialize!(AXfrSecKey);
-*/
+serialize_deserialize!(AXfrKeyPair);
+
+impl ZeiFromToBytes for AXfrSignature {
+    fn zei_to_bytes(&self) -> Vec<u8> {
+        self.0.zei_to_bytes()
+    }
+
+    fn zei_from_bytes(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() != AXFR_SIGNATURE_LENGTH {
+            return Err(eg!(ZeiError::DeserializationError));
+        }
+        let sig = schnorr::Signature::zei_from_bytes(bytes)
+            .c(d!(ZeiError::DeserializationError))?;
+        Ok(AXfrSignature(sig))
+    }
+}
+
+serialize_deserialize!(AXfrSignature);
+
+// A node is encoded as siblings1 || siblings2 || is_left_child || is_right_child
+impl ZeiFromToBytes for MTNode {
+    fn zei_to_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.siblings1.to_bytes();
+        bytes.append(&mut self.siblings2.to_bytes());
+        bytes.push(self.is_left_child);
+        bytes.push(self.is_right_child);
+        bytes
+    }
+
+    fn zei_from_bytes(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() != MT_NODE_LENGTH {
+            return Err(eg!(ZeiError::DeserializationError));
+        }
+        let (is_left_child, is_right_child) =
+            (bytes[2 * BLS_SCALAR_LEN], bytes[2 * BLS_SCALAR_LEN + 1]);
+        if is_left_child > 1 || is_right_child > 1 || is_left_child + is_right_child > 1
+        {
+            return Err(eg!(ZeiError::DeserializationError));
+        }
+        Ok(MTNode {
+            siblings1: BLSScalar::from_bytes(&bytes[..BLS_SCALAR_LEN])
+                .c(d!(ZeiError::DeserializationError))?,
+            siblings2: BLSScalar::from_bytes(&bytes[BLS_SCALAR_LEN..2 * BLS_SCALAR_LEN])
+                .c(d!(ZeiError::DeserializationError))?,
+            is_left_child,
+            is_right_child,
+        })
+    }
+}
+
+serialize_deserialize!(MTNode);
+
+// A path is encoded as the concatenation of its nodes, from the leaf to the root
+impl ZeiFromToBytes for MTPath {
+    fn zei_to_bytes(&self) -> Vec<u8> {
+        self.nodes
+            .iter()
+            .flat_map(|node| node.zei_to_bytes())
+            .collect()
+    }
+
+    fn zei_from_bytes(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() % MT_NODE_LENGTH != 0 {
+            return Err(eg!(ZeiError::DeserializationError));
+        }
+        let nodes = bytes
+            .chunks(MT_NODE_LENGTH)
+            .map(|chunk| MTNode::zei_from_bytes(chunk).c(d!()))
+            .collect::<Result<Vec<MTNode>>>()?;
+        Ok(MTPath::new(nodes))
+    }
+}
+
+serialize_deserialize!(MTPath);
+
+// Leaf information is encoded as root || uid (8 bytes, little endian) || path
+impl ZeiFromToBytes for MTLeafInfo {
+    fn zei_to_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.root.to_bytes();
+        bytes.extend_from_slice(&self.uid.to_le_bytes());
+        bytes.append(&mut self.path.zei_to_bytes());
+        bytes
+    }
+
+    fn zei_from_bytes(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() < BLS_SCALAR_LEN + 8 {
+            return Err(eg!(ZeiError::DeserializationError));
+        }
+        Ok(MTLeafInfo {
+            root: BLSScalar::from_bytes(&bytes[..BLS_SCALAR_LEN])
+                .c(d!(ZeiError::DeserializationError))?,
+            uid: utils::u8_le_slice_to_u64(&bytes[BLS_SCALAR_LEN..BLS_SCALAR_LEN + 8]),
+            path: MTPath::zei_from_bytes(&bytes[BLS_SCALAR_LEN + 8..]).c(d!())?,
+        })
+    }
+}
+
+serialize_deserialize!(MTLeafInfo);
 
 impl ZeiFromToBytes for AssetType {
     fn zei_to_bytes(&self) -> Vec<u8> {
@@ -97,6 +211,29 @@ impl ZeiFromToBytes for XfrSignature {
 
 serialize_deserialize!(XfrSignature);
 
+impl ZeiFromToBytes for AXfrViewingKey {
+    fn zei_to_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.dec_key.zei_to_bytes();
+        bytes.append(&mut self.pub_key.0.zei_to_bytes());
+        bytes
+    }
+
+    fn zei_from_bytes(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() != 64 {
+            return Err(eg!(ZeiError::DeserializationError));
+        }
+        let dec_key = XSecretKey::zei_from_bytes(&bytes[..32]).c(d!())?;
+        let pub_key = schnorr::PublicKey::zei_from_bytes(&bytes[32..])
+            .c(d!(ZeiError::DeserializationError))?;
+        Ok(AXfrViewingKey {
+            dec_key,
+            pub_key: AXfrPubKey(pub_key),
+        })
+    }
+}
+
+serialize_deserialize!(AXfrViewingKey);
+
 /*
 // XXX keep this for future reference
 // use with #[serde(with = "serialization::option_bytes")]
@@ -138,14 +275,24 @@ pub mod option_bytes {
 
 #[cfg(test)]
 mod test {
-    use crate::anon_xfr::keys::{AXfrKeyPair, AXfrPubKey};
+    use crate::anon_xfr::accumulator::{MemoryMTStore, MerkleAccumulator};
+    use crate::anon_xfr::keys::{
+        AXfrKeyPair, AXfrPubKey, AXfrSignature, AXfrViewingKey, AXFR_PUB_KEY_LENGTH,
+        AXFR_SIGNATURE_LENGTH,
+    };
+    use crate::anon_xfr::structs::{
+        AnonBlindAssetRecord, MTLeafInfo, MTNode, OpenAnonBlindAssetRecord,
+        OpenAnonBlindAssetRecordBuilder, MT_NODE_LENGTH,
+    };
     use crate::ristretto::CompressedRistretto;
     use crate::serialization::ZeiFromToBytes;
     use crate::xfr::asset_tracer::RecordDataEncKey;
     use crate::xfr::sig::{XfrKeyPair, XfrPublicKey, XfrSecretKey, XfrSignature};
     use crate::xfr::structs::{
-        BlindAssetRecord, OpenAssetRecord, XfrAmount, XfrAssetType,
+        AssetType, BlindAssetRecord, OpenAssetRecord, XfrAmount, XfrAssetType,
     };
+    use algebra::bls12_381::BLS_SCALAR_LEN;
+    use algebra::jubjub::JUBJUB_SCALAR_LEN;
     use algebra::ristretto::RistrettoPoint;
     use crypto::basics::commitments::ristretto_pedersen::RistrettoPedersenGens;
     use crypto::basics::elgamal::elgamal_key_gen;
@@ -242,6 +389,109 @@ mod test {
         assert_eq!(keypair, keypair_2);
     }
 
+    #[test]
+    fn anon_xfr_viewing_key_serialization() {
+        let mut prng = ChaChaRng::from_seed([0u8; 32]);
+        let keypair = AXfrKeyPair::generate(&mut prng);
+        let viewing_key = keypair.viewing_key();
+
+        let bytes = viewing_key.zei_to_bytes();
+        assert_eq!(bytes.len(), 64);
+        let viewing_key2 = AXfrViewingKey::zei_from_bytes(&bytes).unwrap();
+        assert!(viewing_key == viewing_key2);
+        assert!(AXfrViewingKey::zei_from_bytes(&bytes[..63]).is_err());
+
+        let mut vk_mp_vec = vec![];
+        assert!(viewing_key
+            .serialize(&mut Serializer::new(&mut vk_mp_vec))
+            .is_ok());
+        let mut de = Deserializer::new(&vk_mp_vec[..]);
+        let viewing_key3: AXfrViewingKey = Deserialize::deserialize(&mut de).unwrap();
+        assert!(viewing_key == viewing_key3);
+
+        let json = serde_json::to_string(&viewing_key).unwrap();
+        let viewing_key4: AXfrViewingKey = serde_json::from_str(&json).unwrap();
+        assert!(viewing_key == viewing_key4);
+        assert_eq!(viewing_key4.pub_key(), &keypair.pub_key());
+    }
+
+    #[test]
+    fn anon_xfr_types_byte_and_json_serialization() {
+        let mut prng = ChaChaRng::from_seed([0u8; 32]);
+        let keypair = AXfrKeyPair::generate(&mut prng);
+        let enc_key = XPublicKey::from(&XSecretKey::new(&mut prng));
+
+        // public key
+        let pub_key = keypair.pub_key();
+        let bytes = pub_key.zei_to_bytes();
+        assert_eq!(bytes.len(), AXFR_PUB_KEY_LENGTH);
+        assert_eq!(AXfrPubKey::zei_from_bytes(&bytes).unwrap(), pub_key);
+        assert!(AXfrPubKey::zei_from_bytes(&bytes[..31]).is_err());
+        let json = serde_json::to_string(&pub_key).unwrap();
+        assert_eq!(json, format!("\"{}\"", utils::b64enc(&bytes)));
+        assert_eq!(serde_json::from_str::<AXfrPubKey>(&json).unwrap(), pub_key);
+
+        // key pair
+        let bytes = keypair.zei_to_bytes();
+        assert_eq!(bytes.len(), JUBJUB_SCALAR_LEN);
+        assert_eq!(AXfrKeyPair::zei_from_bytes(&bytes).unwrap(), keypair);
+        let json = serde_json::to_string(&keypair).unwrap();
+        assert_eq!(serde_json::from_str::<AXfrKeyPair>(&json).unwrap(), keypair);
+
+        // signature
+        let sig = keypair.sign(b"message");
+        let bytes = sig.zei_to_bytes();
+        assert_eq!(bytes.len(), AXFR_SIGNATURE_LENGTH);
+        let sig2 = AXfrSignature::zei_from_bytes(&bytes).unwrap();
+        assert!(pub_key.verify(b"message", sig2).is_ok());
+        assert!(AXfrSignature::zei_from_bytes(&bytes[..63]).is_err());
+        let json = serde_json::to_string(&sig).unwrap();
+        assert_eq!(serde_json::from_str::<AXfrSignature>(&json).unwrap(), sig);
+
+        // Merkle tree leaf information
+        let mut acc = MerkleAccumulator::new(MemoryMTStore::new(), 3).unwrap();
+        let mut oabar = OpenAnonBlindAssetRecordBuilder::new()
+            .amount(10)
+            .asset_type(AssetType::from_identical_byte(1))
+            .pub_key(pub_key)
+            .finalize(&mut prng, &enc_key)
+            .unwrap()
+            .build()
+            .unwrap();
+        for _ in 0..4 {
+            acc.add_abar(&AnonBlindAssetRecord::from_oabar(&oabar))
+                .unwrap();
+        }
+        let leaf_info = acc.get_leaf_info(2).unwrap();
+        let bytes = leaf_info.zei_to_bytes();
+        assert_eq!(bytes.len(), BLS_SCALAR_LEN + 8 + 3 * MT_NODE_LENGTH);
+        assert_eq!(MTLeafInfo::zei_from_bytes(&bytes).unwrap(), leaf_info);
+        assert!(MTLeafInfo::zei_from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        let node_bytes = leaf_info.path.nodes[0].zei_to_bytes();
+        assert_eq!(
+            MTNode::zei_from_bytes(&node_bytes).unwrap(),
+            leaf_info.path.nodes[0]
+        );
+        let mut bad_node_bytes = node_bytes.clone();
+        bad_node_bytes[MT_NODE_LENGTH - 2] = 1;
+        bad_node_bytes[MT_NODE_LENGTH - 1] = 1;
+        assert!(MTNode::zei_from_bytes(&bad_node_bytes).is_err());
+        let json = serde_json::to_string(&leaf_info).unwrap();
+        assert_eq!(
+            serde_json::from_str::<MTLeafInfo>(&json).unwrap(),
+            leaf_info
+        );
+
+        // open anonymous record
+        oabar.update_mt_leaf_info(leaf_info);
+        let json = serde_json::to_string(&oabar).unwrap();
+        let oabar2: OpenAnonBlindAssetRecord = serde_json::from_str(&json).unwrap();
+        assert_eq!(oabar2, oabar);
+        let oabar3: OpenAnonBlindAssetRecord =
+            bincode::deserialize(&bincode::serialize(&oabar).unwrap()).unwrap();
+        assert_eq!(oabar3, oabar);
+    }
+
     #[test]
     fn public_key_message_pack_serialization() {
         let mut prng: ChaChaRng;
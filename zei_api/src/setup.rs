@@ -1,7 +1,7 @@
 //The Public Setup needed for Proofs
 use crate::anon_xfr::circuits::{
-    build_eq_committed_vals_cs, build_multi_xfr_cs, AMultiXfrWitness, TurboPlonkCS,
-    TREE_DEPTH,
+    build_anon_issuance_cs, build_eq_committed_vals_cs, build_multi_xfr_cs,
+    AMultiXfrWitness, TurboPlonkCS, TREE_DEPTH,
 };
 use algebra::bls12_381::BLSScalar;
 use algebra::groups::Zero;
@@ -37,6 +37,9 @@ pub struct UserParams {
     pub pcs: KZGCommitmentSchemeBLS,
     pub cs: TurboPlonkCS,
     pub prover_params: ProverParams<KZGCommitmentSchemeBLS>,
+    /// Maximum number of inputs and outputs of an anonymous transfer padded with dummy records,
+    /// None if the circuit is fixed to an exact number of inputs and outputs
+    pub padding: Option<(usize, usize)>,
 }
 
 pub struct NodeParams {
@@ -48,12 +51,20 @@ pub struct NodeParams {
 
 pub const BULLET_PROOF_RANGE: usize = 32;
 
+/// Range of the proofs of records with a single amount commitment
+pub const BULLET_PROOF_RANGE_64: usize = 64;
+
 pub const DEFAULT_BP_NUM_GENS: usize = 256;
 
 pub const MAX_PARTY_NUMBER: usize = 128;
 
 const COMMON_SEED: [u8; 32] = [0u8; 32];
 
+/// Version of the anonymous transfer circuit, part of the name of the cached parameters files.
+/// It must be increased whenever the constraints of the circuit change, so that parameters
+/// cached for a previous circuit are not loaded.
+const MULTI_XFR_CIRCUIT_VERSION: usize = 2;
+
 fn from_file<T: for<'de> Deserialize<'de>>(filename: &str) -> Result<T> {
     let contents = fs::read(filename).c(d!(ZeiError::ParameterError))?;
     bincode::deserialize(&contents).c(d!(ZeiError::DeserializationError))
@@ -110,6 +121,11 @@ impl PublicParams {
         self.bp_circuit_gens
             .increase_capacity(new_size.next_power_of_two());
     }
+
+    /// Has no effect if bits is less or equal than current range proof capacity
+    pub fn increase_range_proof_gens(&mut self, bits: usize) {
+        self.bp_gens.increase_capacity(bits);
+    }
 }
 
 impl Default for PublicParams {
@@ -125,25 +141,84 @@ impl UserParams {
         tree_depth: Option<usize>,
         bp_num_gens: usize,
     ) -> UserParams {
-        let (cs, n_constraints) = match tree_depth {
-            Some(depth) => {
-                build_multi_xfr_cs(AMultiXfrWitness::fake(n_payers, n_payees, depth))
-            }
-            None => build_multi_xfr_cs(AMultiXfrWitness::fake(
-                n_payers, n_payees, TREE_DEPTH,
-            )),
-        };
-        let pcs = KZGCommitmentScheme::new(
-            n_constraints + 2,
-            &mut ChaChaRng::from_seed([0u8; 32]),
-        );
-        let prover_params = preprocess_prover(&cs, &pcs, COMMON_SEED).unwrap();
-        UserParams {
-            bp_params: PublicParams::new(bp_num_gens),
-            pcs,
-            cs,
-            prover_params,
-        }
+        Self::new_with_tracing(n_payers, &vec![false; n_payees], tree_depth, bp_num_gens)
+    }
+
+    /// Generate the parameters of an anonymous transfer whose i-th output amount and asset type
+    /// are encrypted to an asset tracer iff `outputs_tracing[i]` is true.
+    /// * `n_payers` - number of payers
+    /// * `outputs_tracing` - tracing flag of each payee
+    /// * `tree_depth` - depth of the merkle tree
+    /// * `bp_num_gens` - number of BP generators for the circuit
+    pub fn new_with_tracing(
+        n_payers: usize,
+        outputs_tracing: &[bool],
+        tree_depth: Option<usize>,
+        bp_num_gens: usize,
+    ) -> UserParams {
+        let depth = tree_depth.unwrap_or(TREE_DEPTH);
+        Self::from_multi_xfr_witness(
+            AMultiXfrWitness::fake_with_tracing(n_payers, outputs_tracing, depth),
+            bp_num_gens,
+            None,
+        )
+    }
+
+    /// Generate the parameters of an anonymous transfer with `n_public_outputs` outputs
+    /// whose amount, asset type and owner are public.
+    /// * `n_payers` - number of payers
+    /// * `n_payees` - number of payees
+    /// * `n_public_outputs` - number of public outputs
+    /// * `tree_depth` - depth of the merkle tree
+    /// * `bp_num_gens` - number of BP generators for the circuit
+    pub fn new_with_public_outputs(
+        n_payers: usize,
+        n_payees: usize,
+        n_public_outputs: usize,
+        tree_depth: Option<usize>,
+        bp_num_gens: usize,
+    ) -> UserParams {
+        let depth = tree_depth.unwrap_or(TREE_DEPTH);
+        Self::from_multi_xfr_witness(
+            AMultiXfrWitness::fake_with_public_outputs(
+                n_payers,
+                n_payees,
+                n_public_outputs,
+                depth,
+            ),
+            bp_num_gens,
+            None,
+        )
+    }
+
+    /// Generate the parameters of anonymous transfers with up to `max_payers` inputs and
+    /// `max_payees` outputs. Transfers are padded with zero-amount dummy inputs and outputs
+    /// so that all of them share the same verifier parameters.
+    /// * `max_payers` - maximum number of payers
+    /// * `max_payees` - maximum number of payees
+    /// * `tree_depth` - depth of the merkle tree
+    /// * `bp_num_gens` - number of BP generators for the circuit
+    pub fn new_with_padding(
+        max_payers: usize,
+        max_payees: usize,
+        tree_depth: Option<usize>,
+        bp_num_gens: usize,
+    ) -> UserParams {
+        let depth = tree_depth.unwrap_or(TREE_DEPTH);
+        Self::from_multi_xfr_witness(
+            AMultiXfrWitness::fake_padded(max_payers, max_payees, depth),
+            bp_num_gens,
+            Some((max_payers, max_payees)),
+        )
+    }
+
+    fn from_multi_xfr_witness(
+        witness: AMultiXfrWitness,
+        bp_num_gens: usize,
+        padding: Option<(usize, usize)>,
+    ) -> UserParams {
+        let (cs, n_constraints) = build_multi_xfr_cs(witness);
+        Self::from_cs(cs, n_constraints, bp_num_gens, padding)
     }
 
     pub fn eq_committed_vals_params() -> UserParams {
@@ -151,16 +226,34 @@ impl UserParams {
         let pc_gens_jubjub = PedersenGens::<JubjubPoint>::new(2);
         let (cs, n_constraints) =
             build_eq_committed_vals_cs(zero, zero, zero, zero, &pc_gens_jubjub);
+        Self::from_cs(cs, n_constraints, DEFAULT_BP_NUM_GENS, None)
+    }
+
+    /// Parameters of the proof that an issued anonymous record hides the issued asset type
+    pub fn anon_issuance_params() -> UserParams {
+        let zero = BLSScalar::zero();
+        let (cs, n_constraints) = build_anon_issuance_cs(zero, zero, zero, false);
+        Self::from_cs(cs, n_constraints, DEFAULT_BP_NUM_GENS, None)
+    }
+
+    /// Preprocess the parameters of the constraint system `cs` of `n_constraints` constraints
+    fn from_cs(
+        cs: TurboPlonkCS,
+        n_constraints: usize,
+        bp_num_gens: usize,
+        padding: Option<(usize, usize)>,
+    ) -> UserParams {
         let pcs = KZGCommitmentScheme::new(
             n_constraints + 2,
             &mut ChaChaRng::from_seed([0u8; 32]),
         );
         let prover_params = preprocess_prover(&cs, &pcs, COMMON_SEED).unwrap();
         UserParams {
-            bp_params: PublicParams::new(DEFAULT_BP_NUM_GENS),
+            bp_params: PublicParams::new(bp_num_gens),
             pcs,
             cs,
             prover_params,
+            padding,
         }
     }
 
@@ -169,7 +262,7 @@ impl UserParams {
     }
 
     /// Generate the parameters from a file if it exists.
-    /// The filename is derived from some internal path and the values of `n_payers`,`n_payees`,`tree_depth`and `bp_num_gens`.
+    /// The filename is derived from some internal path, the version of the circuit and the values of `n_payers`,`n_payees`,`tree_depth`and `bp_num_gens`.
     /// Otherwise it generates the parameters and store them on disk so that these parameters can be retrieved later.
     /// * `n_payers` - number of payers
     /// * `n_payees` - number of payeers
@@ -185,7 +278,8 @@ impl UserParams {
         path: Option<String>,
     ) -> Result<UserParams> {
         let default_filename = compute_full_path_from_root(&format!(
-            "user_params_{}_{}_{}_{}.bin",
+            "user_params_v{}_{}_{}_{}_{}.bin",
+            MULTI_XFR_CIRCUIT_VERSION,
             n_payers,
             n_payees,
             tree_depth.unwrap_or(0_usize),
@@ -4,16 +4,20 @@ use crate::api::anon_creds::{
 };
 use crate::xfr::sig::{XfrKeyPair, XfrPublicKey};
 use crate::xfr::structs::{
-    AssetRecord, AssetRecordTemplate, AssetType, BlindAssetRecord, OpenAssetRecord,
-    OwnerMemo, TracerMemo, TracingPolicies, XfrAmount, XfrAssetType,
+    AssetRecord, AssetRecordTemplate, AssetTracerEncKeys, AssetType, BlindAssetRecord,
+    OpenAssetRecord, OwnerMemo, TracerMemo, TracingPolicies, XfrAmount, XfrAssetType,
 };
-use algebra::groups::Zero;
+use crate::xfr::threshold::XfrThresholdPolicy;
+use algebra::groups::{Scalar as _, ScalarArithmetic, Zero};
 use algebra::ristretto::RistrettoScalar as Scalar;
 use crypto::basics::commitments::ristretto_pedersen::RistrettoPedersenGens;
+use crypto::basics::hybrid_encryption::ZeiHybridCipher;
 use rand_core::{CryptoRng, RngCore};
 use ruc::*;
 use utils::errors::ZeiError;
-use utils::{self, u64_to_u32_pair};
+use utils::u64_to_u32_pair;
+
+const POW_2_32: u64 = 0xFFFF_FFFFu64 + 1;
 
 /// AssetRecrod confidentiality flags. Indicated if amount and/or assettype should be confidential
 #[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq)]
@@ -23,6 +27,10 @@ pub enum AssetRecordType {
     ConfidentialAmount_NonConfidentialAssetType,
     ConfidentialAmount_ConfidentialAssetType,
     NonConfidentialAmount_NonConfidentialAssetType,
+    /// Amount hidden in a single commitment with a 64-bit range proof
+    ConfidentialAmount64_NonConfidentialAssetType,
+    /// Amount hidden in a single commitment with a 64-bit range proof
+    ConfidentialAmount64_ConfidentialAssetType,
 }
 
 impl AssetRecordType {
@@ -41,6 +49,10 @@ impl AssetRecordType {
                 (false, true)
             }
             AssetRecordType::ConfidentialAmount_ConfidentialAssetType => (true, true),
+            AssetRecordType::ConfidentialAmount64_NonConfidentialAssetType => {
+                (true, false)
+            }
+            AssetRecordType::ConfidentialAmount64_ConfidentialAssetType => (true, true),
         }
     }
 
@@ -49,6 +61,8 @@ impl AssetRecordType {
             self,
             AssetRecordType::ConfidentialAmount_ConfidentialAssetType
                 | AssetRecordType::ConfidentialAmount_NonConfidentialAssetType
+                | AssetRecordType::ConfidentialAmount64_ConfidentialAssetType
+                | AssetRecordType::ConfidentialAmount64_NonConfidentialAssetType
         )
     }
     pub fn is_confidential_asset_type(self) -> bool {
@@ -56,6 +70,7 @@ impl AssetRecordType {
             self,
             AssetRecordType::ConfidentialAmount_ConfidentialAssetType
                 | AssetRecordType::NonConfidentialAmount_ConfidentialAssetType
+                | AssetRecordType::ConfidentialAmount64_ConfidentialAssetType
         )
     }
 
@@ -63,6 +78,17 @@ impl AssetRecordType {
         matches!(
             self,
             AssetRecordType::ConfidentialAmount_ConfidentialAssetType
+                | AssetRecordType::ConfidentialAmount64_ConfidentialAssetType
+        )
+    }
+
+    /// Return true if the amount is hidden in a single commitment instead of a commitment
+    /// for each 32-bit half
+    pub fn is_single_commitment_amount(self) -> bool {
+        matches!(
+            self,
+            AssetRecordType::ConfidentialAmount64_ConfidentialAssetType
+                | AssetRecordType::ConfidentialAmount64_NonConfidentialAssetType
         )
     }
 
@@ -93,6 +119,7 @@ impl AssetRecord {
             identity_proofs: Vec::new(),
             asset_tracers_memos: Vec::new(),
             owner_memo: None,
+            owner_member_locks: Vec::new(),
         }
     }
 
@@ -114,28 +141,26 @@ impl AssetRecord {
                 return Err(eg!(ZeiError::ParameterError)); // should use from_open_asset_record_with_identity_tracing method
             }
 
-            let (amount_info, asset_type_info) =
-                if asset_tracing_policy.asset_tracing {
-                    let amount_info = match oar.get_record_type() {
-          AssetRecordType::NonConfidentialAmount_NonConfidentialAssetType
-          | AssetRecordType::NonConfidentialAmount_ConfidentialAssetType => None,
-          _ => {
-            let amount = u64_to_u32_pair(oar.amount);
-            Some((amount.0, amount.1, &oar.amount_blinds.0, &oar.amount_blinds.1))
-          }
-        };
-                    let asset_type_info = match oar.get_record_type() {
-          AssetRecordType::NonConfidentialAmount_NonConfidentialAssetType
-          | AssetRecordType::ConfidentialAmount_NonConfidentialAssetType => None,
-          _ => Some((&oar.asset_type, &oar.type_blind)),
-        };
-                    (amount_info, asset_type_info)
+            let record_type = oar.get_record_type();
+            let (amount_info, asset_type_info) = if asset_tracing_policy.asset_tracing {
+                let amount_info = if record_type.is_confidential_amount() {
+                    Some((oar.amount, &oar.amount_blinds))
                 } else {
-                    (None, None)
+                    None
                 };
-            let asset_tracer_memo = TracerMemo::new(
+                let asset_type_info = if record_type.is_confidential_asset_type() {
+                    Some((&oar.asset_type, &oar.type_blind))
+                } else {
+                    None
+                };
+                (amount_info, asset_type_info)
+            } else {
+                (None, None)
+            };
+            let asset_tracer_memo = sample_tracer_memo(
                 prng,
                 &asset_tracing_policy.enc_keys,
+                record_type,
                 amount_info,
                 asset_type_info,
                 &[],
@@ -149,6 +174,7 @@ impl AssetRecord {
             identity_proofs,
             asset_tracers_memos: memos,
             owner_memo: None,
+            owner_member_locks: vec![],
         })
     }
 
@@ -168,25 +194,22 @@ impl AssetRecord {
         let mut identity_proofs = vec![];
         for asset_tracing_policy in asset_tracing_policies.get_policies().iter() {
             // 1. compute tracer_memo
-            let (amount_info, asset_type_info) =
-                if asset_tracing_policy.asset_tracing {
-                    let amount_info = match oar.get_record_type() {
-          AssetRecordType::NonConfidentialAmount_NonConfidentialAssetType
-          | AssetRecordType::NonConfidentialAmount_ConfidentialAssetType => None,
-          _ => {
-            let amount = u64_to_u32_pair(oar.amount);
-            Some((amount.0, amount.1, &oar.amount_blinds.0, &oar.amount_blinds.1))
-          }
-        };
-                    let asset_type_info = match oar.get_record_type() {
-          AssetRecordType::NonConfidentialAmount_NonConfidentialAssetType
-          | AssetRecordType::ConfidentialAmount_NonConfidentialAssetType => None,
-          _ => Some((&oar.asset_type, &oar.type_blind)),
-        };
-                    (amount_info, asset_type_info)
+            let record_type = oar.get_record_type();
+            let (amount_info, asset_type_info) = if asset_tracing_policy.asset_tracing {
+                let amount_info = if record_type.is_confidential_amount() {
+                    Some((oar.amount, &oar.amount_blinds))
+                } else {
+                    None
+                };
+                let asset_type_info = if record_type.is_confidential_asset_type() {
+                    Some((&oar.asset_type, &oar.type_blind))
                 } else {
-                    (None, None)
+                    None
                 };
+                (amount_info, asset_type_info)
+            } else {
+                (None, None)
+            };
 
             let (attrs_and_ctexts, proof) =
                 match asset_tracing_policy.identity_tracing.as_ref() {
@@ -216,9 +239,10 @@ impl AssetRecord {
                     }
                     None => (vec![], None),
                 };
-            let asset_tracer_memo = TracerMemo::new(
+            let asset_tracer_memo = sample_tracer_memo(
                 prng,
                 &asset_tracing_policy.enc_keys,
+                record_type,
                 amount_info,
                 asset_type_info,
                 &attrs_and_ctexts,
@@ -232,6 +256,7 @@ impl AssetRecord {
             identity_proofs,
             asset_tracers_memos: memos,
             owner_memo: None,
+            owner_member_locks: vec![],
         })
     }
 
@@ -307,6 +332,7 @@ impl AssetRecordTemplate {
             public_key: address,
             asset_record_type,
             asset_tracing_policies: TracingPolicies::new(),
+            threshold_owner: None,
         }
     }
     pub fn with_asset_tracing(
@@ -325,7 +351,151 @@ impl AssetRecordTemplate {
         template.asset_tracing_policies = policies;
         template
     }
+
+    /// Creates a AssetRecordTemplate owned by a threshold policy, with no associated asset tracing policy
+    pub fn with_threshold_owner(
+        amount: u64,
+        asset_type: AssetType,
+        asset_record_type: AssetRecordType,
+        policy: XfrThresholdPolicy,
+    ) -> AssetRecordTemplate {
+        let mut template = AssetRecordTemplate::with_no_asset_tracing(
+            amount,
+            asset_type,
+            asset_record_type,
+            policy.address(),
+        );
+        template.threshold_owner = Some(policy);
+        template
+    }
+}
+
+/// Amount blinds of a record with a single amount commitment, given the blinds derived from its
+/// owner memo: the commitment is C_low + 2^32 * C_high so the blinds are (low + 2^32 * high, 0)
+pub(crate) fn single_commitment_amount_blinds(
+    blinds: (Scalar, Scalar),
+) -> (Scalar, Scalar) {
+    let pow2_32 = Scalar::from_u64(POW_2_32);
+    (blinds.0.add(&pow2_32.mul(&blinds.1)), Scalar::zero())
+}
+
+// Samples the tracer memo of a record, its amount being encrypted according to `record_type`
+fn sample_tracer_memo<R: CryptoRng + RngCore>(
+    prng: &mut R,
+    enc_keys: &AssetTracerEncKeys,
+    record_type: AssetRecordType,
+    amount_info: Option<(u64, &(Scalar, Scalar))>,
+    asset_type_info: Option<(&AssetType, &Scalar)>,
+    attrs_info: &[(Attr, AttributeCiphertext)],
+) -> TracerMemo {
+    if record_type.is_single_commitment_amount() {
+        let amount_info = amount_info.map(|(amount, blinds)| (amount, &blinds.0));
+        TracerMemo::new_with_amount64(
+            prng,
+            enc_keys,
+            amount_info,
+            asset_type_info,
+            attrs_info,
+        )
+    } else {
+        let amount_info = amount_info.map(|(amount, blinds)| {
+            let (amount_lo, amount_hi) = u64_to_u32_pair(amount);
+            (amount_lo, amount_hi, &blinds.0, &blinds.1)
+        });
+        TracerMemo::new(prng, enc_keys, amount_info, asset_type_info, attrs_info)
+    }
+}
+
+// Samples the owner memo of a record with a confidential amount and/or asset type.
+// Returns (OwnerMemo, member locks, (amount_blind_low, amount_blind_high), asset_type_blind),
+// member locks being empty unless the record is owned by a threshold policy and blinds of
+// non-confidential fields being zero
+fn sample_owner_memo<R: CryptoRng + RngCore>(
+    prng: &mut R,
+    asset_record: &AssetRecordTemplate,
+) -> Result<(OwnerMemo, Vec<ZeiHybridCipher>, (Scalar, Scalar), Scalar)> {
+    let (owner_memo, member_locks, mut amount_blinds, asset_type_blind) =
+        sample_split_amount_owner_memo(prng, asset_record).c(d!())?;
+    if asset_record.asset_record_type.is_single_commitment_amount() {
+        amount_blinds = single_commitment_amount_blinds(amount_blinds);
+    }
+    Ok((owner_memo, member_locks, amount_blinds, asset_type_blind))
+}
+
+// Samples the owner memo of a record as if its amount was committed in two 32-bit halves
+fn sample_split_amount_owner_memo<R: CryptoRng + RngCore>(
+    prng: &mut R,
+    asset_record: &AssetRecordTemplate,
+) -> Result<(OwnerMemo, Vec<ZeiHybridCipher>, (Scalar, Scalar), Scalar)> {
+    let record_type = asset_record.asset_record_type;
+    if let Some(policy) = asset_record.threshold_owner.as_ref() {
+        let amount = if record_type.is_confidential_amount() {
+            Some(asset_record.amount)
+        } else {
+            None
+        };
+        let asset_type = if record_type.is_confidential_asset_type() {
+            Some(&asset_record.asset_type)
+        } else {
+            None
+        };
+        return OwnerMemo::for_threshold_owner(prng, amount, asset_type, policy).c(d!());
+    }
+    let zero_blinds = (Scalar::zero(), Scalar::zero());
+    match record_type {
+        AssetRecordType::ConfidentialAmount_NonConfidentialAssetType
+        | AssetRecordType::ConfidentialAmount64_NonConfidentialAssetType => {
+            OwnerMemo::from_amount(prng, asset_record.amount, &asset_record.public_key)
+                .map(|(memo, amount_blinds)| {
+                    (memo, vec![], amount_blinds, Scalar::zero())
+                })
+        }
+        AssetRecordType::NonConfidentialAmount_ConfidentialAssetType => {
+            OwnerMemo::from_asset_type(
+                prng,
+                &asset_record.asset_type,
+                &asset_record.public_key,
+            )
+            .map(|(memo, asset_type_blind)| {
+                (memo, vec![], zero_blinds, asset_type_blind)
+            })
+        }
+        AssetRecordType::ConfidentialAmount_ConfidentialAssetType
+        | AssetRecordType::ConfidentialAmount64_ConfidentialAssetType => {
+            OwnerMemo::from_amount_and_asset_type(
+                prng,
+                asset_record.amount,
+                &asset_record.asset_type,
+                &asset_record.public_key,
+            )
+            .map(|(memo, amount_blinds, asset_type_blind)| {
+                (memo, vec![], amount_blinds, asset_type_blind)
+            })
+        }
+        AssetRecordType::NonConfidentialAmount_NonConfidentialAssetType => {
+            Err(eg!(ZeiError::ParameterError))
+        }
+    }
+}
+
+// Commits to the amount of a record with a confidential amount
+fn amount_commitments(
+    pc_gens: &RistrettoPedersenGens,
+    asset_record: &AssetRecordTemplate,
+    amount_blinds: &(Scalar, Scalar),
+) -> XfrAmount {
+    if asset_record.asset_record_type.is_single_commitment_amount() {
+        XfrAmount::from_single_blind(pc_gens, asset_record.amount, &amount_blinds.0)
+    } else {
+        XfrAmount::from_blinds(
+            pc_gens,
+            asset_record.amount,
+            &amount_blinds.0,
+            &amount_blinds.1,
+        )
+    }
 }
+
 fn sample_blind_asset_record<R: CryptoRng + RngCore>(
     prng: &mut R,
     pc_gens: &RistrettoPedersenGens,
@@ -337,6 +507,7 @@ fn sample_blind_asset_record<R: CryptoRng + RngCore>(
     Scalar,
     Vec<TracerMemo>,
     Option<OwnerMemo>,
+    Vec<ZeiHybridCipher>,
 ) {
     // use enum matching instead of nested if else clause for readability and clarity
     let (xfr_amount, xfr_asset_type, amount_blinds, asset_type_blind, owner_memo) =
@@ -349,35 +520,23 @@ fn sample_blind_asset_record<R: CryptoRng + RngCore>(
                 None,
             ),
 
-            AssetRecordType::ConfidentialAmount_NonConfidentialAssetType => {
-                let (owner_memo, amount_blinds) = OwnerMemo::from_amount(
-                    prng,
-                    asset_record.amount,
-                    &asset_record.public_key,
-                )
-                .unwrap(); // safe unwrap
+            AssetRecordType::ConfidentialAmount_NonConfidentialAssetType
+            | AssetRecordType::ConfidentialAmount64_NonConfidentialAssetType => {
+                let (owner_memo, member_locks, amount_blinds, _) =
+                    sample_owner_memo(prng, asset_record).unwrap(); // safe unwrap
 
                 (
-                    XfrAmount::from_blinds(
-                        &pc_gens,
-                        asset_record.amount,
-                        &amount_blinds.0,
-                        &amount_blinds.1,
-                    ),
+                    amount_commitments(pc_gens, asset_record, &amount_blinds),
                     XfrAssetType::NonConfidential(asset_record.asset_type),
                     amount_blinds,
                     Scalar::zero(),
-                    Some(owner_memo),
+                    Some((owner_memo, member_locks)),
                 )
             }
 
             AssetRecordType::NonConfidentialAmount_ConfidentialAssetType => {
-                let (owner_memo, asset_type_blind) = OwnerMemo::from_asset_type(
-                    prng,
-                    &asset_record.asset_type,
-                    &asset_record.public_key,
-                )
-                .unwrap(); //safe unwrap
+                let (owner_memo, member_locks, _, asset_type_blind) =
+                    sample_owner_memo(prng, asset_record).unwrap(); //safe unwrap
 
                 (
                     XfrAmount::NonConfidential(asset_record.amount),
@@ -388,26 +547,16 @@ fn sample_blind_asset_record<R: CryptoRng + RngCore>(
                     ),
                     (Scalar::zero(), Scalar::zero()),
                     asset_type_blind,
-                    Some(owner_memo),
+                    Some((owner_memo, member_locks)),
                 )
             }
 
-            AssetRecordType::ConfidentialAmount_ConfidentialAssetType => {
-                let (owner_memo, amount_blinds, asset_type_blind) =
-                    OwnerMemo::from_amount_and_asset_type(
-                        prng,
-                        asset_record.amount,
-                        &asset_record.asset_type,
-                        &asset_record.public_key,
-                    )
-                    .unwrap(); //safe unwrap
+            AssetRecordType::ConfidentialAmount_ConfidentialAssetType
+            | AssetRecordType::ConfidentialAmount64_ConfidentialAssetType => {
+                let (owner_memo, member_locks, amount_blinds, asset_type_blind) =
+                    sample_owner_memo(prng, asset_record).unwrap(); //safe unwrap
                 (
-                    XfrAmount::from_blinds(
-                        &pc_gens,
-                        asset_record.amount,
-                        &amount_blinds.0,
-                        &amount_blinds.1,
-                    ),
+                    amount_commitments(pc_gens, asset_record, &amount_blinds),
                     XfrAssetType::from_blind(
                         &pc_gens,
                         &asset_record.asset_type,
@@ -415,10 +564,14 @@ fn sample_blind_asset_record<R: CryptoRng + RngCore>(
                     ),
                     amount_blinds,
                     asset_type_blind,
-                    Some(owner_memo),
+                    Some((owner_memo, member_locks)),
                 )
             }
         };
+    let (owner_memo, member_locks) = match owner_memo {
+        Some((owner_memo, member_locks)) => (Some(owner_memo), member_locks),
+        None => (None, vec![]),
+    };
     let blind_asset_record = BlindAssetRecord {
         public_key: asset_record.public_key,
         amount: xfr_amount,
@@ -433,17 +586,16 @@ fn sample_blind_asset_record<R: CryptoRng + RngCore>(
         let mut asset_type_info = None;
         if policy.asset_tracing {
             if asset_record.asset_record_type.is_confidential_amount() {
-                let (amount_lo, amount_hi) = utils::u64_to_u32_pair(asset_record.amount);
-                amount_info =
-                    Some((amount_lo, amount_hi, &amount_blinds.0, &amount_blinds.1));
+                amount_info = Some((asset_record.amount, &amount_blinds));
             }
             if asset_record.asset_record_type.is_confidential_asset_type() {
                 asset_type_info = Some((&asset_record.asset_type, &asset_type_blind));
             }
         }
-        let memo = TracerMemo::new(
+        let memo = sample_tracer_memo(
             prng,
             &policy.enc_keys,
+            asset_record.asset_record_type,
             amount_info,
             asset_type_info,
             &attr_ctexts,
@@ -456,12 +608,15 @@ fn sample_blind_asset_record<R: CryptoRng + RngCore>(
         asset_type_blind,
         tracer_memos,
         owner_memo,
+        member_locks,
     )
 }
 
 /// Build OpenAssetRecord and associated memos from an Asset Record Template
 /// and encrypted identity attributes to confidentially reveal (if policy indicates so).
 /// Used to create outputs blind asset record from an asset record template.
+/// The locks of the other members of a threshold policy owning the record are dropped, such
+/// records should be built with `AssetRecord::from_template_*`.
 /// Return:
 ///  - OpenAssetRecord,
 ///  - Option<TracerMemo> // Some(memo) if required by asset_record.asset_tracing policy
@@ -472,8 +627,32 @@ pub fn build_open_asset_record<R: CryptoRng + RngCore>(
     asset_record: &AssetRecordTemplate,
     attrs_and_ctexts: Vec<Vec<(Attr, AttributeCiphertext)>>,
 ) -> (OpenAssetRecord, Vec<TracerMemo>, Option<OwnerMemo>) {
-    let (blind_asset_record, amount_blinds, type_blind, asset_tracing_memos, owner_memo) =
-        sample_blind_asset_record(prng, pc_gens, asset_record, attrs_and_ctexts);
+    let (open_asset_record, asset_tracing_memos, owner_memo, _) =
+        sample_open_asset_record(prng, pc_gens, asset_record, attrs_and_ctexts);
+    (open_asset_record, asset_tracing_memos, owner_memo)
+}
+
+// Same as `build_open_asset_record`, also returning the locks of the other members of the
+// threshold policy owning the record
+fn sample_open_asset_record<R: CryptoRng + RngCore>(
+    prng: &mut R,
+    pc_gens: &RistrettoPedersenGens,
+    asset_record: &AssetRecordTemplate,
+    attrs_and_ctexts: Vec<Vec<(Attr, AttributeCiphertext)>>,
+) -> (
+    OpenAssetRecord,
+    Vec<TracerMemo>,
+    Option<OwnerMemo>,
+    Vec<ZeiHybridCipher>,
+) {
+    let (
+        blind_asset_record,
+        amount_blinds,
+        type_blind,
+        asset_tracing_memos,
+        owner_memo,
+        member_locks,
+    ) = sample_blind_asset_record(prng, pc_gens, asset_record, attrs_and_ctexts);
 
     let open_asset_record = OpenAssetRecord {
         blind_asset_record,
@@ -483,12 +662,19 @@ pub fn build_open_asset_record<R: CryptoRng + RngCore>(
         type_blind,
     };
 
-    (open_asset_record, asset_tracing_memos, owner_memo)
+    (
+        open_asset_record,
+        asset_tracing_memos,
+        owner_memo,
+        member_locks,
+    )
 }
 
 /// Build BlindAssetRecord and associated memos  from an Asset Record Template
 /// and encrypted identity attributes to confidentially reveal (if policy indicates so).
 /// Used to create outputs blind asset record from an asset record template.
+/// The locks of the other members of a threshold policy owning the record are dropped, such
+/// records should be built with `AssetRecord::from_template_*`.
 /// Return:
 ///  - BlindAssetRecord,
 ///  - Option<TracerMemo> // Some(memo) if required by asset_record.asset_tracing policy
@@ -499,7 +685,7 @@ pub fn build_blind_asset_record<R: CryptoRng + RngCore>(
     asset_record: &AssetRecordTemplate,
     attrs_and_ctexts: Vec<Vec<(Attr, AttributeCiphertext)>>,
 ) -> (BlindAssetRecord, Vec<TracerMemo>, Option<OwnerMemo>) {
-    let (blind_asset_record, _, _, asset_tracing_memos, owner_memo) =
+    let (blind_asset_record, _, _, asset_tracing_memos, owner_memo, _) =
         sample_blind_asset_record(prng, pc_gens, asset_record, attrs_and_ctexts);
 
     (blind_asset_record, asset_tracing_memos, owner_memo)
@@ -514,7 +700,8 @@ pub fn open_blind_asset_record(
     owner_memo: &Option<OwnerMemo>,
     keypair: &XfrKeyPair,
 ) -> Result<OpenAssetRecord> {
-    let (amount, asset_type, amount_blinds, type_blind) = match input.get_record_type() {
+    let record_type = input.get_record_type();
+    let (amount, asset_type, mut amount_blinds, type_blind) = match record_type {
         AssetRecordType::NonConfidentialAmount_NonConfidentialAssetType => (
             input.amount.get_amount().c(d!(ZeiError::ParameterError))?,
             input
@@ -525,7 +712,8 @@ pub fn open_blind_asset_record(
             Scalar::zero(),
         ),
 
-        AssetRecordType::ConfidentialAmount_NonConfidentialAssetType => {
+        AssetRecordType::ConfidentialAmount_NonConfidentialAssetType
+        | AssetRecordType::ConfidentialAmount64_NonConfidentialAssetType => {
             let owner_memo = owner_memo.as_ref().c(d!(ZeiError::ParameterError))?;
             let amount = owner_memo.decrypt_amount(&keypair).c(d!())?;
             let amount_blinds = owner_memo.derive_amount_blinds(&keypair).c(d!())?;
@@ -553,7 +741,8 @@ pub fn open_blind_asset_record(
             )
         }
 
-        AssetRecordType::ConfidentialAmount_ConfidentialAssetType => {
+        AssetRecordType::ConfidentialAmount_ConfidentialAssetType
+        | AssetRecordType::ConfidentialAmount64_ConfidentialAssetType => {
             let owner_memo = owner_memo.as_ref().c(d!(ZeiError::ParameterError))?;
             let (amount, asset_type) =
                 owner_memo.decrypt_amount_and_asset_type(&keypair).c(d!())?;
@@ -564,6 +753,9 @@ pub fn open_blind_asset_record(
             (amount, asset_type, amount_blinds, asset_type_blind)
         }
     };
+    if record_type.is_single_commitment_amount() {
+        amount_blinds = single_commitment_amount_blinds(amount_blinds);
+    }
     // TODO check correctness of BlindAssetRecord
     Ok(OpenAssetRecord {
         blind_asset_record: input.clone(),
@@ -607,8 +799,8 @@ fn build_record_input_from_template<R: CryptoRng + RngCore>(
         attrs_ctexts.push(attrs_and_ctexts);
         reveal_proofs.push(reveal_proof);
     }
-    let (open_asset_record, asset_tracing_memos, owner_memo) =
-        build_open_asset_record(prng, &pc_gens, asset_record, attrs_ctexts);
+    let (open_asset_record, asset_tracing_memos, owner_memo, owner_member_locks) =
+        sample_open_asset_record(prng, &pc_gens, asset_record, attrs_ctexts);
 
     Ok(AssetRecord {
         open_asset_record,
@@ -616,6 +808,7 @@ fn build_record_input_from_template<R: CryptoRng + RngCore>(
         identity_proofs: reveal_proofs,
         asset_tracers_memos: asset_tracing_memos,
         owner_memo,
+        owner_member_locks,
     })
 }
 
@@ -4,10 +4,11 @@ use crate::xfr::structs::{AssetType, ASSET_TYPE_LENGTH};
 use algebra::bls12_381::{BLSScalar, BLSG1};
 use algebra::groups::{Group, GroupArithmetic, Scalar as ZeiScalar};
 use algebra::ristretto::{RistrettoPoint, RistrettoScalar as Scalar};
+use crypto::basics::bsgs::BsgsTable;
 use crypto::basics::commitments::ristretto_pedersen::RistrettoPedersenGens;
 use crypto::basics::elgamal::{
-    elgamal_decrypt, elgamal_decrypt_elem, elgamal_encrypt, ElGamalCiphertext,
-    ElGamalDecKey, ElGamalEncKey,
+    elgamal_decrypt_elem, elgamal_decrypt_with_table, elgamal_encrypt,
+    ElGamalCiphertext, ElGamalDecKey, ElGamalEncKey,
 };
 use crypto::basics::hybrid_encryption::{
     hybrid_decrypt_with_x25519_secret_key, hybrid_encrypt_with_x25519_key,
@@ -24,6 +25,38 @@ type DecryptedAssetMemo = (Option<u64>, Option<AssetType>, Vec<Attr>);
 
 const U32_BYTES: usize = 4;
 
+/// Number of baby steps (log2) of the tables built by the brute force decryption functions
+pub const TRACER_TABLE_BITS: usize = 16;
+
+/// Precomputed tables decrypting the 32-bit amounts and identity attributes of tracer memos.
+/// Building them once and reusing them (or serializing them) avoids paying the precomputation
+/// on every decryption
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TracerDecryptionTables {
+    pub amount: BsgsTable<RistrettoPoint>,
+    pub attributes: BsgsTable<BLSG1>,
+}
+
+impl TracerDecryptionTables {
+    /// Tables with 2^table_bits baby steps, decrypting a value in at most 2^(32 - table_bits)
+    /// giant steps.
+    /// Returns ZeiError::ParameterError if table_bits > 32
+    pub fn new(table_bits: usize) -> Result<Self> {
+        Ok(TracerDecryptionTables {
+            amount: amount_table(table_bits).c(d!())?,
+            attributes: attributes_table(table_bits).c(d!())?,
+        })
+    }
+}
+
+fn amount_table(table_bits: usize) -> Result<BsgsTable<RistrettoPoint>> {
+    BsgsTable::new(&RistrettoPoint::get_base(), 32, table_bits).c(d!())
+}
+
+fn attributes_table(table_bits: usize) -> Result<BsgsTable<BLSG1>> {
+    BsgsTable::new(&BLSG1::get_base(), 32, table_bits).c(d!())
+}
+
 impl TracerMemo {
     /// Sample a new TracerMemo
     /// amount_info is (amount_low, amount_high, amount_blind_low, amount_blind_high) tuple
@@ -34,22 +67,75 @@ impl TracerMemo {
         amount_info: Option<(u32, u32, &Scalar, &Scalar)>,
         asset_type_info: Option<(&AssetType, &Scalar)>,
         attrs_info: &[(Attr, AttributeCiphertext)],
+    ) -> Self {
+        let amount_info =
+            amount_info.map(|(amount_low, amount_high, blind_low, blind_high)| {
+                (
+                    (amount_low as u64) + ((amount_high as u64) << 32),
+                    (Scalar::from_u32(amount_low), blind_low),
+                    (Scalar::from_u32(amount_high), blind_high),
+                )
+            });
+        Self::sample(
+            prng,
+            tracer_enc_key,
+            amount_info,
+            asset_type_info,
+            attrs_info,
+        )
+    }
+
+    /// Sample a new TracerMemo for a record whose amount is hidden in a single commitment
+    /// amount_info is (amount, amount_blind) tuple. The amount is encrypted as a whole in the low
+    /// ciphertext and the high ciphertext encrypts zero, so the tracer recovers the amount from
+    /// the hybrid encrypted hint in lock_info rather than by brute force.
+    /// asset_type_info is (asset_type, asset_type_blind) tuple
+    pub fn new_with_amount64<R: CryptoRng + RngCore>(
+        prng: &mut R,
+        tracer_enc_key: &AssetTracerEncKeys,
+        amount_info: Option<(u64, &Scalar)>,
+        asset_type_info: Option<(&AssetType, &Scalar)>,
+        attrs_info: &[(Attr, AttributeCiphertext)],
+    ) -> Self {
+        let zero = Scalar::from_u32(0);
+        let amount_info = amount_info.map(|(amount, blind)| {
+            (amount, (Scalar::from_u64(amount), blind), (zero, &zero))
+        });
+        Self::sample(
+            prng,
+            tracer_enc_key,
+            amount_info,
+            asset_type_info,
+            attrs_info,
+        )
+    }
+
+    // amount_info is (amount, (value_low, blind_low), (value_high, blind_high)) where the values
+    // are the ones committed in the record
+    #[allow(clippy::type_complexity)]
+    fn sample<R: CryptoRng + RngCore>(
+        prng: &mut R,
+        tracer_enc_key: &AssetTracerEncKeys,
+        amount_info: Option<(u64, (Scalar, &Scalar), (Scalar, &Scalar))>,
+        asset_type_info: Option<(&AssetType, &Scalar)>,
+        attrs_info: &[(Attr, AttributeCiphertext)],
     ) -> Self {
         let mut plaintext = vec![];
         let pc_gens = RistrettoPedersenGens::default();
         let lock_amount =
-            amount_info.map(|(amount_low, amount_high, blind_low, blind_high)| {
+            amount_info.map(|(amount, (low, blind_low), (high, blind_high))| {
+                let (amount_low, amount_high) = u64_to_u32_pair(amount);
                 plaintext.extend_from_slice(&amount_low.to_be_bytes());
                 plaintext.extend_from_slice(&amount_high.to_be_bytes());
                 let ctext_amount_low = elgamal_encrypt(
                     &pc_gens.B,
-                    &Scalar::from_u32(amount_low),
+                    &low,
                     blind_low,
                     &tracer_enc_key.record_data_enc_key,
                 );
                 let ctext_amount_high = elgamal_encrypt(
                     &pc_gens.B,
-                    &Scalar::from_u32(amount_high),
+                    &high,
                     blind_high,
                     &tracer_enc_key.record_data_enc_key,
                 );
@@ -144,7 +230,8 @@ impl TracerMemo {
         Ok((amount, asset_type, attrs))
     }
 
-    /// Check if the amount encrypted in self.lock_amount is expected
+    /// Check if the amount encrypted in self.lock_amount is expected, either split in two 32-bit
+    /// halves or as a whole (see `TracerMemo::new_with_amount64`)
     /// If self.lock_amount is None, return Err(ZeiError::ParameterError)
     /// Otherwise, if decrypted amount is not expected amount, return Err(ZeiError::AssetTracingExtractionError), else Ok(())
     pub fn verify_amount(
@@ -157,12 +244,14 @@ impl TracerMemo {
             let decrypted_low = elgamal_decrypt_elem(ctext_low, dec_key);
             let decrypted_high = elgamal_decrypt_elem(ctext_high, dec_key);
             let base = RistrettoPoint::get_base();
-            if base.mul(&Scalar::from_u32(low)) != decrypted_low
-                || base.mul(&Scalar::from_u32(high)) != decrypted_high
-            {
-                Err(eg!(ZeiError::AssetTracingExtractionError))
-            } else {
+            let split_amount = base.mul(&Scalar::from_u32(low)) == decrypted_low
+                && base.mul(&Scalar::from_u32(high)) == decrypted_high;
+            let single_amount = base.mul(&Scalar::from_u64(expected)) == decrypted_low
+                && RistrettoPoint::get_identity() == decrypted_high;
+            if split_amount || single_amount {
                 Ok(())
+            } else {
+                Err(eg!(ZeiError::AssetTracingExtractionError))
             }
         } else {
             Err(eg!(ZeiError::ParameterError)) // nothing to decrypt
@@ -187,17 +276,36 @@ impl TracerMemo {
         }
     }
 
-    /// Decrypt amount in self.lock_amount via brute force check taking 2^33 Ristretto additions in the worst case.
+    /// Decrypt amount in self.lock_amount via brute force, building a table with
+    /// 2^TRACER_TABLE_BITS baby steps. Use `TracerMemo::extract_amount_with_table` to reuse a table.
+    /// Amounts of records with a single commitment can only be brute forced if they are less
+    /// than 2^32, use `TracerMemo::decrypt` otherwise.
     /// If self.lock_amount is None, return Err(ZeiError::ParameterError)
     /// Otherwise, return Scalar representing the amount
     pub fn extract_amount_brute_force(
         &self,
         dec_key: &ElGamalDecKey<Scalar>,
+    ) -> Result<u64> {
+        if self.lock_amount.is_none() {
+            return Err(eg!(ZeiError::ParameterError)); // nothing to decrypt
+        }
+        let table = amount_table(TRACER_TABLE_BITS).c(d!())?;
+        self.extract_amount_with_table(dec_key, &table).c(d!())
+    }
+
+    /// Decrypt amount in self.lock_amount with a baby-step giant-step table for 32-bit values.
+    /// If self.lock_amount is None, return Err(ZeiError::ParameterError)
+    /// Otherwise, return Scalar representing the amount
+    pub fn extract_amount_with_table(
+        &self,
+        dec_key: &ElGamalDecKey<Scalar>,
+        table: &BsgsTable<RistrettoPoint>,
     ) -> Result<u64> {
         if let Some((ctext_low, ctext_high)) = self.lock_amount.as_ref() {
-            let base = RistrettoPoint::get_base();
-            let decrypted_low = elgamal_decrypt(&base, ctext_low, dec_key).c(d!())?;
-            let decrypted_high = elgamal_decrypt(&base, ctext_high, dec_key).c(d!())?;
+            let decrypted_low =
+                elgamal_decrypt_with_table(ctext_low, dec_key, table).c(d!())?;
+            let decrypted_high =
+                elgamal_decrypt_with_table(ctext_high, dec_key, table).c(d!())?;
             let result = decrypted_low + decrypted_high * (1u64 << 32);
             Ok(result)
         } else {
@@ -257,11 +365,24 @@ impl TracerMemo {
     pub fn extract_identity_attributes_brute_force(
         &self,
         dec_key: &ElGamalDecKey<BLSScalar>,
+    ) -> Result<Vec<u32>> {
+        if self.lock_attributes.is_empty() {
+            return Ok(vec![]);
+        }
+        let table = attributes_table(TRACER_TABLE_BITS).c(d!())?;
+        self.extract_identity_attributes_with_table(dec_key, &table)
+            .c(d!())
+    }
+
+    /// Decrypt the attributes in self.lock_attrs with a baby-step giant-step table for 32-bit values
+    pub fn extract_identity_attributes_with_table(
+        &self,
+        dec_key: &ElGamalDecKey<BLSScalar>,
+        table: &BsgsTable<BLSG1>,
     ) -> Result<Vec<u32>> {
         let mut result = vec![];
-        let base = BLSG1::get_base();
         for ctext in self.lock_attributes.iter() {
-            let attr = elgamal_decrypt(&base, ctext, dec_key).c(d!())? as u32;
+            let attr = elgamal_decrypt_with_table(ctext, dec_key, table).c(d!())? as u32;
             result.push(attr);
         }
         Ok(result)
@@ -270,6 +391,7 @@ impl TracerMemo {
 
 #[cfg(test)]
 mod tests {
+    use crate::xfr::asset_tracer::TracerDecryptionTables;
     use crate::xfr::structs::{AssetTracerKeyPair, AssetType, TracerMemo};
     use algebra::bls12_381::{BLSScalar, BLSG1};
     use algebra::groups::{Group, Scalar as ZeiScalar};
@@ -314,6 +436,59 @@ mod tests {
             .is_ok());
     }
 
+    #[test]
+    fn extract_from_tracer_memo_with_tables() {
+        let mut prng = ChaChaRng::from_seed([0u8; 32]);
+        let tracer_keys = AssetTracerKeyPair::generate(&mut prng);
+        let tables = TracerDecryptionTables::new(12).unwrap();
+        // tables can be precomputed and loaded by the tracer
+        let tables: TracerDecryptionTables =
+            serde_json::from_str(&serde_json::to_string(&tables).unwrap()).unwrap();
+
+        let amount = (7u64 << 32) + 123_456_789;
+        let (low, high) = u64_to_u32_pair(amount);
+        let attr = 70_000u32;
+        let ctext = elgamal_encrypt(
+            &BLSG1::get_base(),
+            &BLSScalar::from_u32(attr),
+            &BLSScalar::from_u32(1000u32),
+            &tracer_keys.enc_key.attrs_enc_key,
+        );
+        let memo = TracerMemo::new(
+            &mut prng,
+            &tracer_keys.enc_key,
+            Some((
+                low,
+                high,
+                &Scalar::from_u32(191919u32),
+                &Scalar::from_u32(2222u32),
+            )),
+            None,
+            &[(attr, ctext)],
+        );
+        assert_eq!(
+            memo.extract_amount_with_table(
+                &tracer_keys.dec_key.record_data_dec_key,
+                &tables.amount
+            )
+            .unwrap(),
+            amount
+        );
+        assert_eq!(
+            memo.extract_identity_attributes_with_table(
+                &tracer_keys.dec_key.attrs_dec_key,
+                &tables.attributes
+            )
+            .unwrap(),
+            vec![attr]
+        );
+
+        err_eq!(
+            ZeiError::ParameterError,
+            TracerDecryptionTables::new(33).unwrap_err()
+        );
+    }
+
     #[test]
     fn extract_asset_type_from_tracer_memo() {
         let mut prng = ChaChaRng::from_seed([0u8; 32]);
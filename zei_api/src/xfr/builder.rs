@@ -0,0 +1,465 @@
+use crate::api::anon_creds::{
+    ACCommitment, ACCommitmentKey, ACUserSecretKey, Credential,
+};
+use crate::xfr::asset_record::AssetRecordType;
+use crate::xfr::coin_selection::{
+    select_coins_per_asset_type, CoinSelectionStrategy, InputCount,
+};
+use crate::xfr::lib::{gen_xfr_note, XfrNotePolicies};
+use crate::xfr::sig::{XfrKeyPair, XfrPublicKey};
+use crate::xfr::structs::{
+    AssetRecord, AssetRecordTemplate, AssetType, OpenAssetRecord, TracingPolicies,
+    XfrNote,
+};
+use itertools::Itertools;
+use rand_core::{CryptoRng, RngCore};
+use ruc::*;
+use std::collections::HashMap;
+use utils::errors::ZeiError;
+
+/// Credential of the sender, used for the identity tracing policies of its inputs and of the
+/// outputs it receives (e.g. change)
+struct SenderCredential<'a> {
+    user_sec_key: &'a ACUserSecretKey,
+    credential: &'a Credential,
+    commitment_key: &'a ACCommitmentKey,
+    commitment: &'a ACCommitment,
+}
+
+/// Builds a transfer note from the spendable records of a sender and a list of payments.
+/// For each asset type, inputs are selected with a coin selection strategy, by default in the
+/// order the records were added, until the payments are covered, and the remaining amount is
+/// sent back to the sender in a change output.
+/// The tracing policies registered for an asset type are attached to every input and output of
+/// that asset type.
+pub struct XfrNoteBuilder<'a> {
+    key_pair: &'a XfrKeyPair,
+    spendable: Vec<OpenAssetRecord>,
+    payments: Vec<AssetRecordTemplate>,
+    tracing_policies: HashMap<AssetType, TracingPolicies>,
+    change_record_type: Option<AssetRecordType>,
+    coin_selection: CoinSelectionStrategy,
+    credential: Option<SenderCredential<'a>>,
+}
+
+impl<'a> XfrNoteBuilder<'a> {
+    /// Creates a builder for a transfer spending records owned by `key_pair`
+    pub fn new(key_pair: &'a XfrKeyPair) -> Self {
+        XfrNoteBuilder {
+            key_pair,
+            spendable: vec![],
+            payments: vec![],
+            tracing_policies: HashMap::new(),
+            change_record_type: None,
+            coin_selection: CoinSelectionStrategy::InOrder,
+            credential: None,
+        }
+    }
+
+    /// Adds a record owned by the sender that can be spent by the transfer
+    pub fn add_spendable(&mut self, record: OpenAssetRecord) -> &mut Self {
+        self.spendable.push(record);
+        self
+    }
+
+    /// Pays `amount` of `asset_type` to `address` in a record of type `record_type`
+    pub fn add_payment(
+        &mut self,
+        amount: u64,
+        asset_type: AssetType,
+        record_type: AssetRecordType,
+        address: XfrPublicKey,
+    ) -> &mut Self {
+        self.payments
+            .push(AssetRecordTemplate::with_no_asset_tracing(
+                amount,
+                asset_type,
+                record_type,
+                address,
+            ));
+        self
+    }
+
+    /// Sets the tracing policies of every input and output of `asset_type`
+    pub fn with_tracing_policies(
+        &mut self,
+        asset_type: AssetType,
+        policies: TracingPolicies,
+    ) -> &mut Self {
+        self.tracing_policies.insert(asset_type, policies);
+        self
+    }
+
+    /// Sets the record type of the change outputs. By default, the change of an asset type
+    /// has the record type of the first input selected for it
+    pub fn with_change_record_type(
+        &mut self,
+        record_type: AssetRecordType,
+    ) -> &mut Self {
+        self.change_record_type = Some(record_type);
+        self
+    }
+
+    /// Sets the strategy selecting the spendable records used as inputs
+    pub fn with_coin_selection(&mut self, strategy: CoinSelectionStrategy) -> &mut Self {
+        self.coin_selection = strategy;
+        self
+    }
+
+    /// Sets the credential of the sender, needed if some policy requires identity tracing.
+    /// `commitment` is the credential commitment used to verify the note
+    pub fn with_credential(
+        &mut self,
+        user_sec_key: &'a ACUserSecretKey,
+        credential: &'a Credential,
+        commitment_key: &'a ACCommitmentKey,
+        commitment: &'a ACCommitment,
+    ) -> &mut Self {
+        self.credential = Some(SenderCredential {
+            user_sec_key,
+            credential,
+            commitment_key,
+            commitment,
+        });
+        self
+    }
+
+    /// Selects the inputs, computes the change and generates the note.
+    /// Returns the note with the policies needed to verify it, or
+    /// ZeiError::XfrCreationAssetAmountError if the spendable records do not cover the payments and
+    /// ZeiError::ParameterError if identity tracing is required for a payment to another key or
+    /// without the credential of the sender
+    pub fn build<R: CryptoRng + RngCore>(
+        &self,
+        prng: &mut R,
+    ) -> Result<(XfrNote, XfrNotePolicies)> {
+        if self.payments.is_empty() {
+            return Err(eg!(ZeiError::ParameterError));
+        }
+        let payments = self
+            .payments
+            .iter()
+            .map(|payment| (payment.asset_type, payment.amount))
+            .collect_vec();
+        let selections = select_coins_per_asset_type(
+            prng,
+            &self.spendable,
+            &payments,
+            self.coin_selection,
+            &InputCount::Any,
+        )
+        .c(d!())?;
+
+        let mut inputs = vec![];
+        let mut change = vec![];
+        for selection in selections {
+            let selected = selection
+                .inputs
+                .iter()
+                .map(|i| &self.spendable[*i])
+                .collect_vec();
+            if selection.change > 0 {
+                let change_record_type = match self.change_record_type {
+                    Some(record_type) => record_type,
+                    None => selected
+                        .first()
+                        .c(d!(ZeiError::XfrCreationAssetAmountError))?
+                        .get_record_type(),
+                };
+                change.push(AssetRecordTemplate::with_no_asset_tracing(
+                    selection.change,
+                    selection.asset_type,
+                    change_record_type,
+                    self.key_pair.pub_key,
+                ));
+            }
+            inputs.extend(selected);
+        }
+
+        let mut policies = XfrNotePolicies::new(vec![], vec![], vec![], vec![]);
+        let mut input_records = vec![];
+        for record in inputs {
+            let (record, tracing_policies, commitment) =
+                self.input_record(prng, record).c(d!())?;
+            input_records.push(record);
+            policies.inputs_tracing_policies.push(tracing_policies);
+            policies.inputs_sig_commitments.push(commitment);
+        }
+        let mut output_records = vec![];
+        for template in self.payments.iter().chain(change.iter()) {
+            let (record, tracing_policies, commitment) =
+                self.output_record(prng, template).c(d!())?;
+            output_records.push(record);
+            policies.outputs_tracing_policies.push(tracing_policies);
+            policies.outputs_sig_commitments.push(commitment);
+        }
+
+        let input_keys = vec![self.key_pair; input_records.len()];
+        let note =
+            gen_xfr_note(prng, &input_records, &output_records, &input_keys).c(d!())?;
+        Ok((note, policies))
+    }
+
+    // Returns the tracing policies of `asset_type` and whether they require identity tracing
+    fn policies(&self, asset_type: &AssetType) -> (TracingPolicies, bool) {
+        let policies = self
+            .tracing_policies
+            .get(asset_type)
+            .cloned()
+            .unwrap_or_default();
+        let identity_tracing = policies
+            .get_policies()
+            .iter()
+            .any(|policy| policy.identity_tracing.is_some());
+        (policies, identity_tracing)
+    }
+
+    fn sender_credential(&self) -> Result<&SenderCredential<'a>> {
+        self.credential.as_ref().c(d!(ZeiError::ParameterError))
+    }
+
+    fn input_record<R: CryptoRng + RngCore>(
+        &self,
+        prng: &mut R,
+        record: &OpenAssetRecord,
+    ) -> Result<(AssetRecord, TracingPolicies, Option<ACCommitment>)> {
+        let (policies, identity_tracing) = self.policies(&record.asset_type);
+        if policies.is_empty() {
+            let record =
+                AssetRecord::from_open_asset_record_no_asset_tracing(record.clone());
+            return Ok((record, policies, None));
+        }
+        if !identity_tracing {
+            let record =
+                AssetRecord::from_open_asset_record_with_asset_tracing_but_no_identity(
+                    prng,
+                    record.clone(),
+                    policies.clone(),
+                )
+                .c(d!())?;
+            return Ok((record, policies, None));
+        }
+        let credential = self.sender_credential().c(d!())?;
+        let record = AssetRecord::from_open_asset_record_with_tracing(
+            prng,
+            record.clone(),
+            policies.clone(),
+            credential.user_sec_key,
+            credential.credential,
+            credential.commitment_key,
+        )
+        .c(d!())?;
+        Ok((record, policies, Some(credential.commitment.clone())))
+    }
+
+    fn output_record<R: CryptoRng + RngCore>(
+        &self,
+        prng: &mut R,
+        template: &AssetRecordTemplate,
+    ) -> Result<(AssetRecord, TracingPolicies, Option<ACCommitment>)> {
+        let (policies, identity_tracing) = self.policies(&template.asset_type);
+        let mut template = template.clone();
+        template.asset_tracing_policies = policies.clone();
+        if !identity_tracing {
+            let record = AssetRecord::from_template_no_identity_tracing(prng, &template)
+                .c(d!())?;
+            return Ok((record, policies, None));
+        }
+        // only the receiver can prove its identity attributes
+        if template.public_key != self.key_pair.pub_key {
+            return Err(eg!(ZeiError::ParameterError));
+        }
+        let credential = self.sender_credential().c(d!())?;
+        let record = AssetRecord::from_template_with_identity_tracing(
+            prng,
+            &template,
+            credential.user_sec_key,
+            credential.credential,
+            credential.commitment_key,
+        )
+        .c(d!())?;
+        Ok((record, policies, Some(credential.commitment.clone())))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::setup::PublicParams;
+    use crate::xfr::asset_record::{open_blind_asset_record, AssetRecordType};
+    use crate::xfr::builder::XfrNoteBuilder;
+    use crate::xfr::lib::{trace_assets, verify_xfr_note};
+    use crate::xfr::sig::XfrKeyPair;
+    use crate::xfr::structs::{
+        AssetTracerKeyPair, AssetType, TracingPolicies, TracingPolicy,
+    };
+    use crate::xfr::test_utils::{
+        credential_with_tracing_policies, untraced_asset_record,
+    };
+    use rand_chacha::ChaChaRng;
+    use rand_core::SeedableRng;
+    use utils::errors::ZeiError;
+
+    #[test]
+    fn test_builder_change_and_asset_tracing() {
+        let mut prng = ChaChaRng::from_seed([0u8; 32]);
+        let mut params = PublicParams::default();
+        let gold = AssetType::from_identical_byte(0);
+        let silver = AssetType::from_identical_byte(1);
+        let alice = XfrKeyPair::generate(&mut prng);
+        let bob = XfrKeyPair::generate(&mut prng);
+        let tracer = AssetTracerKeyPair::generate(&mut prng);
+        let gold_policies = TracingPolicies::from_policy(TracingPolicy {
+            enc_keys: tracer.enc_key.clone(),
+            asset_tracing: true,
+            identity_tracing: None,
+        });
+
+        let mut builder = XfrNoteBuilder::new(&alice);
+        builder
+            .add_spendable(
+                untraced_asset_record(
+                    &mut prng,
+                    10,
+                    gold,
+                    AssetRecordType::ConfidentialAmount_ConfidentialAssetType,
+                    &alice.pub_key,
+                )
+                .open_asset_record,
+            )
+            .add_spendable(
+                untraced_asset_record(
+                    &mut prng,
+                    20,
+                    silver,
+                    AssetRecordType::ConfidentialAmount_ConfidentialAssetType,
+                    &alice.pub_key,
+                )
+                .open_asset_record,
+            )
+            .add_spendable(
+                untraced_asset_record(
+                    &mut prng,
+                    5,
+                    gold,
+                    AssetRecordType::ConfidentialAmount_ConfidentialAssetType,
+                    &alice.pub_key,
+                )
+                .open_asset_record,
+            )
+            .add_spendable(
+                untraced_asset_record(
+                    &mut prng,
+                    30,
+                    gold,
+                    AssetRecordType::ConfidentialAmount_ConfidentialAssetType,
+                    &alice.pub_key,
+                )
+                .open_asset_record,
+            )
+            .add_payment(
+                12,
+                gold,
+                AssetRecordType::ConfidentialAmount_ConfidentialAssetType,
+                bob.pub_key,
+            )
+            .add_payment(
+                20,
+                silver,
+                AssetRecordType::NonConfidentialAmount_NonConfidentialAssetType,
+                bob.pub_key,
+            )
+            .with_tracing_policies(gold, gold_policies);
+
+        let (note, policies) = builder.build(&mut prng).unwrap();
+        // the two first gold records and the silver one are spent, 3 gold are sent back
+        assert_eq!(note.body.inputs.len(), 3);
+        assert_eq!(note.body.outputs.len(), 3);
+        assert!(
+            verify_xfr_note(&mut prng, &mut params, &note, &policies.to_ref()).is_ok()
+        );
+        let change = open_blind_asset_record(
+            &note.body.outputs[2],
+            &note.body.owners_memos[2],
+            &alice,
+        )
+        .unwrap();
+        assert_eq!(change.amount, 3);
+        assert_eq!(change.asset_type, gold);
+        // the tracer sees the two gold inputs and the gold outputs
+        let records = trace_assets(&note.body, &tracer).unwrap();
+        assert_eq!(records.len(), 4);
+
+        // not enough silver
+        builder.add_payment(
+            1,
+            silver,
+            AssetRecordType::ConfidentialAmount_ConfidentialAssetType,
+            bob.pub_key,
+        );
+        err_eq!(
+            ZeiError::XfrCreationAssetAmountError,
+            builder.build(&mut prng).unwrap_err()
+        );
+    }
+
+    #[test]
+    fn test_builder_identity_tracing() {
+        let mut prng = ChaChaRng::from_seed([1u8; 32]);
+        let mut params = PublicParams::default();
+        let asset_type = AssetType::from_identical_byte(0);
+        let alice = XfrKeyPair::generate(&mut prng);
+        let bob = XfrKeyPair::generate(&mut prng);
+        let tracer = AssetTracerKeyPair::generate(&mut prng);
+
+        let (user_sk, credential, key, commitment, policies) =
+            credential_with_tracing_policies(&mut prng, tracer.enc_key, &alice.pub_key);
+
+        let mut builder = XfrNoteBuilder::new(&alice);
+        builder
+            .add_spendable(
+                untraced_asset_record(
+                    &mut prng,
+                    10,
+                    asset_type,
+                    AssetRecordType::ConfidentialAmount_ConfidentialAssetType,
+                    &alice.pub_key,
+                )
+                .open_asset_record,
+            )
+            .add_payment(
+                4,
+                asset_type,
+                AssetRecordType::ConfidentialAmount_ConfidentialAssetType,
+                alice.pub_key,
+            )
+            .with_tracing_policies(asset_type, policies)
+            .with_change_record_type(
+                AssetRecordType::NonConfidentialAmount_NonConfidentialAssetType,
+            );
+
+        // identity tracing requires the credential of the sender
+        err_eq!(
+            ZeiError::ParameterError,
+            builder.build(&mut prng).unwrap_err()
+        );
+
+        builder.with_credential(&user_sk, &credential, &key, &commitment);
+        let (note, policies) = builder.build(&mut prng).unwrap();
+        assert_eq!(note.body.outputs[1].amount.get_amount(), Some(6));
+        assert!(
+            verify_xfr_note(&mut prng, &mut params, &note, &policies.to_ref()).is_ok()
+        );
+
+        // bob would have to prove his identity attributes himself
+        builder.add_payment(
+            1,
+            asset_type,
+            AssetRecordType::ConfidentialAmount_ConfidentialAssetType,
+            bob.pub_key,
+        );
+        err_eq!(
+            ZeiError::ParameterError,
+            builder.build(&mut prng).unwrap_err()
+        );
+    }
+}
@@ -0,0 +1,583 @@
+use crate::anon_xfr::structs::OpenAnonBlindAssetRecord;
+use crate::setup::UserParams;
+use crate::xfr::structs::{AssetType, OpenAssetRecord};
+use itertools::Itertools;
+use rand_core::{CryptoRng, RngCore};
+use ruc::*;
+use utils::errors::ZeiError;
+
+/// Maximum number of records of an asset type for which MinimizeChange tries every subset
+pub const MAX_EXHAUSTIVE_SELECTION: usize = 16;
+
+/// A record that can be spent by a transfer
+pub trait SpendableRecord {
+    fn amount(&self) -> u64;
+    fn asset_type(&self) -> AssetType;
+}
+
+impl SpendableRecord for OpenAssetRecord {
+    fn amount(&self) -> u64 {
+        self.amount
+    }
+
+    fn asset_type(&self) -> AssetType {
+        self.asset_type
+    }
+}
+
+impl SpendableRecord for OpenAnonBlindAssetRecord {
+    fn amount(&self) -> u64 {
+        self.amount
+    }
+
+    fn asset_type(&self) -> AssetType {
+        self.asset_type
+    }
+}
+
+/// How the records of an asset type are chosen to pay an amount
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum CoinSelectionStrategy {
+    /// Spends the records in the order they are given
+    InOrder,
+    /// Spends the records with the largest amounts first, minimizing the number of inputs
+    LargestFirst,
+    /// Spends the records leaving the smallest change, with as few inputs as possible.
+    /// The search is exhaustive if there are at most MAX_EXHAUSTIVE_SELECTION records of the
+    /// asset type, and greedy otherwise
+    MinimizeChange,
+    /// Spends the records in a random order, so that the selection does not reveal how the
+    /// wallet sorts its records
+    Random,
+}
+
+/// Number of inputs a transfer can have
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum InputCount {
+    Any,
+    /// At most the given number of inputs, e.g. anonymous transfers padded with dummy inputs
+    AtMost(usize),
+    /// Exactly one of the given numbers of inputs, e.g. one for each set of anonymous transfer
+    /// parameters generated for a fixed number of inputs
+    OneOf(Vec<usize>),
+}
+
+impl InputCount {
+    /// Number of inputs of anonymous transfers padded with dummy inputs.
+    /// Returns None if `params` are fixed to the number of inputs they were generated for, which
+    /// has to be given with InputCount::OneOf
+    pub fn from_user_params(params: &UserParams) -> Option<Self> {
+        params
+            .padding
+            .map(|(max_payers, _)| InputCount::AtMost(max_payers))
+    }
+
+    // Returns the smallest allowed number of inputs that is not lower than `n`
+    fn fit(&self, n: usize) -> Result<usize> {
+        match self {
+            InputCount::Any => Ok(n),
+            InputCount::AtMost(max) => {
+                if n <= *max {
+                    Ok(n)
+                } else {
+                    Err(eg!(ZeiError::ParameterError))
+                }
+            }
+            InputCount::OneOf(counts) => counts
+                .iter()
+                .filter(|count| **count >= n)
+                .min()
+                .copied()
+                .c(d!(ZeiError::ParameterError)),
+        }
+    }
+}
+
+/// Records selected to pay an amount of an asset type
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CoinSelection {
+    pub asset_type: AssetType,
+    /// Indices of the selected records
+    pub inputs: Vec<usize>,
+    /// Amount of the selected records exceeding the payment, to be sent back to their owner
+    pub change: u64,
+}
+
+/// Selects records of `asset_type` among `records` to pay `amount`.
+/// Returns ZeiError::XfrCreationAssetAmountError if the records do not cover the amount and
+/// ZeiError::ParameterError if the number of inputs cannot match `input_count`
+pub fn select_coins<R: CryptoRng + RngCore, T: SpendableRecord>(
+    prng: &mut R,
+    records: &[T],
+    asset_type: AssetType,
+    amount: u64,
+    strategy: CoinSelectionStrategy,
+    input_count: &InputCount,
+) -> Result<CoinSelection> {
+    let mut selections = select_coins_per_asset_type(
+        prng,
+        records,
+        &[(asset_type, amount)],
+        strategy,
+        input_count,
+    )
+    .c(d!())?;
+    Ok(selections.remove(0))
+}
+
+/// Selects records among `records` to pay each (asset type, amount) of `payments`, returning a
+/// selection per asset type in the order of `payments`. Amounts of the same asset type are added.
+/// If the selected records are fewer than allowed by `input_count`, unselected records of the paid
+/// asset types with the smallest amounts are added, increasing the change.
+/// Returns ZeiError::XfrCreationAssetAmountError if the records do not cover a payment and
+/// ZeiError::ParameterError if the number of inputs cannot match `input_count`
+pub fn select_coins_per_asset_type<R: CryptoRng + RngCore, T: SpendableRecord>(
+    prng: &mut R,
+    records: &[T],
+    payments: &[(AssetType, u64)],
+    strategy: CoinSelectionStrategy,
+    input_count: &InputCount,
+) -> Result<Vec<CoinSelection>> {
+    let mut targets: Vec<(AssetType, u64)> = vec![];
+    for (asset_type, amount) in payments.iter() {
+        match targets.iter_mut().find(|(target, _)| target == asset_type) {
+            Some((_, total)) => {
+                *total = total
+                    .checked_add(*amount)
+                    .c(d!(ZeiError::XfrCreationAssetAmountError))?;
+            }
+            None => targets.push((*asset_type, *amount)),
+        }
+    }
+
+    let mut selections = vec![];
+    for (asset_type, target) in targets {
+        let mut candidates = records
+            .iter()
+            .enumerate()
+            .filter(|(_, record)| record.asset_type() == asset_type)
+            .map(|(i, record)| (i, record.amount()))
+            .collect_vec();
+        let inputs = match strategy {
+            CoinSelectionStrategy::InOrder => take_until(&candidates, target),
+            CoinSelectionStrategy::LargestFirst => {
+                candidates.sort_by(|a, b| b.1.cmp(&a.1));
+                take_until(&candidates, target)
+            }
+            CoinSelectionStrategy::MinimizeChange => {
+                minimize_change(&candidates, target)
+            }
+            CoinSelectionStrategy::Random => {
+                shuffle(prng, &mut candidates);
+                take_until(&candidates, target)
+            }
+        }
+        .c(d!())?;
+        let selected = total_amount(&inputs).c(d!())?;
+        selections.push(CoinSelection {
+            asset_type,
+            inputs: inputs.into_iter().map(|(i, _)| i).collect_vec(),
+            change: selected - target,
+        });
+    }
+
+    let n = selections
+        .iter()
+        .map(|selection| selection.inputs.len())
+        .sum();
+    let required = input_count.fit(n).c(d!())?;
+    for _ in n..required {
+        let (k, i, amount) = selections
+            .iter()
+            .enumerate()
+            .flat_map(|(k, selection)| {
+                records
+                    .iter()
+                    .enumerate()
+                    .filter(move |(i, record)| {
+                        record.asset_type() == selection.asset_type
+                            && !selection.inputs.contains(i)
+                    })
+                    .map(move |(i, record)| (k, i, record.amount()))
+            })
+            .min_by_key(|(_, _, amount)| *amount)
+            .c(d!(ZeiError::ParameterError))?;
+        selections[k].inputs.push(i);
+        selections[k].change = selections[k]
+            .change
+            .checked_add(amount)
+            .c(d!(ZeiError::XfrCreationAssetAmountError))?;
+    }
+    Ok(selections)
+}
+
+// Takes the (index, amount) candidates in order until their amounts cover `target`
+fn take_until(candidates: &[(usize, u64)], target: u64) -> Result<Vec<(usize, u64)>> {
+    let mut selected = vec![];
+    let mut sum = 0u64;
+    for (i, amount) in candidates.iter() {
+        if sum >= target {
+            break;
+        }
+        sum = sum
+            .checked_add(*amount)
+            .c(d!(ZeiError::XfrCreationAssetAmountError))?;
+        selected.push((*i, *amount));
+    }
+    if sum < target {
+        return Err(eg!(ZeiError::XfrCreationAssetAmountError));
+    }
+    Ok(selected)
+}
+
+// Returns the subset of candidates covering `target` with the smallest change, and the fewest
+// inputs among those
+fn minimize_change(
+    candidates: &[(usize, u64)],
+    target: u64,
+) -> Result<Vec<(usize, u64)>> {
+    if target == 0 {
+        return Ok(vec![]);
+    }
+    if candidates.len() > MAX_EXHAUSTIVE_SELECTION {
+        return greedy_minimize_change(candidates, target).c(d!());
+    }
+    let mut best: Option<(u128, u32, usize)> = None;
+    for mask in 1usize..(1 << candidates.len()) {
+        let sum: u128 = candidates
+            .iter()
+            .enumerate()
+            .filter(|(k, _)| mask & (1 << k) != 0)
+            .map(|(_, (_, amount))| *amount as u128)
+            .sum();
+        if sum < target as u128 {
+            continue;
+        }
+        let cost = (sum - target as u128, mask.count_ones());
+        if best.map_or(true, |(change, n, _)| cost < (change, n)) {
+            best = Some((cost.0, cost.1, mask));
+        }
+    }
+    let (_, _, mask) = best.c(d!(ZeiError::XfrCreationAssetAmountError))?;
+    Ok(candidates
+        .iter()
+        .enumerate()
+        .filter(|(k, _)| mask & (1 << k) != 0)
+        .map(|(_, candidate)| *candidate)
+        .collect_vec())
+}
+
+// Compares the smallest candidate covering `target` on its own with the largest smaller
+// candidates completed by the smallest candidate covering the rest
+fn greedy_minimize_change(
+    candidates: &[(usize, u64)],
+    target: u64,
+) -> Result<Vec<(usize, u64)>> {
+    let single = candidates
+        .iter()
+        .filter(|(_, amount)| *amount >= target)
+        .min_by_key(|(_, amount)| *amount)
+        .copied();
+
+    let mut smaller = candidates
+        .iter()
+        .filter(|(_, amount)| *amount < target)
+        .copied()
+        .collect_vec();
+    smaller.sort_by(|a, b| b.1.cmp(&a.1));
+    let mut combination = vec![];
+    let mut remaining = target;
+    for (i, amount) in smaller.iter() {
+        let rest = smaller
+            .iter()
+            .filter(|(j, amount)| {
+                *amount >= remaining && !combination.iter().any(|(k, _)| k == j)
+            })
+            .min_by_key(|(_, amount)| *amount);
+        if let Some(rest) = rest {
+            combination.push(*rest);
+            remaining = 0;
+            break;
+        }
+        combination.push((*i, *amount));
+        remaining -= amount;
+    }
+
+    match (single, remaining == 0) {
+        (Some(single), true) => {
+            if single.1 <= total_amount(&combination).c(d!())? {
+                Ok(vec![single])
+            } else {
+                Ok(combination)
+            }
+        }
+        (Some(single), false) => Ok(vec![single]),
+        (None, true) => Ok(combination),
+        (None, false) => Err(eg!(ZeiError::XfrCreationAssetAmountError)),
+    }
+}
+
+// Fisher-Yates shuffle
+fn shuffle<R: CryptoRng + RngCore, T>(prng: &mut R, items: &mut [T]) {
+    for i in (1..items.len()).rev() {
+        let j = (prng.next_u64() % (i as u64 + 1)) as usize;
+        items.swap(i, j);
+    }
+}
+
+fn total_amount(selected: &[(usize, u64)]) -> Result<u64> {
+    selected
+        .iter()
+        .try_fold(0u64, |sum, (_, amount)| sum.checked_add(*amount))
+        .c(d!(ZeiError::XfrCreationAssetAmountError))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::anon_xfr::keys::AXfrKeyPair;
+    use crate::anon_xfr::structs::OpenAnonBlindAssetRecordBuilder;
+    use crate::xfr::asset_record::AssetRecordType;
+    use crate::xfr::coin_selection::{
+        select_coins, select_coins_per_asset_type, CoinSelectionStrategy, InputCount,
+    };
+    use crate::xfr::sig::XfrKeyPair;
+    use crate::xfr::structs::{
+        AssetRecord, AssetRecordTemplate, AssetType, OpenAssetRecord,
+    };
+    use crypto::basics::hybrid_encryption::{XPublicKey, XSecretKey};
+    use rand_chacha::ChaChaRng;
+    use rand_core::SeedableRng;
+    use utils::errors::ZeiError;
+
+    fn records(
+        prng: &mut ChaChaRng,
+        amounts: &[(u64, AssetType)],
+        key_pair: &XfrKeyPair,
+    ) -> Vec<OpenAssetRecord> {
+        amounts
+            .iter()
+            .map(|(amount, asset_type)| {
+                let template = AssetRecordTemplate::with_no_asset_tracing(
+                    *amount,
+                    *asset_type,
+                    AssetRecordType::NonConfidentialAmount_NonConfidentialAssetType,
+                    key_pair.pub_key,
+                );
+                AssetRecord::from_template_no_identity_tracing(prng, &template)
+                    .unwrap()
+                    .open_asset_record
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_select_coins_strategies() {
+        let mut prng = ChaChaRng::from_seed([0u8; 32]);
+        let key_pair = XfrKeyPair::generate(&mut prng);
+        let asset_type = AssetType::from_identical_byte(0);
+        let other_asset_type = AssetType::from_identical_byte(1);
+        let records = records(
+            &mut prng,
+            &[
+                (3, asset_type),
+                (100, other_asset_type),
+                (20, asset_type),
+                (7, asset_type),
+                (12, asset_type),
+            ],
+            &key_pair,
+        );
+
+        let selection = select_coins(
+            &mut prng,
+            &records,
+            asset_type,
+            10,
+            CoinSelectionStrategy::InOrder,
+            &InputCount::Any,
+        )
+        .unwrap();
+        assert_eq!(selection.inputs, vec![0, 2]);
+        assert_eq!(selection.change, 13);
+
+        let selection = select_coins(
+            &mut prng,
+            &records,
+            asset_type,
+            25,
+            CoinSelectionStrategy::LargestFirst,
+            &InputCount::Any,
+        )
+        .unwrap();
+        assert_eq!(selection.inputs, vec![2, 4]);
+        assert_eq!(selection.change, 7);
+
+        // 12 alone leaves 2, 3 + 7 leaves none
+        let selection = select_coins(
+            &mut prng,
+            &records,
+            asset_type,
+            10,
+            CoinSelectionStrategy::MinimizeChange,
+            &InputCount::Any,
+        )
+        .unwrap();
+        assert_eq!(selection.inputs, vec![0, 3]);
+        assert_eq!(selection.change, 0);
+
+        // 12 alone leaves 1, 3 + 7 is not enough and 3 + 12 leaves 4
+        let selection = select_coins(
+            &mut prng,
+            &records,
+            asset_type,
+            11,
+            CoinSelectionStrategy::MinimizeChange,
+            &InputCount::Any,
+        )
+        .unwrap();
+        assert_eq!(selection.inputs, vec![4]);
+        assert_eq!(selection.change, 1);
+
+        for _ in 0..10 {
+            let selection = select_coins(
+                &mut prng,
+                &records,
+                asset_type,
+                30,
+                CoinSelectionStrategy::Random,
+                &InputCount::Any,
+            )
+            .unwrap();
+            let selected: u64 =
+                selection.inputs.iter().map(|i| records[*i].amount).sum();
+            assert_eq!(selected, 30 + selection.change);
+            assert!(selection
+                .inputs
+                .iter()
+                .all(|i| records[*i].asset_type == asset_type));
+        }
+
+        for strategy in [
+            CoinSelectionStrategy::InOrder,
+            CoinSelectionStrategy::LargestFirst,
+            CoinSelectionStrategy::MinimizeChange,
+            CoinSelectionStrategy::Random,
+        ]
+        .iter()
+        {
+            let err = select_coins(
+                &mut prng,
+                &records,
+                asset_type,
+                43,
+                *strategy,
+                &InputCount::Any,
+            )
+            .unwrap_err();
+            err_eq!(ZeiError::XfrCreationAssetAmountError, err);
+        }
+    }
+
+    #[test]
+    fn test_select_coins_input_count() {
+        let mut prng = ChaChaRng::from_seed([0u8; 32]);
+        let key_pair = XfrKeyPair::generate(&mut prng);
+        let asset_type = AssetType::from_identical_byte(0);
+        let other_asset_type = AssetType::from_identical_byte(1);
+        let records = records(
+            &mut prng,
+            &[
+                (5, asset_type),
+                (5, asset_type),
+                (1, asset_type),
+                (8, other_asset_type),
+                (2, other_asset_type),
+            ],
+            &key_pair,
+        );
+        let payments = [(asset_type, 4), (other_asset_type, 6), (asset_type, 4)];
+
+        let selections = select_coins_per_asset_type(
+            &mut prng,
+            &records,
+            &payments,
+            CoinSelectionStrategy::LargestFirst,
+            &InputCount::Any,
+        )
+        .unwrap();
+        assert_eq!(selections.len(), 2);
+        assert_eq!(selections[0].asset_type, asset_type);
+        assert_eq!(selections[0].inputs, vec![0, 1]);
+        assert_eq!(selections[0].change, 2);
+        assert_eq!(selections[1].asset_type, other_asset_type);
+        assert_eq!(selections[1].inputs, vec![3]);
+        assert_eq!(selections[1].change, 2);
+
+        // fixed size parameters for 2 or 5 inputs: the smallest records are added
+        let selections = select_coins_per_asset_type(
+            &mut prng,
+            &records,
+            &payments,
+            CoinSelectionStrategy::LargestFirst,
+            &InputCount::OneOf(vec![2, 5]),
+        )
+        .unwrap();
+        assert_eq!(selections[0].inputs, vec![0, 1, 2]);
+        assert_eq!(selections[0].change, 3);
+        assert_eq!(selections[1].inputs, vec![3, 4]);
+        assert_eq!(selections[1].change, 4);
+
+        let err = select_coins_per_asset_type(
+            &mut prng,
+            &records,
+            &payments,
+            CoinSelectionStrategy::LargestFirst,
+            &InputCount::OneOf(vec![2, 6]),
+        )
+        .unwrap_err();
+        err_eq!(ZeiError::ParameterError, err);
+
+        let err = select_coins_per_asset_type(
+            &mut prng,
+            &records,
+            &payments,
+            CoinSelectionStrategy::LargestFirst,
+            &InputCount::AtMost(2),
+        )
+        .unwrap_err();
+        err_eq!(ZeiError::ParameterError, err);
+    }
+
+    #[test]
+    fn test_select_anon_coins() {
+        let mut prng = ChaChaRng::from_seed([0u8; 32]);
+        let asset_type = AssetType::from_identical_byte(0);
+        let keypair = AXfrKeyPair::generate(&mut prng);
+        let enc_key = XPublicKey::from(&XSecretKey::new(&mut prng));
+        let oabars = [4u64, 9, 6]
+            .iter()
+            .map(|amount| {
+                OpenAnonBlindAssetRecordBuilder::new()
+                    .amount(*amount)
+                    .asset_type(asset_type)
+                    .pub_key(keypair.pub_key())
+                    .finalize(&mut prng, &enc_key)
+                    .unwrap()
+                    .build()
+                    .unwrap()
+            })
+            .collect::<Vec<_>>();
+
+        let selection = select_coins(
+            &mut prng,
+            &oabars,
+            asset_type,
+            10,
+            CoinSelectionStrategy::MinimizeChange,
+            &InputCount::AtMost(3),
+        )
+        .unwrap();
+        assert_eq!(selection.inputs, vec![0, 2]);
+        assert_eq!(selection.change, 0);
+    }
+}
@@ -3,14 +3,19 @@ use crate::setup::PublicParams;
 use crate::xfr::asset_mixer::{
     batch_verify_asset_mixing, prove_asset_mixing, AssetMixProof, AssetMixingInstance,
 };
+use crate::xfr::asset_tracer::{TracerDecryptionTables, TRACER_TABLE_BITS};
+use crate::xfr::musig::{aggregate_public_keys, sign_aggregated};
 use crate::xfr::proofs::{
     asset_amount_tracing_proofs, asset_proof, batch_verify_confidential_amount,
     batch_verify_confidential_asset, batch_verify_tracer_tracing_proof, range_proof,
 };
 use crate::xfr::sig::{XfrKeyPair, XfrMultiSig, XfrPublicKey};
 use crate::xfr::structs::*;
-use algebra::groups::{GroupArithmetic, Scalar as _, ScalarArithmetic};
-use algebra::ristretto::{CompressedRistretto, RistrettoScalar as Scalar};
+use crate::xfr::threshold::{XfrInputSigner, XfrThresholdSig};
+use algebra::groups::{Group, GroupArithmetic, Scalar as _, ScalarArithmetic};
+use algebra::ristretto::{
+    CompressedRistretto, RistrettoPoint, RistrettoScalar as Scalar,
+};
 use crypto::basics::commitments::ristretto_pedersen::RistrettoPedersenGens;
 use itertools::Itertools;
 use rand_core::{CryptoRng, RngCore};
@@ -55,10 +60,11 @@ impl XfrType {
             if asset_type != record.open_asset_record.asset_type {
                 multi_asset = true;
             }
-            let confidential_amount = matches!(
-                record.open_asset_record.blind_asset_record.amount,
-                XfrAmount::Confidential(_)
-            );
+            let confidential_amount = record
+                .open_asset_record
+                .blind_asset_record
+                .amount
+                .is_confidential();
             let confidential_asset_type = matches!(
                 record.open_asset_record.blind_asset_record.asset_type,
                 XfrAssetType::Confidential(_)
@@ -180,7 +186,94 @@ pub fn gen_xfr_note<R: CryptoRng + RngCore>(
 
     let multisig = compute_transfer_multisig(&body, input_key_pairs).c(d!())?;
 
-    Ok(XfrNote { body, multisig })
+    Ok(XfrNote {
+        body,
+        multisig,
+        threshold_signatures: vec![],
+        aggregated_signature: None,
+    })
+}
+
+/// Build a XfrNote signed with a single aggregated signature of the input keys instead of one
+/// signature per input. The signers run all the rounds of `xfr::musig` in this process; signers
+/// in different processes use `xfr_body_signing_message` and set `XfrNote::aggregated_signature`
+/// * `prng` - pseudo-random number generator
+/// * `inputs` - asset records containing amounts, assets, policies and memos
+/// * `outputs` - asset records containing amounts, assets, policies and memos
+/// * `input_key_pairs` - list of inputs' key pairs to sign the transfer
+/// * `returns` - an error or a valid XfrNote
+pub fn gen_xfr_note_with_aggregated_signature<R: CryptoRng + RngCore>(
+    prng: &mut R,
+    inputs: &[AssetRecord],
+    outputs: &[AssetRecord],
+    input_key_pairs: &[&XfrKeyPair],
+) -> Result<XfrNote> {
+    if inputs.is_empty() {
+        return Err(eg!(ZeiError::ParameterError));
+    }
+
+    check_keys(inputs, input_key_pairs).c(d!())?;
+
+    let body = gen_xfr_body(prng, inputs, outputs).c(d!())?;
+
+    let message = xfr_body_signing_message(&body).c(d!())?;
+    let signature = sign_aggregated(prng, input_key_pairs, &message).c(d!())?;
+
+    Ok(XfrNote {
+        body,
+        multisig: XfrMultiSig::default(),
+        threshold_signatures: vec![],
+        aggregated_signature: Some(signature),
+    })
+}
+
+/// Build a XfrNote whose inputs may be owned by threshold policies
+/// * `prng` - pseudo-random number generator
+/// * `inputs` - asset records containing amounts, assets, policies and memos
+/// * `outputs` - asset records containing amounts, assets, policies and memos
+/// * `input_signers` - signer of each input: a key pair for inputs owned by a single key, or
+///    members of the policy for inputs owned by a threshold policy
+/// * `returns` - an error or a valid XfrNote
+pub fn gen_xfr_note_with_input_signers<R: CryptoRng + RngCore>(
+    prng: &mut R,
+    inputs: &[AssetRecord],
+    outputs: &[AssetRecord],
+    input_signers: &[XfrInputSigner],
+) -> Result<XfrNote> {
+    if inputs.is_empty() || inputs.len() != input_signers.len() {
+        return Err(eg!(ZeiError::ParameterError));
+    }
+    for (input, signer) in inputs.iter().zip(input_signers.iter()) {
+        if input.open_asset_record.blind_asset_record.public_key != signer.owner() {
+            return Err(eg!(ZeiError::ParameterError));
+        }
+    }
+
+    let body = gen_xfr_body(prng, inputs, outputs).c(d!())?;
+
+    let keys = input_signers
+        .iter()
+        .filter_map(|signer| match signer {
+            XfrInputSigner::Key(key_pair) => Some(*key_pair),
+            XfrInputSigner::Threshold(..) => None,
+        })
+        .collect_vec();
+    let multisig = compute_transfer_multisig(&body, &keys).c(d!())?;
+    let bytes = xfr_body_signing_message(&body).c(d!())?;
+    let mut threshold_signatures = vec![];
+    for signer in input_signers.iter() {
+        if let XfrInputSigner::Threshold(policy, members) = signer {
+            threshold_signatures
+                .push(XfrThresholdSig::sign(policy, members, &bytes).c(d!())?);
+        }
+    }
+
+    Ok(XfrNote {
+        body,
+        multisig,
+        threshold_signatures,
+        aggregated_signature: None,
+    })
 }
 
 /// I create the body of a xfr note. This body contains the data to be signed.
@@ -320,12 +413,25 @@ pub fn gen_xfr_body<R: CryptoRng + RngCore>(
             record_input.owner_memo.clone() // Can I avoid this clone?
         })
         .collect_vec();
+    // member locks are only carried by transfers with outputs owned by a threshold policy
+    let owners_member_locks = if outputs
+        .iter()
+        .any(|output| !output.owner_member_locks.is_empty())
+    {
+        outputs
+            .iter()
+            .map(|output| output.owner_member_locks.clone())
+            .collect_vec()
+    } else {
+        vec![]
+    };
     Ok(XfrBody {
         inputs: xfr_inputs,
         outputs: xfr_outputs,
         proofs,
         asset_tracing_memos: tracer_memos,
         owners_memos: owner_memos,
+        owners_member_locks,
     })
 }
 
@@ -454,30 +560,63 @@ fn check_asset_amount(inputs: &[AssetRecord], outputs: &[AssetRecord]) -> Result
     Ok(())
 }
 
+/// Returns the message signed by the owners of the transfer's inputs.
+/// The member locks of outputs owned by a threshold policy are only part of the message when
+/// the body has some, so that the message of other bodies is their encoding without that field
+pub fn xfr_body_signing_message(body: &XfrBody) -> Result<Vec<u8>> {
+    let mut bytes = vec![];
+    let mut serializer = rmp_serde::Serializer::new(&mut bytes);
+    let result = if body.owners_member_locks.is_empty() {
+        (
+            &body.inputs,
+            &body.outputs,
+            &body.proofs,
+            &body.asset_tracing_memos,
+            &body.owners_memos,
+        )
+            .serialize(&mut serializer)
+    } else {
+        body.serialize(&mut serializer)
+    };
+    result.c(d!(ZeiError::SerializationError))?;
+    Ok(bytes)
+}
+
 /// I compute a multisignature over the transfer's body
 pub(crate) fn compute_transfer_multisig(
     body: &XfrBody,
     keys: &[&XfrKeyPair],
 ) -> Result<XfrMultiSig> {
-    let mut bytes = vec![];
-    body.serialize(&mut rmp_serde::Serializer::new(&mut bytes))
-        .c(d!(ZeiError::SerializationError))?;
+    let bytes = xfr_body_signing_message(body).c(d!())?;
     Ok(XfrMultiSig::sign(&keys, &bytes))
 }
 
 /// I verify the transfer multisignature over the its body
 pub(crate) fn verify_transfer_multisig(xfr_note: &XfrNote) -> Result<()> {
-    let mut bytes = vec![];
-    xfr_note
-        .body
-        .serialize(&mut rmp_serde::Serializer::new(&mut bytes))
-        .c(d!(ZeiError::SerializationError))?;
-    let pubkeys = xfr_note
-        .body
-        .inputs
-        .iter()
-        .map(|input| &input.public_key)
-        .collect_vec();
+    let bytes = xfr_body_signing_message(&xfr_note.body).c(d!())?;
+    // inputs owned by a threshold policy are matched, in order, with the threshold signatures
+    let mut threshold_signatures = xfr_note.threshold_signatures.iter().peekable();
+    let mut pubkeys = vec![];
+    for input in xfr_note.body.inputs.iter() {
+        match threshold_signatures.peek() {
+            Some(sig) if sig.policy.address() == input.public_key => {
+                sig.verify(&bytes).c(d!())?;
+                threshold_signatures.next();
+            }
+            _ => pubkeys.push(&input.public_key),
+        }
+    }
+    if threshold_signatures.next().is_some() {
+        return Err(eg!(ZeiError::SignatureError));
+    }
+    if let Some(signature) = xfr_note.aggregated_signature.as_ref() {
+        if !xfr_note.multisig.signatures.is_empty() {
+            return Err(eg!(ZeiError::SignatureError));
+        }
+        let aggregated_key =
+            aggregate_public_keys(&pubkeys).c(d!(ZeiError::SignatureError))?;
+        return aggregated_key.verify(&bytes, signature).c(d!());
+    }
     xfr_note.multisig.verify(&pubkeys, &bytes)
 }
 
@@ -830,6 +969,10 @@ fn batch_verify_asset_mix<R: CryptoRng + RngCore>(
                         c1.decompress().c(d!(ZeiError::DecompressElementError)),
                         c2.decompress().c(d!(ZeiError::DecompressElementError)),
                     ),
+                    XfrAmount::Confidential64(c) => (
+                        c.decompress().c(d!(ZeiError::DecompressElementError)),
+                        Ok(RistrettoPoint::get_identity()),
+                    ),
                     XfrAmount::NonConfidential(amount) => {
                         let pc_gens = RistrettoPedersenGens::default();
                         let (low, high) = u64_to_u32_pair(amount);
@@ -922,6 +1065,7 @@ pub fn trace_assets(
 /// Scan XfrBody transfers involving asset tracing memos intended for `tracer_keypair`.
 /// It takes each AssetTracer memo, decrypt the ElGamalEncryption
 /// and brute-force Dlog computation to retrieve amount and identity attributed.
+/// The decryption tables are built on every call, use `trace_assets_with_tables` to reuse them.
 /// Return Vector of RecordData = (amount, asset_type, identity attributes, public key)
 /// Returning ZeiError::BogusAssetTracerMemo in case a TracerMemo decrypts inconsistent information, and
 /// ZeiError::InconsistentStructureError if amount or asset_type cannot be found.
@@ -939,6 +1083,28 @@ pub fn trace_assets_brute_force(
     .c(d!())
 }
 
+/// Scan XfrBody transfers involving asset tracing memos intended for `tracer_keypair`.
+/// It takes each AssetTracer memo, decrypt the ElGamalEncryption
+/// and solves the Dlog with the precomputed `tables` to retrieve amount and identity attributed.
+/// Return Vector of RecordData = (amount, asset_type, identity attributes, public key)
+/// Returning ZeiError::BogusAssetTracerMemo in case a TracerMemo decrypts inconsistent information, and
+/// ZeiError::InconsistentStructureError if amount or asset_type cannot be found.
+pub fn trace_assets_with_tables(
+    xfr_body: &XfrBody,
+    tracer_keypair: &AssetTracerKeyPair,
+    candidate_asset_types: &[AssetType],
+    tables: &TracerDecryptionTables,
+) -> Result<Vec<RecordData>> {
+    let bars_memos = find_tracing_memos(xfr_body, &tracer_keypair.enc_key).c(d!())?;
+    extract_tracing_info_with_tables(
+        bars_memos.as_slice(),
+        &tracer_keypair.dec_key,
+        candidate_asset_types,
+        tables,
+    )
+    .c(d!())
+}
+
 /// Scan list of (BlindAssetRecord, AssetTracerMemo) retrieved by find_tracing_memos
 /// (e.i. intended for the same asset tracer). It takes each AssetTracer memo,
 /// decrypts its lock_info field to retrieve amount, asset type and identity attributed.
@@ -1001,6 +1167,27 @@ pub(crate) fn extract_tracing_info_brute_force(
     memos: &[(&BlindAssetRecord, &TracerMemo)],
     dec_key: &AssetTracerDecKeys,
     candidate_asset_types: &[AssetType],
+) -> Result<Vec<RecordData>> {
+    if memos.is_empty() {
+        return Ok(vec![]);
+    }
+    let tables = TracerDecryptionTables::new(TRACER_TABLE_BITS).c(d!())?;
+    extract_tracing_info_with_tables(memos, dec_key, candidate_asset_types, &tables)
+        .c(d!())
+}
+
+/// Scan list of (BlindAssetRecord, AssetTracerMemo) retrieved by find_tracing_memos
+/// (e.i. intended for the same asset tracer). It takes each AssetTracer memo, decrypt the ElGamalEncryption
+/// and solves the Dlog with the precomputed `tables` to retrieve amount and identity attributed.
+/// The asset type is verified agains a known list of possible asset types `candidate_asset_types`
+/// Return Vector of RecordData = (amount, asset_type, identity attributes, public key)
+/// Return Error in case data cannot be retrieved due to inconsistent structure.
+/// Eg. amount is not in a BlindAssetRecord nor in the corresponding AssetTracerMemo
+pub(crate) fn extract_tracing_info_with_tables(
+    memos: &[(&BlindAssetRecord, &TracerMemo)],
+    dec_key: &AssetTracerDecKeys,
+    candidate_asset_types: &[AssetType],
+    tables: &TracerDecryptionTables,
 ) -> Result<Vec<RecordData>> {
     let mut result = vec![];
     for bar_memo in memos {
@@ -1012,7 +1199,7 @@ pub(crate) fn extract_tracing_info_brute_force(
                 .get_amount()
                 .c(d!(ZeiError::InconsistentStructureError))?,
             Some(_) => memo
-                .extract_amount_brute_force(&dec_key.record_data_dec_key)
+                .extract_amount_with_table(&dec_key.record_data_dec_key, &tables.amount)
                 .c(d!())?,
         };
 
@@ -1027,7 +1214,10 @@ pub(crate) fn extract_tracing_info_brute_force(
         };
 
         let attributes = memo
-            .extract_identity_attributes_brute_force(&dec_key.attrs_dec_key)
+            .extract_identity_attributes_with_table(
+                &dec_key.attrs_dec_key,
+                &tables.attributes,
+            )
             .c(d!())?;
 
         result.push((
@@ -1,10 +1,17 @@
 pub mod asset_mixer;
 pub mod asset_record;
 pub mod asset_tracer;
+pub mod builder;
+pub mod coin_selection;
 pub mod lib;
+pub mod musig;
+pub mod partial;
+pub mod policies;
 pub mod proofs;
 pub mod sig;
 pub mod structs;
+pub mod swap;
 pub mod test_utils; // for integration test
 #[cfg(test)]
 pub(crate) mod tests; // unit tests
+pub mod threshold;
@@ -0,0 +1,480 @@
+//! Aggregated (MuSig-style) Schnorr signatures over the ed25519 keys of transfer inputs.
+//! Signers run three rounds: they broadcast a commitment to their nonce, then their nonce, and
+//! then their partial signature. The combined signature is a standard ed25519 signature under the
+//! aggregated key sum(a_i * X_i), where the coefficient a_i of each key depends on the whole
+//! signer set so that no signer can cancel the others' keys (rogue key attack).
+use crate::xfr::sig::{XfrKeyPair, XfrPublicKey, XfrSignature};
+use algebra::groups::{Scalar as _, ScalarArithmetic, Zero};
+use algebra::ristretto::{CompressedEdwardsY, RistrettoScalar as Scalar};
+use curve25519_dalek::edwards::EdwardsPoint;
+use digest::Digest;
+use itertools::Itertools;
+use rand_core::{CryptoRng, RngCore};
+use ruc::*;
+use sha2::Sha512;
+use utils::errors::ZeiError;
+use utils::serialization::ZeiFromToBytes;
+
+const MUSIG_KEY_COEFFICIENT_DOMAIN: &[u8] = b"Zei MuSig key coefficient";
+const MUSIG_NONCE_COMMITMENT_DOMAIN: &[u8] = b"Zei MuSig nonce commitment";
+
+/// Commitment to the nonce of a signer, broadcast in the first round
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct XfrMuSigNonceCommitment {
+    pub pub_key: XfrPublicKey,
+    pub commitment: Vec<u8>,
+}
+
+/// Nonce of a signer, broadcast in the second round once all the commitments are received
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct XfrMuSigNonce {
+    pub pub_key: XfrPublicKey,
+    pub point: CompressedEdwardsY,
+}
+
+/// Partial signature of a signer, sent in the third round once all the nonces are received
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct XfrMuSigPartialSig {
+    pub pub_key: XfrPublicKey,
+    pub s: Scalar,
+}
+
+trait SignerMessage {
+    fn signer(&self) -> &XfrPublicKey;
+}
+
+impl SignerMessage for XfrMuSigNonceCommitment {
+    fn signer(&self) -> &XfrPublicKey {
+        &self.pub_key
+    }
+}
+
+impl SignerMessage for XfrMuSigNonce {
+    fn signer(&self) -> &XfrPublicKey {
+        &self.pub_key
+    }
+}
+
+impl SignerMessage for XfrMuSigPartialSig {
+    fn signer(&self) -> &XfrPublicKey {
+        &self.pub_key
+    }
+}
+
+/// State of a signer in an aggregated signing session.
+/// A session produces a single partial signature, its nonce is never reused.
+pub struct XfrMuSigSigner<'a> {
+    key_pair: &'a XfrKeyPair,
+    keys: Vec<XfrPublicKey>,
+    nonce: Scalar,
+    nonce_point: CompressedEdwardsY,
+}
+
+impl<'a> XfrMuSigSigner<'a> {
+    /// Starts the session of `key_pair` for a signature by `signers`.
+    /// Returns the session and the nonce commitment to broadcast to the other signers
+    pub fn new<R: CryptoRng + RngCore>(
+        prng: &mut R,
+        key_pair: &'a XfrKeyPair,
+        signers: &[&XfrPublicKey],
+    ) -> Result<(Self, XfrMuSigNonceCommitment)> {
+        let keys = signer_set(signers).c(d!())?;
+        if !keys.contains(&key_pair.pub_key) {
+            return Err(eg!(ZeiError::ParameterError));
+        }
+        let (nonce, nonce_point) = Scalar::random_scalar_with_compressed_edwards(prng);
+        let commitment = XfrMuSigNonceCommitment {
+            pub_key: key_pair.pub_key,
+            commitment: nonce_commitment(&nonce_point),
+        };
+        Ok((
+            XfrMuSigSigner {
+                key_pair,
+                keys,
+                nonce,
+                nonce_point,
+            },
+            commitment,
+        ))
+    }
+
+    /// Returns the nonce to broadcast once the commitments of all the signers are received
+    pub fn nonce(&self) -> XfrMuSigNonce {
+        XfrMuSigNonce {
+            pub_key: self.key_pair.pub_key,
+            point: self.nonce_point,
+        }
+    }
+
+    /// Computes the partial signature of `message` given the nonce commitments and the nonces of
+    /// all the signers. Returns SignatureError if a nonce does not match its commitment
+    pub fn sign(
+        self,
+        message: &[u8],
+        commitments: &[XfrMuSigNonceCommitment],
+        nonces: &[XfrMuSigNonce],
+    ) -> Result<XfrMuSigPartialSig> {
+        let commitments = order_by_signer(&self.keys, commitments).c(d!())?;
+        let nonces = order_by_signer(&self.keys, nonces).c(d!())?;
+        for (commitment, nonce) in commitments.iter().zip(nonces.iter()) {
+            if commitment.commitment != nonce_commitment(&nonce.point) {
+                return Err(eg!(ZeiError::SignatureError));
+            }
+        }
+        // safe unwrap: the key is in the signer set
+        let index = self
+            .keys
+            .iter()
+            .position(|key| *key == self.key_pair.pub_key)
+            .unwrap();
+        if nonces[index].point != self.nonce_point {
+            return Err(eg!(ZeiError::ParameterError));
+        }
+
+        let coefficients = key_coefficients(&self.keys);
+        let aggregated_key = aggregate_keys(&self.keys, &coefficients).c(d!())?;
+        let nonce_point = aggregate_nonces(&nonces).c(d!())?;
+        let c = challenge(&nonce_point, &aggregated_key, message);
+        let s = self.nonce.add(
+            &c.mul(&coefficients[index])
+                .mul(&self.key_pair.sec_key.as_scalar()),
+        );
+        Ok(XfrMuSigPartialSig {
+            pub_key: self.key_pair.pub_key,
+            s,
+        })
+    }
+}
+
+/// Returns the key the aggregated signature of `signers` verifies against
+pub fn aggregate_public_keys(signers: &[&XfrPublicKey]) -> Result<XfrPublicKey> {
+    let keys = signer_set(signers).c(d!())?;
+    aggregate_keys(&keys, &key_coefficients(&keys)).c(d!())
+}
+
+/// Combines the partial signatures of `signers` into an ed25519 signature of `message` under
+/// the aggregated key. Returns SignatureError if a partial signature is invalid
+pub fn aggregate_partial_signatures(
+    message: &[u8],
+    signers: &[&XfrPublicKey],
+    nonces: &[XfrMuSigNonce],
+    partial_sigs: &[XfrMuSigPartialSig],
+) -> Result<XfrSignature> {
+    let keys = signer_set(signers).c(d!())?;
+    let nonces = order_by_signer(&keys, nonces).c(d!())?;
+    let partial_sigs = order_by_signer(&keys, partial_sigs).c(d!())?;
+    let coefficients = key_coefficients(&keys);
+    let aggregated_key = aggregate_keys(&keys, &coefficients).c(d!())?;
+    let nonce_point = aggregate_nonces(&nonces).c(d!())?;
+    let c = challenge(&nonce_point, &aggregated_key, message);
+
+    let mut s = Scalar::zero();
+    for (((key, a), nonce), partial_sig) in keys
+        .iter()
+        .zip(coefficients.iter())
+        .zip(nonces.iter())
+        .zip(partial_sigs.iter())
+    {
+        // s_i * G = R_i + c * a_i * X_i
+        let expected = decompress(&nonce.point).c(d!())?
+            + c.mul(a).0 * decompress(&key.as_compressed_edwards_point()).c(d!())?;
+        if CompressedEdwardsY::scalar_mul_basepoint(&partial_sig.s)
+            != CompressedEdwardsY(expected.compress())
+        {
+            return Err(eg!(ZeiError::SignatureError));
+        }
+        s.add_assign(&partial_sig.s);
+    }
+
+    let mut bytes = nonce_point.0.as_bytes().to_vec();
+    bytes.extend_from_slice(&s.to_bytes());
+    XfrSignature::zei_from_bytes(&bytes).c(d!())
+}
+
+/// Runs all the rounds of an aggregated signature of `message` in a single process
+pub fn sign_aggregated<R: CryptoRng + RngCore>(
+    prng: &mut R,
+    key_pairs: &[&XfrKeyPair],
+    message: &[u8],
+) -> Result<XfrSignature> {
+    let mut key_pairs = key_pairs.to_owned();
+    key_pairs.sort_unstable_by_key(|kp| kp.pub_key);
+    key_pairs.dedup_by_key(|kp| kp.pub_key);
+    let signers = key_pairs.iter().map(|kp| &kp.pub_key).collect_vec();
+
+    let mut sessions = vec![];
+    let mut commitments = vec![];
+    for key_pair in key_pairs.iter() {
+        let (session, commitment) =
+            XfrMuSigSigner::new(prng, key_pair, &signers).c(d!())?;
+        sessions.push(session);
+        commitments.push(commitment);
+    }
+    let nonces = sessions.iter().map(|session| session.nonce()).collect_vec();
+    let partial_sigs = sessions
+        .into_iter()
+        .map(|session| session.sign(message, &commitments, &nonces))
+        .collect::<Result<Vec<_>>>()
+        .c(d!())?;
+    aggregate_partial_signatures(message, &signers, &nonces, &partial_sigs).c(d!())
+}
+
+// returns the sorted distinct keys of the signers
+fn signer_set(signers: &[&XfrPublicKey]) -> Result<Vec<XfrPublicKey>> {
+    let mut keys = signers.iter().map(|key| **key).collect_vec();
+    keys.sort();
+    keys.dedup();
+    if keys.is_empty() {
+        return Err(eg!(ZeiError::ParameterError));
+    }
+    Ok(keys)
+}
+
+// returns a_i = H(X_1 || ... || X_n || X_i) for each key X_i of the signer set
+fn key_coefficients(keys: &[XfrPublicKey]) -> Vec<Scalar> {
+    let mut set_hasher = Sha512::new();
+    set_hasher.update(MUSIG_KEY_COEFFICIENT_DOMAIN);
+    for key in keys.iter() {
+        set_hasher.update(key.as_bytes());
+    }
+    keys.iter()
+        .map(|key| {
+            let mut hasher = set_hasher.clone();
+            hasher.update(key.as_bytes());
+            Scalar::from_hash(hasher)
+        })
+        .collect_vec()
+}
+
+// returns sum(a_i * X_i)
+fn aggregate_keys(
+    keys: &[XfrPublicKey],
+    coefficients: &[Scalar],
+) -> Result<XfrPublicKey> {
+    let mut points = vec![];
+    for (key, a) in keys.iter().zip(coefficients.iter()) {
+        points.push(a.0 * decompress(&key.as_compressed_edwards_point()).c(d!())?);
+    }
+    let aggregated: EdwardsPoint = points.iter().sum();
+    XfrPublicKey::zei_from_bytes(aggregated.compress().as_bytes()).c(d!())
+}
+
+// returns sum(R_i)
+fn aggregate_nonces(nonces: &[&XfrMuSigNonce]) -> Result<CompressedEdwardsY> {
+    let mut points = vec![];
+    for nonce in nonces.iter() {
+        points.push(decompress(&nonce.point).c(d!())?);
+    }
+    let aggregated: EdwardsPoint = points.iter().sum();
+    Ok(CompressedEdwardsY(aggregated.compress()))
+}
+
+// ed25519 challenge H(R || A || message)
+fn challenge(
+    nonce_point: &CompressedEdwardsY,
+    aggregated_key: &XfrPublicKey,
+    message: &[u8],
+) -> Scalar {
+    let mut hasher = Sha512::new();
+    hasher.update(nonce_point.0.as_bytes());
+    hasher.update(aggregated_key.as_bytes());
+    hasher.update(message);
+    Scalar::from_hash(hasher)
+}
+
+fn nonce_commitment(nonce_point: &CompressedEdwardsY) -> Vec<u8> {
+    let mut hasher = Sha512::new();
+    hasher.update(MUSIG_NONCE_COMMITMENT_DOMAIN);
+    hasher.update(nonce_point.0.as_bytes());
+    hasher.finalize().to_vec()
+}
+
+fn decompress(point: &CompressedEdwardsY) -> Result<EdwardsPoint> {
+    point.decompress().c(d!(ZeiError::DecompressElementError))
+}
+
+// orders the messages of the signers as the signer set, each signer must send exactly one message
+fn order_by_signer<'b, T: SignerMessage>(
+    keys: &[XfrPublicKey],
+    messages: &'b [T],
+) -> Result<Vec<&'b T>> {
+    if messages.len() != keys.len() {
+        return Err(eg!(ZeiError::ParameterError));
+    }
+    keys.iter()
+        .map(|key| {
+            messages
+                .iter()
+                .find(|message| message.signer() == key)
+                .c(d!(ZeiError::ParameterError))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::setup::PublicParams;
+    use crate::xfr::asset_record::AssetRecordType;
+    use crate::xfr::lib::{
+        gen_xfr_note_with_aggregated_signature, verify_xfr_note,
+        xfr_body_signing_message, XfrNotePolicies,
+    };
+    use crate::xfr::musig::{
+        aggregate_partial_signatures, aggregate_public_keys, sign_aggregated,
+        XfrMuSigSigner,
+    };
+    use crate::xfr::sig::XfrKeyPair;
+    use crate::xfr::structs::{AssetRecord, AssetRecordTemplate, AssetType};
+    use itertools::Itertools;
+    use rand_chacha::ChaChaRng;
+    use rand_core::SeedableRng;
+    use utils::errors::ZeiError;
+
+    #[test]
+    fn test_aggregated_signature() {
+        let mut prng = ChaChaRng::from_seed([0u8; 32]);
+        let key_pairs = (0..3)
+            .map(|_| XfrKeyPair::generate(&mut prng))
+            .collect_vec();
+        let signers = key_pairs.iter().map(|kp| &kp.pub_key).collect_vec();
+        let message = b"transfer body";
+
+        // the key does not depend on the order of the signers
+        let aggregated_key = aggregate_public_keys(&signers).unwrap();
+        let reversed = signers.iter().rev().cloned().collect_vec();
+        assert_eq!(aggregate_public_keys(&reversed).unwrap(), aggregated_key);
+        assert_ne!(
+            aggregate_public_keys(&signers[..2]).unwrap(),
+            aggregated_key
+        );
+
+        // interactive session
+        let mut sessions = vec![];
+        let mut commitments = vec![];
+        for key_pair in key_pairs.iter() {
+            let (session, commitment) =
+                XfrMuSigSigner::new(&mut prng, key_pair, &signers).unwrap();
+            sessions.push(session);
+            commitments.push(commitment);
+        }
+        let nonces = sessions.iter().map(|s| s.nonce()).collect_vec();
+        let mut partial_sigs = sessions
+            .into_iter()
+            .map(|s| s.sign(message, &commitments, &nonces).unwrap())
+            .collect_vec();
+        // messages can be received in any order
+        partial_sigs.reverse();
+        let signature =
+            aggregate_partial_signatures(message, &signers, &nonces, &partial_sigs)
+                .unwrap();
+        assert!(aggregated_key.verify(message, &signature).is_ok());
+        let err = aggregated_key.verify(b"other", &signature).unwrap_err();
+        err_eq!(ZeiError::SignatureError, err);
+
+        // an invalid partial signature is detected
+        let mut bad_sigs = partial_sigs.clone();
+        bad_sigs[1].s = partial_sigs[0].s;
+        let err = aggregate_partial_signatures(message, &signers, &nonces, &bad_sigs)
+            .unwrap_err();
+        err_eq!(ZeiError::SignatureError, err);
+        // a missing partial signature is detected
+        assert!(aggregate_partial_signatures(
+            message,
+            &signers,
+            &nonces,
+            &partial_sigs[..2]
+        )
+        .is_err());
+
+        // a nonce that does not match its commitment is rejected
+        let (session, commitment) =
+            XfrMuSigSigner::new(&mut prng, &key_pairs[0], &signers).unwrap();
+        let mut commitments = vec![commitment];
+        let mut nonces = vec![session.nonce()];
+        for key_pair in key_pairs[1..].iter() {
+            let (other, commitment) =
+                XfrMuSigSigner::new(&mut prng, key_pair, &signers).unwrap();
+            commitments.push(commitment);
+            nonces.push(other.nonce());
+        }
+        nonces[1].point = nonces[2].point;
+        let err = session.sign(message, &commitments, &nonces).unwrap_err();
+        err_eq!(ZeiError::SignatureError, err);
+
+        // only members of the signer set start a session
+        let other = XfrKeyPair::generate(&mut prng);
+        assert!(XfrMuSigSigner::new(&mut prng, &other, &signers).is_err());
+
+        // single process signing
+        let key_pair_refs = key_pairs.iter().collect_vec();
+        let signature = sign_aggregated(&mut prng, &key_pair_refs, message).unwrap();
+        assert!(aggregated_key.verify(message, &signature).is_ok());
+    }
+
+    #[test]
+    fn test_xfr_note_with_aggregated_signature() {
+        let mut prng = ChaChaRng::from_seed([1u8; 32]);
+        let mut params = PublicParams::default();
+        let asset_type = AssetType::from_identical_byte(0);
+        let record_type = AssetRecordType::ConfidentialAmount_ConfidentialAssetType;
+        let key_pairs = (0..3)
+            .map(|_| XfrKeyPair::generate(&mut prng))
+            .collect_vec();
+        let recipient = XfrKeyPair::generate(&mut prng);
+
+        let inputs = key_pairs
+            .iter()
+            .map(|key_pair| {
+                let template = AssetRecordTemplate::with_no_asset_tracing(
+                    10,
+                    asset_type,
+                    record_type,
+                    key_pair.pub_key,
+                );
+                AssetRecord::from_template_no_identity_tracing(&mut prng, &template)
+                    .unwrap()
+            })
+            .collect_vec();
+        let template = AssetRecordTemplate::with_no_asset_tracing(
+            30,
+            asset_type,
+            record_type,
+            recipient.pub_key,
+        );
+        let output =
+            AssetRecord::from_template_no_identity_tracing(&mut prng, &template)
+                .unwrap();
+        let key_pair_refs = key_pairs.iter().collect_vec();
+        let policies = XfrNotePolicies::empty_policies(3, 1);
+
+        let note = gen_xfr_note_with_aggregated_signature(
+            &mut prng,
+            &inputs,
+            &[output],
+            &key_pair_refs,
+        )
+        .unwrap();
+        assert!(note.multisig.signatures.is_empty());
+        assert!(note.aggregated_signature.is_some());
+        assert!(
+            verify_xfr_note(&mut prng, &mut params, &note, &policies.to_ref()).is_ok()
+        );
+
+        // the signature is bound to the inputs of the body
+        let mut bad_note = note.clone();
+        bad_note.body.inputs[2] = bad_note.body.inputs[0].clone();
+        let err = verify_xfr_note(&mut prng, &mut params, &bad_note, &policies.to_ref())
+            .unwrap_err();
+        err_eq!(ZeiError::SignatureError, err);
+
+        // the signature of a subset of the keys does not verify
+        let message = xfr_body_signing_message(&note.body).unwrap();
+        let signature =
+            sign_aggregated(&mut prng, &key_pair_refs[..2], &message).unwrap();
+        let mut bad_note = note;
+        bad_note.aggregated_signature = Some(signature);
+        let err = verify_xfr_note(&mut prng, &mut params, &bad_note, &policies.to_ref())
+            .unwrap_err();
+        err_eq!(ZeiError::SignatureError, err);
+    }
+}
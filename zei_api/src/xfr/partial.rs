@@ -0,0 +1,309 @@
+use crate::xfr::lib::{gen_xfr_body, xfr_body_signing_message};
+use crate::xfr::sig::{XfrKeyPair, XfrMultiSig, XfrPublicKey, XfrSignature};
+use crate::xfr::structs::{AssetRecord, XfrBody, XfrNote};
+use itertools::Itertools;
+use rand_core::{CryptoRng, RngCore};
+use ruc::*;
+use utils::errors::ZeiError;
+
+/// Inputs and outputs contributed by the parties of a transfer.
+/// Each party adds the openings of its inputs and its outputs (with their memos), then a
+/// coordinator builds the body and its proofs. Key pairs are never part of a proposal.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct XfrNoteProposal {
+    pub inputs: Vec<AssetRecord>,
+    pub outputs: Vec<AssetRecord>,
+}
+
+impl XfrNoteProposal {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn add_input(&mut self, input: AssetRecord) {
+        self.inputs.push(input);
+    }
+
+    pub fn add_output(&mut self, output: AssetRecord) {
+        self.outputs.push(output);
+    }
+
+    /// Appends the inputs and outputs contributed by another party
+    pub fn merge(&mut self, other: XfrNoteProposal) {
+        self.inputs.extend(other.inputs);
+        self.outputs.extend(other.outputs);
+    }
+
+    /// Generates the body of the transfer and returns a note waiting for the signatures of the
+    /// inputs owners
+    pub fn build<R: CryptoRng + RngCore>(
+        &self,
+        prng: &mut R,
+    ) -> Result<PartiallySignedXfrNote> {
+        if self.inputs.is_empty() {
+            return Err(eg!(ZeiError::ParameterError));
+        }
+        let body = gen_xfr_body(prng, &self.inputs, &self.outputs).c(d!())?;
+        Ok(PartiallySignedXfrNote {
+            signatures: vec![None; body.inputs.len()],
+            body,
+        })
+    }
+}
+
+/// Signature of a transfer body by the owner of some of its inputs
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct XfrInputSignature {
+    pub pub_key: XfrPublicKey,
+    pub signature: XfrSignature,
+}
+
+/// A transfer note whose body is final but that is missing signatures of some inputs owners.
+/// Every owner signs the body independently, and the signatures are combined into a XfrNote.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct PartiallySignedXfrNote {
+    pub body: XfrBody,
+    /// Signature of the owner of each input, None if missing
+    pub signatures: Vec<Option<XfrSignature>>,
+}
+
+impl PartiallySignedXfrNote {
+    /// Signs the body with `key_pair`.
+    /// Returns ParameterError if `key_pair` does not own any input
+    pub fn sign(&self, key_pair: &XfrKeyPair) -> Result<XfrInputSignature> {
+        if !self
+            .body
+            .inputs
+            .iter()
+            .any(|input| input.public_key == key_pair.pub_key)
+        {
+            return Err(eg!(ZeiError::ParameterError));
+        }
+        let message = xfr_body_signing_message(&self.body).c(d!())?;
+        Ok(XfrInputSignature {
+            pub_key: key_pair.pub_key,
+            signature: key_pair.sign(&message),
+        })
+    }
+
+    /// Adds the signature of the owner of some inputs.
+    /// Returns ParameterError if the signer does not own any input or SignatureError
+    /// if the signature is not valid
+    pub fn add_signature(&mut self, signature: XfrInputSignature) -> Result<()> {
+        let message = xfr_body_signing_message(&self.body).c(d!())?;
+        signature
+            .pub_key
+            .verify(&message, &signature.signature)
+            .c(d!())?;
+        let mut owner = false;
+        for (input, slot) in self.body.inputs.iter().zip(self.signatures.iter_mut()) {
+            if input.public_key == signature.pub_key {
+                *slot = Some(signature.signature.clone());
+                owner = true;
+            }
+        }
+        if !owner {
+            return Err(eg!(ZeiError::ParameterError));
+        }
+        Ok(())
+    }
+
+    /// Adds the signatures of another copy of the note signed independently.
+    /// Returns ParameterError if the bodies are different or SignatureError if an added
+    /// signature is not valid for the owner of its input, in which case no signature is added
+    pub fn combine(&mut self, other: &PartiallySignedXfrNote) -> Result<()> {
+        if self.body != other.body || self.signatures.len() != other.signatures.len() {
+            return Err(eg!(ZeiError::ParameterError));
+        }
+        let message = xfr_body_signing_message(&self.body).c(d!())?;
+        let mut added = vec![];
+        for (i, (slot, other_slot)) in self
+            .signatures
+            .iter()
+            .zip(other.signatures.iter())
+            .enumerate()
+        {
+            if let (None, Some(signature)) = (slot, other_slot) {
+                self.body.inputs[i]
+                    .public_key
+                    .verify(&message, signature)
+                    .c(d!())?;
+                added.push((i, signature.clone()));
+            }
+        }
+        for (i, signature) in added {
+            self.signatures[i] = Some(signature);
+        }
+        Ok(())
+    }
+
+    /// Returns the distinct owners of the inputs that are not signed yet
+    pub fn missing_signers(&self) -> Vec<XfrPublicKey> {
+        self.body
+            .inputs
+            .iter()
+            .zip(self.signatures.iter())
+            .filter(|(_, slot)| slot.is_none())
+            .map(|(input, _)| input.public_key)
+            .sorted()
+            .dedup()
+            .collect_vec()
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.signatures.iter().all(|slot| slot.is_some())
+    }
+
+    /// Builds the XfrNote once every input is signed.
+    /// Returns ParameterError if a signature is missing
+    pub fn finalize(self) -> Result<XfrNote> {
+        if self.signatures.len() != self.body.inputs.len() {
+            return Err(eg!(ZeiError::ParameterError));
+        }
+        let mut signatures = vec![];
+        for (input, slot) in self.body.inputs.iter().zip(self.signatures.into_iter()) {
+            signatures.push((input.public_key, slot.c(d!(ZeiError::ParameterError))?));
+        }
+        // signatures of a multisig are sorted by public key, see `XfrMultiSig::sign`
+        signatures.sort_by_key(|(pub_key, _)| *pub_key);
+        Ok(XfrNote {
+            body: self.body,
+            multisig: XfrMultiSig {
+                signatures: signatures.into_iter().map(|(_, sig)| sig).collect_vec(),
+            },
+            threshold_signatures: vec![],
+            aggregated_signature: None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::setup::PublicParams;
+    use crate::xfr::asset_record::{open_blind_asset_record, AssetRecordType};
+    use crate::xfr::lib::{verify_xfr_note, XfrNotePolicies};
+    use crate::xfr::partial::{PartiallySignedXfrNote, XfrNoteProposal};
+    use crate::xfr::sig::XfrKeyPair;
+    use crate::xfr::structs::AssetType;
+    use crate::xfr::test_utils::untraced_asset_record;
+    use rand_chacha::ChaChaRng;
+    use rand_core::SeedableRng;
+    use utils::errors::ZeiError;
+
+    #[test]
+    fn test_partially_signed_xfr_note() {
+        let mut prng = ChaChaRng::from_seed([0u8; 32]);
+        let mut params = PublicParams::default();
+        let asset_type = AssetType::from_identical_byte(0);
+        let alice = XfrKeyPair::generate(&mut prng);
+        let bob = XfrKeyPair::generate(&mut prng);
+        let carol = XfrKeyPair::generate(&mut prng);
+
+        // alice contributes two inputs, bob one, and both pay carol
+        let mut alice_proposal = XfrNoteProposal::new();
+        alice_proposal.add_input(untraced_asset_record(
+            &mut prng,
+            10,
+            asset_type,
+            AssetRecordType::ConfidentialAmount_ConfidentialAssetType,
+            &alice.pub_key,
+        ));
+        alice_proposal.add_input(untraced_asset_record(
+            &mut prng,
+            5,
+            asset_type,
+            AssetRecordType::ConfidentialAmount_ConfidentialAssetType,
+            &alice.pub_key,
+        ));
+        alice_proposal.add_output(untraced_asset_record(
+            &mut prng,
+            15,
+            asset_type,
+            AssetRecordType::ConfidentialAmount_ConfidentialAssetType,
+            &carol.pub_key,
+        ));
+        let mut bob_proposal = XfrNoteProposal::new();
+        bob_proposal.add_input(untraced_asset_record(
+            &mut prng,
+            7,
+            asset_type,
+            AssetRecordType::ConfidentialAmount_ConfidentialAssetType,
+            &bob.pub_key,
+        ));
+        bob_proposal.add_output(untraced_asset_record(
+            &mut prng,
+            7,
+            asset_type,
+            AssetRecordType::ConfidentialAmount_ConfidentialAssetType,
+            &carol.pub_key,
+        ));
+
+        // the coordinator receives the serialized proposals
+        let mut proposal: XfrNoteProposal =
+            serde_json::from_str(&serde_json::to_string(&alice_proposal).unwrap())
+                .unwrap();
+        proposal.merge(
+            serde_json::from_str(&serde_json::to_string(&bob_proposal).unwrap())
+                .unwrap(),
+        );
+        let note = proposal.build(&mut prng).unwrap();
+        assert!(!note.is_complete());
+        let mut expected_signers = vec![alice.pub_key, bob.pub_key];
+        expected_signers.sort();
+        assert_eq!(note.missing_signers(), expected_signers);
+
+        // carol does not own any input
+        let err = note.sign(&carol).unwrap_err();
+        err_eq!(ZeiError::ParameterError, err);
+
+        // alice and bob sign their own copy
+        let mut alice_copy = note.clone();
+        let alice_sig = alice_copy.sign(&alice).unwrap();
+        alice_copy.add_signature(alice_sig.clone()).unwrap();
+        assert_eq!(alice_copy.missing_signers(), vec![bob.pub_key]);
+        let mut bob_copy: PartiallySignedXfrNote =
+            serde_json::from_str(&serde_json::to_string(&note).unwrap()).unwrap();
+        let bob_sig = bob_copy.sign(&bob).unwrap();
+        bob_copy.add_signature(bob_sig).unwrap();
+
+        // a signature by a key that does not own the claimed inputs is rejected
+        let mut bad_sig = alice_sig;
+        bad_sig.pub_key = bob.pub_key;
+        let err = bob_copy.clone().add_signature(bad_sig).unwrap_err();
+        err_eq!(ZeiError::SignatureError, err);
+
+        // incomplete notes cannot be finalized
+        let err = alice_copy.clone().finalize().unwrap_err();
+        err_eq!(ZeiError::ParameterError, err);
+
+        // notes with different bodies cannot be combined
+        let other_note = proposal.build(&mut prng).unwrap();
+        let err = alice_copy.clone().combine(&other_note).unwrap_err();
+        err_eq!(ZeiError::ParameterError, err);
+
+        // signatures of another copy are checked against the owners of their inputs:
+        // alice's signature cannot stand for bob's input
+        let mut forged_copy = bob_copy.clone();
+        forged_copy.signatures[2] = alice_copy.signatures[0].clone();
+        let mut combined = alice_copy.clone();
+        let err = combined.combine(&forged_copy).unwrap_err();
+        err_eq!(ZeiError::SignatureError, err);
+        assert_eq!(combined, alice_copy);
+
+        alice_copy.combine(&bob_copy).unwrap();
+        assert!(alice_copy.is_complete());
+        let xfr_note = alice_copy.finalize().unwrap();
+        let policies = XfrNotePolicies::empty_policies(3, 2);
+        assert!(
+            verify_xfr_note(&mut prng, &mut params, &xfr_note, &policies.to_ref())
+                .is_ok()
+        );
+        let oar = open_blind_asset_record(
+            &xfr_note.body.outputs[1],
+            &xfr_note.body.owners_memos[1],
+            &carol,
+        )
+        .unwrap();
+        assert_eq!(oar.amount, 7);
+    }
+}
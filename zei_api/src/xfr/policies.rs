@@ -0,0 +1,495 @@
+use crate::api::anon_creds::ACCommitment;
+use crate::setup::PublicParams;
+use crate::xfr::lib::{batch_verify_xfr_notes, XfrNotePoliciesRef};
+use crate::xfr::sig::XfrPublicKey;
+use crate::xfr::structs::{
+    AssetTracerEncKeys, AssetType, BlindAssetRecord, TracerMemo, TracingPolicies,
+    XfrAssetType, XfrBody, XfrNote,
+};
+use itertools::Itertools;
+use rand_core::{CryptoRng, RngCore};
+use ruc::*;
+use std::collections::HashMap;
+use utils::errors::ZeiError;
+
+// Policies of the records that are not traced
+static NO_POLICIES: TracingPolicies = TracingPolicies(Vec::new());
+
+/// Provides the tracing policies and credential commitments needed to verify transfers, so that
+/// a verifier registers them once instead of assembling XfrNotePolicies for every note
+pub trait PolicyResolver {
+    /// Tracing policies of the records of `asset_type`, None if they are not traced
+    fn asset_tracing_policies(&self, asset_type: &AssetType)
+        -> Option<&TracingPolicies>;
+
+    /// Credential commitment of `owner`, used to verify the identity tracing proofs of its records
+    fn credential_commitment(&self, owner: &XfrPublicKey) -> Option<&ACCommitment>;
+
+    /// Tracing policies of some asset type whose encryption keys are `enc_keys`, in order, None
+    /// if there are none. Used to resolve the records hiding their asset type
+    fn tracer_tracing_policies(
+        &self,
+        _enc_keys: &[&AssetTracerEncKeys],
+    ) -> Option<&TracingPolicies> {
+        None
+    }
+
+    /// Whether the records of some asset type are traced
+    fn has_asset_tracing_policies(&self) -> bool {
+        false
+    }
+
+    /// Tracing policies of `record`, whose tracing memos are `tracer_memos`. By default, the
+    /// policies of its asset type if it is revealed, otherwise the policies matching the
+    /// encryption keys of its memos. A record hiding its asset type without tracing memos is not
+    /// traced, the verifier cannot tell its asset type.
+    /// Returns ZeiError::ParameterError if the memos match no policies
+    fn record_tracing_policies(
+        &self,
+        record: &BlindAssetRecord,
+        tracer_memos: &[TracerMemo],
+    ) -> Result<Option<&TracingPolicies>> {
+        match &record.asset_type {
+            XfrAssetType::NonConfidential(asset_type) => {
+                Ok(self.asset_tracing_policies(asset_type))
+            }
+            XfrAssetType::Confidential(_) => {
+                if tracer_memos.is_empty() {
+                    return Ok(None);
+                }
+                let enc_keys =
+                    tracer_memos.iter().map(|memo| &memo.enc_key).collect_vec();
+                self.tracer_tracing_policies(&enc_keys)
+                    .map(Some)
+                    .c(d!(ZeiError::ParameterError))
+            }
+        }
+    }
+}
+
+/// PolicyResolver keeping the policies of each asset type and the credential commitments of
+/// each owner in memory
+#[derive(Clone, Default)]
+pub struct PolicyRegistry {
+    asset_policies: HashMap<AssetType, TracingPolicies>,
+    commitments: HashMap<XfrPublicKey, ACCommitment>,
+}
+
+impl PolicyRegistry {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Sets the tracing policies of the records of `asset_type`
+    pub fn register_asset_policies(
+        &mut self,
+        asset_type: AssetType,
+        policies: TracingPolicies,
+    ) {
+        self.asset_policies.insert(asset_type, policies);
+    }
+
+    /// Sets the credential commitment of `owner`
+    pub fn register_credential_commitment(
+        &mut self,
+        owner: XfrPublicKey,
+        commitment: ACCommitment,
+    ) {
+        self.commitments.insert(owner, commitment);
+    }
+}
+
+impl PolicyResolver for PolicyRegistry {
+    fn asset_tracing_policies(
+        &self,
+        asset_type: &AssetType,
+    ) -> Option<&TracingPolicies> {
+        self.asset_policies.get(asset_type)
+    }
+
+    /// The policies of the asset types traced with `enc_keys`, if they are all the same
+    fn tracer_tracing_policies(
+        &self,
+        enc_keys: &[&AssetTracerEncKeys],
+    ) -> Option<&TracingPolicies> {
+        let mut matching = self.asset_policies.values().filter(|policies| {
+            let policies = policies.get_policies();
+            policies.len() == enc_keys.len()
+                && policies
+                    .iter()
+                    .zip(enc_keys.iter())
+                    .all(|(policy, enc_key)| &policy.enc_keys == *enc_key)
+        });
+        let policies = matching.next()?;
+        if matching.all(|other| other == policies) {
+            Some(policies)
+        } else {
+            None
+        }
+    }
+
+    fn has_asset_tracing_policies(&self) -> bool {
+        !self.asset_policies.is_empty()
+    }
+
+    fn credential_commitment(&self, owner: &XfrPublicKey) -> Option<&ACCommitment> {
+        self.commitments.get(owner)
+    }
+}
+
+/// Resolves the tracing policies of every input and output of `body`, in order, and the credential
+/// commitments of the owners of the records whose policies require identity tracing.
+/// Returns an error if the policies of a record cannot be resolved
+pub fn resolve_xfr_policies<'a, P: PolicyResolver>(
+    resolver: &'a P,
+    body: &XfrBody,
+) -> Result<XfrNotePoliciesRef<'a>> {
+    let (inputs_memos, outputs_memos) = body
+        .asset_tracing_memos
+        .split_at(body.inputs.len().min(body.asset_tracing_memos.len()));
+    let (inputs_tracing_policies, inputs_sig_commitments) =
+        resolve_records_policies(resolver, &body.inputs, inputs_memos).c(d!())?;
+    let (outputs_tracing_policies, outputs_sig_commitments) =
+        resolve_records_policies(resolver, &body.outputs, outputs_memos).c(d!())?;
+    Ok(XfrNotePoliciesRef::new(
+        inputs_tracing_policies,
+        inputs_sig_commitments,
+        outputs_tracing_policies,
+        outputs_sig_commitments,
+    ))
+}
+
+#[allow(clippy::type_complexity)]
+fn resolve_records_policies<'a, P: PolicyResolver>(
+    resolver: &'a P,
+    records: &[BlindAssetRecord],
+    tracer_memos: &[Vec<TracerMemo>],
+) -> Result<(Vec<&'a TracingPolicies>, Vec<Option<&'a ACCommitment>>)> {
+    let resolved = records
+        .iter()
+        .enumerate()
+        .map(|(i, record)| -> Result<_> {
+            let memos = tracer_memos.get(i).map(Vec::as_slice).unwrap_or(&[]);
+            let policies = resolver
+                .record_tracing_policies(record, memos)
+                .c(d!())?
+                .unwrap_or(&NO_POLICIES);
+            let identity_tracing = policies
+                .get_policies()
+                .iter()
+                .any(|policy| policy.identity_tracing.is_some());
+            let commitment = if identity_tracing {
+                resolver.credential_commitment(&record.public_key)
+            } else {
+                None
+            };
+            Ok((policies, commitment))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(resolved.into_iter().unzip())
+}
+
+/// XfrNote verification with the policies provided by `resolver`
+/// * `prng` - pseudo-random number generator
+/// * `xfr_note` - XfrNote struct to be verified
+/// * `resolver` - provider of the policies of the records and the commitments of their owners
+/// * `returns` - () or an ZeiError in case of verification error
+pub fn verify_xfr_note_with_resolver<R: CryptoRng + RngCore, P: PolicyResolver>(
+    prng: &mut R,
+    params: &mut PublicParams,
+    xfr_note: &XfrNote,
+    resolver: &P,
+) -> Result<()> {
+    batch_verify_xfr_notes_with_resolver(prng, params, &[xfr_note], resolver).c(d!())
+}
+
+/// XfrNote batch verification with the policies provided by `resolver`
+/// * `prng` - pseudo-random number generator
+/// * `notes` - XfrNote structs to be verified
+/// * `resolver` - provider of the policies of the records and the commitments of their owners
+/// * `returns` - () or an ZeiError in case of verification error
+pub fn batch_verify_xfr_notes_with_resolver<
+    R: CryptoRng + RngCore,
+    P: PolicyResolver,
+>(
+    prng: &mut R,
+    params: &mut PublicParams,
+    notes: &[&XfrNote],
+    resolver: &P,
+) -> Result<()> {
+    let policies = notes
+        .iter()
+        .map(|note| resolve_xfr_policies(resolver, &note.body))
+        .collect::<Result<Vec<_>>>()
+        .c(d!())?;
+    let policies_refs = policies.iter().collect_vec();
+    batch_verify_xfr_notes(prng, params, notes, &policies_refs).c(d!())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::setup::PublicParams;
+    use crate::xfr::asset_record::AssetRecordType;
+    use crate::xfr::builder::XfrNoteBuilder;
+    use crate::xfr::policies::{
+        batch_verify_xfr_notes_with_resolver, verify_xfr_note_with_resolver,
+        PolicyRegistry,
+    };
+    use crate::xfr::sig::XfrKeyPair;
+    use crate::xfr::structs::{
+        AssetTracerKeyPair, AssetType, TracingPolicies, TracingPolicy,
+    };
+    use crate::xfr::test_utils::{
+        credential_with_tracing_policies, untraced_asset_record,
+    };
+    use rand_chacha::ChaChaRng;
+    use rand_core::SeedableRng;
+    use utils::errors::ZeiError;
+
+    #[test]
+    fn test_verify_with_asset_policies() {
+        let mut prng = ChaChaRng::from_seed([0u8; 32]);
+        let mut params = PublicParams::default();
+        let gold = AssetType::from_identical_byte(0);
+        let silver = AssetType::from_identical_byte(1);
+        let alice = XfrKeyPair::generate(&mut prng);
+        let bob = XfrKeyPair::generate(&mut prng);
+        let tracer = AssetTracerKeyPair::generate(&mut prng);
+        let gold_policies = TracingPolicies::from_policy(TracingPolicy {
+            enc_keys: tracer.enc_key,
+            asset_tracing: true,
+            identity_tracing: None,
+        });
+
+        let mut builder = XfrNoteBuilder::new(&alice);
+        builder
+            .add_spendable(
+                untraced_asset_record(
+                    &mut prng,
+                    10,
+                    silver,
+                    AssetRecordType::ConfidentialAmount_NonConfidentialAssetType,
+                    &alice.pub_key,
+                )
+                .open_asset_record,
+            )
+            .add_spendable(
+                untraced_asset_record(
+                    &mut prng,
+                    10,
+                    gold,
+                    AssetRecordType::ConfidentialAmount_NonConfidentialAssetType,
+                    &alice.pub_key,
+                )
+                .open_asset_record,
+            )
+            .add_payment(
+                7,
+                gold,
+                AssetRecordType::ConfidentialAmount_NonConfidentialAssetType,
+                bob.pub_key,
+            )
+            .add_payment(
+                10,
+                silver,
+                AssetRecordType::NonConfidentialAmount_NonConfidentialAssetType,
+                bob.pub_key,
+            )
+            .with_tracing_policies(gold, gold_policies.clone());
+        let (gold_note, _) = builder.build(&mut prng).unwrap();
+        let mut builder = XfrNoteBuilder::new(&bob);
+        builder
+            .add_spendable(
+                untraced_asset_record(
+                    &mut prng,
+                    4,
+                    silver,
+                    AssetRecordType::ConfidentialAmount_NonConfidentialAssetType,
+                    &bob.pub_key,
+                )
+                .open_asset_record,
+            )
+            .add_payment(
+                3,
+                silver,
+                AssetRecordType::ConfidentialAmount_NonConfidentialAssetType,
+                alice.pub_key,
+            );
+        let (silver_note, _) = builder.build(&mut prng).unwrap();
+
+        // the tracing memos of gold records cannot be checked without gold policies
+        let mut registry = PolicyRegistry::new();
+        assert!(verify_xfr_note_with_resolver(
+            &mut prng,
+            &mut params,
+            &gold_note,
+            &registry
+        )
+        .is_err());
+
+        registry.register_asset_policies(gold, gold_policies);
+        assert!(verify_xfr_note_with_resolver(
+            &mut prng,
+            &mut params,
+            &gold_note,
+            &registry
+        )
+        .is_ok());
+        assert!(batch_verify_xfr_notes_with_resolver(
+            &mut prng,
+            &mut params,
+            &[&gold_note, &silver_note],
+            &registry
+        )
+        .is_ok());
+
+        // silver records are not traced
+        let mut registry = PolicyRegistry::new();
+        registry.register_asset_policies(
+            silver,
+            TracingPolicies::from_policy(TracingPolicy {
+                enc_keys: AssetTracerKeyPair::generate(&mut prng).enc_key,
+                asset_tracing: true,
+                identity_tracing: None,
+            }),
+        );
+        assert!(verify_xfr_note_with_resolver(
+            &mut prng,
+            &mut params,
+            &silver_note,
+            &registry
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_verify_with_credential_commitment() {
+        let mut prng = ChaChaRng::from_seed([1u8; 32]);
+        let mut params = PublicParams::default();
+        let asset_type = AssetType::from_identical_byte(0);
+        let alice = XfrKeyPair::generate(&mut prng);
+        let tracer = AssetTracerKeyPair::generate(&mut prng);
+
+        let (user_sk, credential, key, commitment, policies) =
+            credential_with_tracing_policies(&mut prng, tracer.enc_key, &alice.pub_key);
+
+        let mut builder = XfrNoteBuilder::new(&alice);
+        builder
+            .add_spendable(
+                untraced_asset_record(
+                    &mut prng,
+                    10,
+                    asset_type,
+                    AssetRecordType::ConfidentialAmount_NonConfidentialAssetType,
+                    &alice.pub_key,
+                )
+                .open_asset_record,
+            )
+            .add_payment(
+                10,
+                asset_type,
+                AssetRecordType::ConfidentialAmount_NonConfidentialAssetType,
+                alice.pub_key,
+            )
+            .with_tracing_policies(asset_type, policies.clone())
+            .with_credential(&user_sk, &credential, &key, &commitment);
+        let (note, _) = builder.build(&mut prng).unwrap();
+
+        let mut registry = PolicyRegistry::new();
+        registry.register_asset_policies(asset_type, policies);
+        err_eq!(
+            ZeiError::XfrVerifyAssetTracingIdentityError,
+            verify_xfr_note_with_resolver(&mut prng, &mut params, &note, &registry)
+                .unwrap_err()
+        );
+
+        registry.register_credential_commitment(alice.pub_key, commitment);
+        assert!(
+            verify_xfr_note_with_resolver(&mut prng, &mut params, &note, &registry)
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn test_verify_confidential_asset_type() {
+        let mut prng = ChaChaRng::from_seed([2u8; 32]);
+        let mut params = PublicParams::default();
+        let gold = AssetType::from_identical_byte(0);
+        let silver = AssetType::from_identical_byte(1);
+        let alice = XfrKeyPair::generate(&mut prng);
+        let bob = XfrKeyPair::generate(&mut prng);
+        let tracer = AssetTracerKeyPair::generate(&mut prng);
+        let gold_policies = TracingPolicies::from_policy(TracingPolicy {
+            enc_keys: tracer.enc_key,
+            asset_tracing: true,
+            identity_tracing: None,
+        });
+        let record_type = AssetRecordType::ConfidentialAmount_ConfidentialAssetType;
+
+        // gold is sent with a hidden asset type and memos for the gold tracer
+        let mut builder = XfrNoteBuilder::new(&alice);
+        builder
+            .add_spendable(
+                untraced_asset_record(&mut prng, 10, gold, record_type, &alice.pub_key)
+                    .open_asset_record,
+            )
+            .add_payment(10, gold, record_type, bob.pub_key)
+            .with_tracing_policies(gold, gold_policies.clone());
+        let (gold_note, _) = builder.build(&mut prng).unwrap();
+
+        // silver is sent with a hidden asset type and no tracing memo
+        let mut builder = XfrNoteBuilder::new(&alice);
+        builder
+            .add_spendable(
+                untraced_asset_record(
+                    &mut prng,
+                    10,
+                    silver,
+                    record_type,
+                    &alice.pub_key,
+                )
+                .open_asset_record,
+            )
+            .add_payment(10, silver, record_type, bob.pub_key);
+        let (silver_note, _) = builder.build(&mut prng).unwrap();
+
+        // the gold records are resolved from the keys of their tracing memos
+        let mut registry = PolicyRegistry::new();
+        err_eq!(
+            ZeiError::ParameterError,
+            verify_xfr_note_with_resolver(&mut prng, &mut params, &gold_note, &registry)
+                .unwrap_err()
+        );
+        registry.register_asset_policies(gold, gold_policies);
+        assert!(batch_verify_xfr_notes_with_resolver(
+            &mut prng,
+            &mut params,
+            &[&gold_note, &silver_note],
+            &registry
+        )
+        .is_ok());
+
+        // memos for a tracer of no registered policy are rejected
+        let mut registry = PolicyRegistry::new();
+        registry.register_asset_policies(
+            gold,
+            TracingPolicies::from_policy(TracingPolicy {
+                enc_keys: AssetTracerKeyPair::generate(&mut prng).enc_key,
+                asset_tracing: true,
+                identity_tracing: None,
+            }),
+        );
+        err_eq!(
+            ZeiError::ParameterError,
+            verify_xfr_note_with_resolver(&mut prng, &mut params, &gold_note, &registry)
+                .unwrap_err()
+        );
+        assert!(verify_xfr_note_with_resolver(
+            &mut prng,
+            &mut params,
+            &silver_note,
+            &registry
+        )
+        .is_ok());
+    }
+}
@@ -1,6 +1,8 @@
 use crate::api::anon_creds::ACCommitment;
 use crate::api::anon_creds::{ac_confidential_verify, ACConfidentialRevealProof};
-use crate::setup::{PublicParams, BULLET_PROOF_RANGE, MAX_PARTY_NUMBER};
+use crate::setup::{
+    PublicParams, BULLET_PROOF_RANGE, BULLET_PROOF_RANGE_64, MAX_PARTY_NUMBER,
+};
 use crate::xfr::asset_record::AssetRecordType;
 use crate::xfr::asset_tracer::RecordDataEncKey;
 use crate::xfr::lib::XfrNotePoliciesRef;
@@ -76,15 +78,22 @@ fn build_same_key_asset_type_amount_tracing_proof<R: CryptoRng + RngCore>(
 
     for (record, memo) in records_memos {
         let open_record = &record.open_asset_record;
-        let (low, high) = u64_to_u32_pair(open_record.amount);
-        if let XfrAmount::Confidential((com_low, com_high)) =
-            open_record.blind_asset_record.amount
+        // a single commitment c is seen as (c, identity), see `XfrAmount::get_commitments`
+        let (low, high) = if open_record.blind_asset_record.amount.is_single_commitment()
+        {
+            (Scalar::from_u64(open_record.amount), Scalar::from_u32(0))
+        } else {
+            let (low, high) = u64_to_u32_pair(open_record.amount);
+            (Scalar::from_u32(low), Scalar::from_u32(high))
+        };
+        if let Some((com_low, com_high)) =
+            open_record.blind_asset_record.amount.get_commitments()
         {
             let (lock_amount_low, lock_amount_high) = memo
                 .lock_amount
                 .as_ref()
                 .c(d!(ZeiError::InconsistentStructureError))?;
-            m.push(Scalar::from_u32(low));
+            m.push(low);
             r.push(open_record.amount_blinds.0);
             ctexts.push(lock_amount_low.clone()); // TODO avoid this clone
             commitments.push(
@@ -92,7 +101,7 @@ fn build_same_key_asset_type_amount_tracing_proof<R: CryptoRng + RngCore>(
                     .decompress()
                     .c(d!(ZeiError::DecompressElementError))?,
             );
-            m.push(Scalar::from_u32(high));
+            m.push(high);
             r.push(open_record.amount_blinds.1);
             ctexts.push(lock_amount_high.clone()); // TODO avoid this clone
             commitments.push(
@@ -508,10 +517,20 @@ fn extract_ciphertext_and_commitments(
 /// I compute a range proof for confidential amount transfers.
 /// The proof guarantees that output amounts and difference between total input
 /// and total output are in the range [0,2^{64} - 1]
+/// If some record hides its amount in a single commitment, each amount is proven with a 64-bit
+/// range proof, otherwise each 32-bit half of the amounts is proven with a 32-bit range proof.
 pub(crate) fn range_proof(
     inputs: &[&OpenAssetRecord],
     outputs: &[&OpenAssetRecord],
 ) -> Result<XfrRangeProof> {
+    if has_single_commitment_amount(
+        inputs
+            .iter()
+            .chain(outputs.iter())
+            .map(|x| &x.blind_asset_record),
+    ) {
+        return range_proof_64(inputs, outputs).c(d!());
+    }
     let num_output = outputs.len();
     let upper_power2 =
         min_greater_equal_power_of_two((2 * (num_output + 1)) as u32) as usize;
@@ -578,6 +597,70 @@ pub(crate) fn range_proof(
         xfr_diff_commitment_high: diff_com_high,
     })
 }
+
+// Range proof over the amounts as a whole, the commitment of a record with split amount being
+// C_low + 2^32 * C_high. The difference is committed in the low commitment of the proof, the high
+// one being the identity
+fn range_proof_64(
+    inputs: &[&OpenAssetRecord],
+    outputs: &[&OpenAssetRecord],
+) -> Result<XfrRangeProof> {
+    let num_output = outputs.len();
+    let upper_power2 = min_greater_equal_power_of_two((num_output + 1) as u32) as usize;
+    if upper_power2 > MAX_PARTY_NUMBER {
+        return Err(eg!(ZeiError::RangeProofProveError));
+    }
+
+    let mut params = PublicParams::default();
+    params.increase_range_proof_gens(BULLET_PROOF_RANGE_64);
+
+    //build values vector (out amounts + amount difference)
+    let in_total = inputs.iter().fold(0u64, |accum, x| accum + x.amount);
+    let mut values = outputs.iter().map(|x| x.amount).collect_vec();
+    let out_total = values.iter().sum::<u64>();
+    if in_total < out_total {
+        return Err(eg!(ZeiError::RangeProofProveError));
+    }
+    values.push(in_total - out_total);
+    values.resize(upper_power2, 0u64);
+
+    //build blinding vectors (out blindings + blindings difference)
+    let pow2_32 = Scalar::from_u64(POW_2_32);
+    let combine_blinds = |(low, high): (Scalar, Scalar)| low.add(&pow2_32.mul(&high));
+    let mut range_proof_blinds = outputs
+        .iter()
+        .map(|x| combine_blinds(x.amount_blinds))
+        .collect_vec();
+    let xfr_blind_diff = combine_blinds(add_blindings(inputs))
+        .sub(&combine_blinds(add_blindings(outputs)));
+    range_proof_blinds.push(xfr_blind_diff);
+    range_proof_blinds.resize(upper_power2, Scalar::default());
+
+    let mut transcript = Transcript::new(b"Zei 64-bit Range Proof");
+    let (range_proof, coms) = prove_ranges(
+        &params.bp_gens,
+        &params.pc_gens,
+        &mut transcript,
+        values.as_slice(),
+        range_proof_blinds.as_slice(),
+        BULLET_PROOF_RANGE_64,
+    )
+    .c(d!(ZeiError::RangeProofProveError))?;
+
+    Ok(XfrRangeProof {
+        range_proof,
+        xfr_diff_commitment_low: coms[num_output],
+        xfr_diff_commitment_high: CompressedRistretto::identity(),
+    })
+}
+
+// Returns true if the range proof of a transfer with these records is a 64-bit range proof
+fn has_single_commitment_amount<'a, I: Iterator<Item = &'a BlindAssetRecord>>(
+    mut records: I,
+) -> bool {
+    records.any(|record| record.amount.is_single_commitment())
+}
+
 fn add_blindings(oar: &[&OpenAssetRecord]) -> (Scalar, Scalar) {
     oar.iter().fold(
         (Scalar::from_u32(0), Scalar::from_u32(0)),
@@ -587,22 +670,62 @@ fn add_blindings(oar: &[&OpenAssetRecord]) -> (Scalar, Scalar) {
 
 pub(crate) fn batch_verify_confidential_amount<R: CryptoRng + RngCore>(
     prng: &mut R,
-    params: &PublicParams,
+    params: &mut PublicParams,
     instances: &[(
         &Vec<BlindAssetRecord>,
         &Vec<BlindAssetRecord>,
         &XfrRangeProof,
     )],
 ) -> Result<()> {
-    let mut transcripts = vec![Transcript::new(b"Zei Range Proof"); instances.len()];
+    // notes with a single commitment amount are batched apart as their proofs are 64 bits long
+    let (instances_64, instances_32): (Vec<_>, Vec<_>) =
+        instances.iter().partition(|(input, output, _)| {
+            has_single_commitment_amount(input.iter().chain(output.iter()))
+        });
+    batch_verify_range_proofs(
+        prng,
+        params,
+        &instances_32,
+        b"Zei Range Proof",
+        BULLET_PROOF_RANGE,
+    )
+    .c(d!())?;
+    if !instances_64.is_empty() {
+        params.increase_range_proof_gens(BULLET_PROOF_RANGE_64);
+        batch_verify_range_proofs(
+            prng,
+            params,
+            &instances_64,
+            b"Zei 64-bit Range Proof",
+            BULLET_PROOF_RANGE_64,
+        )
+        .c(d!())?;
+    }
+    Ok(())
+}
+
+fn batch_verify_range_proofs<R: CryptoRng + RngCore>(
+    prng: &mut R,
+    params: &PublicParams,
+    instances: &[&(
+        &Vec<BlindAssetRecord>,
+        &Vec<BlindAssetRecord>,
+        &XfrRangeProof,
+    )],
+    transcript_label: &'static [u8],
+    range: usize,
+) -> Result<()> {
+    let mut transcripts = vec![Transcript::new(transcript_label); instances.len()];
     let proofs: Vec<&RangeProof> =
         instances.iter().map(|(_, _, pf)| &pf.range_proof).collect();
     let mut commitments = vec![];
     for (input, output, proof) in instances {
-        commitments.push(
+        let value_commitments = if range == BULLET_PROOF_RANGE_64 {
+            extract_value_commitments_64(input.as_slice(), output.as_slice(), proof)
+        } else {
             extract_value_commitments(input.as_slice(), output.as_slice(), proof)
-                .c(d!())?,
-        );
+        };
+        commitments.push(value_commitments.c(d!())?);
     }
     let value_commitments = commitments.iter().map(|c| c.as_slice()).collect_vec();
     batch_verify_ranges(
@@ -612,7 +735,7 @@ pub(crate) fn batch_verify_confidential_amount<R: CryptoRng + RngCore>(
         proofs.as_slice(),
         &mut transcripts,
         &value_commitments,
-        BULLET_PROOF_RANGE,
+        range,
     )
     .c(d!(ZeiError::XfrVerifyConfidentialAmountError))
 }
@@ -649,6 +772,10 @@ fn extract_value_commitments(
                     pc_gens.commit(Scalar::from_u32(high), Scalar::from_u32(0));
                 (com_low, com_high)
             }
+            // proven by a 64-bit range proof
+            XfrAmount::Confidential64(_) => {
+                return Err(eg!(ZeiError::XfrVerifyConfidentialAmountError));
+            }
         };
         total_input_com_low = total_input_com_low.add(&com_low);
         total_input_com_high = total_input_com_high.add(&com_high);
@@ -669,6 +796,9 @@ fn extract_value_commitments(
                     pc_gens.commit(Scalar::from_u32(high), Scalar::from_u32(0));
                 (com_low, com_high)
             }
+            XfrAmount::Confidential64(_) => {
+                return Err(eg!(ZeiError::XfrVerifyConfidentialAmountError));
+            }
         };
         total_output_com_low = total_output_com_low.add(&com_low);
         total_output_com_high = total_output_com_high.add(&com_high);
@@ -709,6 +839,74 @@ fn extract_value_commitments(
 
     Ok(commitments)
 }
+
+// Value commitments of a 64-bit range proof: the amount commitment of each output (its low and
+// high commitments combined if split) followed by the commitment to the difference
+fn extract_value_commitments_64(
+    inputs: &[BlindAssetRecord],
+    outputs: &[BlindAssetRecord],
+    proof: &XfrRangeProof,
+) -> Result<Vec<CompressedRistretto>> {
+    let upper_power2 =
+        min_greater_equal_power_of_two((outputs.len() + 1) as u32) as usize;
+
+    // the difference must be committed as a whole, otherwise its high part would not be
+    // covered by the range proof
+    if proof.xfr_diff_commitment_high != CompressedRistretto::identity() {
+        return Err(eg!(ZeiError::XfrVerifyConfidentialAmountError));
+    }
+
+    let mut commitments = Vec::with_capacity(upper_power2);
+    let mut total_input_com = RistrettoPoint::get_identity();
+    for input in inputs.iter() {
+        total_input_com = total_input_com.add(&amount_commitment(input).c(d!())?);
+    }
+    let mut total_output_com = RistrettoPoint::get_identity();
+    for output in outputs.iter() {
+        let com = amount_commitment(output).c(d!())?;
+        total_output_com = total_output_com.add(&com);
+        commitments.push(com.compress());
+    }
+
+    let proof_xfr_com_diff = proof
+        .xfr_diff_commitment_low
+        .decompress()
+        .c(d!(ZeiError::DecompressElementError))?;
+    if total_input_com.sub(&total_output_com).compress() != proof_xfr_com_diff.compress()
+    {
+        return Err(eg!(ZeiError::XfrVerifyConfidentialAmountError));
+    }
+    commitments.push(proof.xfr_diff_commitment_low);
+
+    for _ in commitments.len()..upper_power2 {
+        commitments.push(CompressedRistretto::identity());
+    }
+
+    Ok(commitments)
+}
+
+// Commitment to the whole amount of a record, C_low + 2^32 * C_high if split
+fn amount_commitment(record: &BlindAssetRecord) -> Result<RistrettoPoint> {
+    match record.amount {
+        XfrAmount::NonConfidential(amount) => {
+            let pc_gens = RistrettoPedersenGens::default();
+            Ok(pc_gens.commit(Scalar::from_u64(amount), Scalar::from_u32(0)))
+        }
+        _ => {
+            let (com_low, com_high) = record
+                .amount
+                .get_commitments()
+                .c(d!(ZeiError::XfrVerifyConfidentialAmountError))?;
+            let com_low = com_low
+                .decompress()
+                .c(d!(ZeiError::XfrVerifyConfidentialAmountError))?;
+            let com_high = com_high
+                .decompress()
+                .c(d!(ZeiError::XfrVerifyConfidentialAmountError))?;
+            Ok(com_low.add(&com_high.mul(&Scalar::from_u64(POW_2_32))))
+        }
+    }
+}
 /**** Asset Equality Proofs *****/
 
 /// I compute asset equality proof for confidential asset transfers
@@ -9,7 +9,8 @@ use crate::xfr::asset_record::AssetRecordType;
 use crate::xfr::asset_tracer::{
     RecordDataCiphertext, RecordDataDecKey, RecordDataEncKey,
 };
-use crate::xfr::sig::{XfrKeyPair, XfrMultiSig, XfrPublicKey};
+use crate::xfr::sig::{XfrKeyPair, XfrMultiSig, XfrPublicKey, XfrSignature};
+use crate::xfr::threshold::{XfrThresholdPolicy, XfrThresholdSig};
 use algebra::bls12_381::BLSG1;
 use algebra::groups::{Group, Scalar as ZeiScalar};
 use algebra::ristretto::{
@@ -103,6 +104,14 @@ impl AssetTypeZeiRepr {
 pub struct XfrNote {
     pub body: XfrBody,
     pub multisig: XfrMultiSig,
+    /// Signatures of the inputs owned by a threshold policy, in the order of the inputs,
+    /// see `xfr::threshold`
+    #[serde(default)]
+    pub threshold_signatures: Vec<XfrThresholdSig>,
+    /// Single signature of the input keys under their aggregated key, replacing the
+    /// signatures of `multisig`, see `xfr::musig`
+    #[serde(default)]
+    pub aggregated_signature: Option<XfrSignature>,
 }
 
 impl XfrNote {
@@ -119,6 +128,22 @@ pub struct XfrBody {
     pub proofs: XfrProofs,
     pub asset_tracing_memos: Vec<Vec<TracerMemo>>, // each input or output can have a set of tracing memos
     pub owners_memos: Vec<Option<OwnerMemo>>, // If confidential amount or asset type, lock the amount and/or asset type to the public key in asset_record
+    /// Locks of the other members of the threshold policies owning the outputs, see
+    /// `xfr::threshold`. Empty if no output is owned by a threshold policy, otherwise one
+    /// (possibly empty) vector per output
+    #[serde(default)]
+    pub owners_member_locks: Vec<Vec<ZeiHybridCipher>>,
+}
+
+impl XfrBody {
+    /// Returns the locks of the other members of the threshold policy owning the `index`-th
+    /// output, empty if the output is owned by a single key
+    pub fn output_member_locks(&self, index: usize) -> &[ZeiHybridCipher] {
+        self.owners_member_locks
+            .get(index)
+            .map(|locks| locks.as_slice())
+            .unwrap_or(&[])
+    }
 }
 
 /// A transfer input or output record as seen in the ledger
@@ -132,10 +157,20 @@ pub struct BlindAssetRecord {
 
 impl BlindAssetRecord {
     pub fn get_record_type(&self) -> AssetRecordType {
-        AssetRecordType::from_flags(
-            matches!(self.amount, XfrAmount::Confidential(_)),
-            matches!(self.asset_type, XfrAssetType::Confidential(_)),
-        )
+        let confidential_asset_type =
+            matches!(self.asset_type, XfrAssetType::Confidential(_));
+        match self.amount {
+            XfrAmount::Confidential64(_) if confidential_asset_type => {
+                AssetRecordType::ConfidentialAmount64_ConfidentialAssetType
+            }
+            XfrAmount::Confidential64(_) => {
+                AssetRecordType::ConfidentialAmount64_NonConfidentialAssetType
+            }
+            _ => AssetRecordType::from_flags(
+                matches!(self.amount, XfrAmount::Confidential(_)),
+                confidential_asset_type,
+            ),
+        }
     }
 
     // TODO: (alex) remove this if the concept of public v.s. hidden asset are no longer in use
@@ -148,13 +183,16 @@ impl BlindAssetRecord {
     }
 }
 
-/// Amount in blind asset record: if confidential, provide commitments for lower and hight 32 bits
+/// Amount in blind asset record: if confidential, provide commitments for lower and hight 32 bits,
+/// or a single commitment to the 64-bit amount
 #[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
 pub enum XfrAmount {
     // amount is a 64 bit positive integer expressed in base 2^32 in confidential transactions
     Confidential((CompressedRistretto, CompressedRistretto)),
     #[serde(with = "serde_str")]
     NonConfidential(u64),
+    // amount is a 64 bit positive integer committed as a whole, its range proof is 64 bits long
+    Confidential64(CompressedRistretto),
 }
 
 impl XfrAmount {
@@ -169,7 +207,15 @@ impl XfrAmount {
     /// assert!(!xfr_amount.is_confidential());
     /// ```
     pub fn is_confidential(&self) -> bool {
-        matches!(self, XfrAmount::Confidential(_))
+        matches!(
+            self,
+            XfrAmount::Confidential(_) | XfrAmount::Confidential64(_)
+        )
+    }
+
+    /// Returns true only if amount is hidden in a single 64-bit commitment
+    pub fn is_single_commitment(&self) -> bool {
+        matches!(self, XfrAmount::Confidential64(_))
     }
     /// Return Some(amount) if amount is non-confidential. Otherwise, return None
     /// # Example:
@@ -189,7 +235,9 @@ impl XfrAmount {
     }
 
     /// Return Some((c1,c2)), where (c1,c2) is a commitment to the amount
-    /// if amount is confidential. Otherwise, return None
+    /// if amount is confidential. Otherwise, return None.
+    /// A single 64-bit commitment c is returned as (c, identity), so that c1 + 2^32 * c2
+    /// is a commitment to the amount in both encodings
     /// # Example:
     /// ```
     /// use zei::xfr::structs::XfrAmount;
@@ -202,6 +250,7 @@ impl XfrAmount {
     pub fn get_commitments(&self) -> Option<(CompressedRistretto, CompressedRistretto)> {
         match self {
             XfrAmount::Confidential(x) => Some(*x),
+            XfrAmount::Confidential64(x) => Some((*x, CompressedRistretto::identity())),
             _ => None,
         }
     }
@@ -222,6 +271,16 @@ impl XfrAmount {
             .compress();
         XfrAmount::Confidential((comm_lo, comm_hi))
     }
+
+    /// construct a confidential XfrAmount with a single commitment to amount
+    pub fn from_single_blind(
+        pc_gens: &RistrettoPedersenGens,
+        amount: u64,
+        blind: &Scalar,
+    ) -> Self {
+        let comm = pc_gens.commit(Scalar::from_u64(amount), *blind).compress();
+        XfrAmount::Confidential64(comm)
+    }
 }
 
 /// Asset type in BlindAsset record: if confidential, provide commitment.
@@ -573,7 +632,9 @@ impl OwnerMemo {
     // Given a shared point, calculate the amount blinds
     // returns (amount_blind_low, amount_blind_high)
     // noted shared_point = PK ^ r = blind_share ^ sk = (g^sk) ^ r
-    fn calc_amount_blinds(shared_point: &CompressedEdwardsY) -> (Scalar, Scalar) {
+    pub(crate) fn calc_amount_blinds(
+        shared_point: &CompressedEdwardsY,
+    ) -> (Scalar, Scalar) {
         (
             OwnerMemo::hash_to_scalar(&shared_point, b"amount_low"),
             OwnerMemo::hash_to_scalar(&shared_point, b"amount_high"),
@@ -582,7 +643,7 @@ impl OwnerMemo {
 
     // Given a shared point, calculate the asset type blind
     // noted shared_point = PK ^ r = blind_share ^ sk = (g^sk) ^ r
-    fn calc_asset_type_blind(shared_point: &CompressedEdwardsY) -> Scalar {
+    pub(crate) fn calc_asset_type_blind(shared_point: &CompressedEdwardsY) -> Scalar {
         OwnerMemo::hash_to_scalar(&shared_point, b"asset_type")
     }
 
@@ -590,7 +651,7 @@ impl OwnerMemo {
     // during `OwnerMemo` creation, point = PublicKey = g^sk, s = r, where r is the randomization scalar
     // during `OwnerMemo` decryption, point = blind_share = g^r, s = sk, where sk is the secret key
     // in both cases, returns g^(sk*r) in `CompressedEdwardsY` form
-    fn derive_shared_edwards_point(
+    pub(crate) fn derive_shared_edwards_point(
         s: &Scalar,
         point: &CompressedEdwardsY,
     ) -> Result<CompressedEdwardsY> {
@@ -645,6 +706,10 @@ pub struct AssetRecord {
     pub identity_proofs: Vec<Option<ACConfidentialRevealProof>>,
     pub asset_tracers_memos: Vec<TracerMemo>,
     pub owner_memo: Option<OwnerMemo>,
+    /// Locks of the other members of the threshold policy owning the record, empty if the
+    /// record is owned by a single key, see `xfr::threshold`
+    #[serde(default)]
+    pub owner_member_locks: Vec<ZeiHybridCipher>,
 }
 
 /// An asset record template: amount, asset type, owner public key, type and tracing
@@ -656,6 +721,9 @@ pub struct AssetRecordTemplate {
     pub public_key: XfrPublicKey, // ownership address
     pub asset_record_type: AssetRecordType,
     pub asset_tracing_policies: TracingPolicies,
+    /// Threshold policy owning the record, `public_key` must be the policy address
+    #[serde(default)]
+    pub threshold_owner: Option<XfrThresholdPolicy>,
 }
 
 // PROOFS STRUCTURES
@@ -0,0 +1,520 @@
+//! Two-party confidential swap: the offerer gives `offered_amount` of an asset type in exchange of
+//! `requested_amount` of another asset type. Both legs are settled by a single confidential
+//! multi-asset XfrNote, valid only once both parties signed it.
+//! The offer and acceptance messages contain record openings and are sent privately to the
+//! counterparty, outsiders only see a confidential transfer.
+use crate::xfr::asset_record::AssetRecordType;
+use crate::xfr::partial::{PartiallySignedXfrNote, XfrNoteProposal};
+use crate::xfr::sig::{XfrKeyPair, XfrPublicKey};
+use crate::xfr::structs::{
+    AssetRecord, AssetRecordTemplate, AssetType, OpenAssetRecord, XfrNote,
+};
+use itertools::Itertools;
+use rand_core::{CryptoRng, RngCore};
+use ruc::*;
+use utils::errors::ZeiError;
+
+/// Swap offer, sent by the offerer to the counterparty
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct XfrSwapOffer {
+    /// Inputs of the offerer, its output receiving the requested asset and its change
+    pub proposal: XfrNoteProposal,
+    #[serde(with = "serde_str")]
+    pub offered_amount: u64,
+    pub offered_asset_type: AssetType,
+    #[serde(with = "serde_str")]
+    pub requested_amount: u64,
+    pub requested_asset_type: AssetType,
+}
+
+/// Swap acceptance, sent by the counterparty back to the offerer: the note settling the swap,
+/// signed by the counterparty
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct XfrSwapAcceptance {
+    pub note: PartiallySignedXfrNote,
+}
+
+/// Creates an offer of `offered_amount` units of the asset type of `inputs` in exchange of
+/// `requested_amount` units of `requested_asset_type`. The change and the requested asset are
+/// paid to `owner` in confidential records.
+/// Returns ParameterError if the inputs are not all owned by `owner`, of the same asset type
+/// different from the requested one, or if they do not cover the offered amount
+pub fn create_swap_offer<R: CryptoRng + RngCore>(
+    prng: &mut R,
+    inputs: Vec<OpenAssetRecord>,
+    offered_amount: u64,
+    requested_amount: u64,
+    requested_asset_type: AssetType,
+    owner: &XfrPublicKey,
+) -> Result<XfrSwapOffer> {
+    let (proposal, offered_asset_type) = swap_proposal(
+        prng,
+        inputs,
+        offered_amount,
+        requested_amount,
+        requested_asset_type,
+        owner,
+    )
+    .c(d!())?;
+    Ok(XfrSwapOffer {
+        proposal,
+        offered_amount,
+        offered_asset_type,
+        requested_amount,
+        requested_asset_type,
+    })
+}
+
+/// Accepts `offer`, paying the requested amount from `inputs` owned by `key_pair`.
+/// The counterparty receives the offered amount and its change in confidential records, and
+/// signs the resulting note.
+/// Returns ParameterError if the offer or the inputs are not consistent or if the offer spends
+/// records owned by `key_pair`, and XfrCreationAssetAmountError if the offer records do not
+/// balance the offered and requested amounts
+pub fn accept_swap_offer<R: CryptoRng + RngCore>(
+    prng: &mut R,
+    offer: &XfrSwapOffer,
+    inputs: Vec<OpenAssetRecord>,
+    key_pair: &XfrKeyPair,
+) -> Result<XfrSwapAcceptance> {
+    // the signature of the counterparty spends every input it owns
+    if offer.proposal.inputs.iter().any(|input| {
+        input.open_asset_record.blind_asset_record.public_key == key_pair.pub_key
+    }) {
+        return Err(eg!(ZeiError::ParameterError));
+    }
+    let offer_records = offer
+        .proposal
+        .inputs
+        .iter()
+        .chain(offer.proposal.outputs.iter());
+    for record in offer_records {
+        let asset_type = record.open_asset_record.asset_type;
+        if asset_type != offer.offered_asset_type
+            && asset_type != offer.requested_asset_type
+        {
+            return Err(eg!(ZeiError::ParameterError));
+        }
+    }
+    let (counter_proposal, asset_type) = swap_proposal(
+        prng,
+        inputs,
+        offer.requested_amount,
+        offer.offered_amount,
+        offer.offered_asset_type,
+        &key_pair.pub_key,
+    )
+    .c(d!())?;
+    if asset_type != offer.requested_asset_type {
+        return Err(eg!(ZeiError::ParameterError));
+    }
+
+    let mut proposal = offer.proposal.clone();
+    proposal.merge(counter_proposal);
+    let mut note = proposal.build(prng).c(d!())?;
+    let signature = note.sign(key_pair).c(d!())?;
+    note.add_signature(signature).c(d!())?;
+    Ok(XfrSwapAcceptance { note })
+}
+
+/// Checks that the note of `acceptance` pays the outputs of `offer` and that the inputs it spends
+/// from `key_pair` are exactly the inputs of `offer`, signs it with `key_pair` and returns the
+/// complete XfrNote.
+/// Returns ParameterError if the note does not settle the offer or if the counterparty did not
+/// sign all its inputs
+pub fn complete_swap(
+    offer: &XfrSwapOffer,
+    acceptance: XfrSwapAcceptance,
+    key_pair: &XfrKeyPair,
+) -> Result<XfrNote> {
+    let mut note = acceptance.note;
+    // the signature of the offerer spends every input it owns
+    let owned_inputs = note
+        .body
+        .inputs
+        .iter()
+        .filter(|input| input.public_key == key_pair.pub_key)
+        .collect_vec();
+    if owned_inputs.len() != offer.proposal.inputs.len()
+        || offer.proposal.inputs.iter().any(|input| {
+            !owned_inputs.contains(&&input.open_asset_record.blind_asset_record)
+        })
+    {
+        return Err(eg!(ZeiError::ParameterError));
+    }
+    // the offerer must be able to open its outputs
+    for output in offer.proposal.outputs.iter() {
+        let position = note
+            .body
+            .outputs
+            .iter()
+            .position(|bar| *bar == output.open_asset_record.blind_asset_record)
+            .c(d!(ZeiError::ParameterError))?;
+        if note.body.owners_memos.get(position) != Some(&output.owner_memo) {
+            return Err(eg!(ZeiError::ParameterError));
+        }
+    }
+    let signature = note.sign(key_pair).c(d!())?;
+    note.add_signature(signature).c(d!())?;
+    note.finalize().c(d!())
+}
+
+// Builds the records of a party giving `give_amount` of the asset type of `inputs` and receiving
+// `receive_amount` of `receive_asset_type`. Returns the proposal and the given asset type
+fn swap_proposal<R: CryptoRng + RngCore>(
+    prng: &mut R,
+    inputs: Vec<OpenAssetRecord>,
+    give_amount: u64,
+    receive_amount: u64,
+    receive_asset_type: AssetType,
+    owner: &XfrPublicKey,
+) -> Result<(XfrNoteProposal, AssetType)> {
+    let give_asset_type = inputs.first().c(d!(ZeiError::ParameterError))?.asset_type;
+    if give_asset_type == receive_asset_type {
+        return Err(eg!(ZeiError::ParameterError));
+    }
+    let mut total = 0u128;
+    for input in inputs.iter() {
+        if input.asset_type != give_asset_type || input.get_pub_key() != owner {
+            return Err(eg!(ZeiError::ParameterError));
+        }
+        total += input.amount as u128;
+    }
+    if total < give_amount as u128 {
+        return Err(eg!(ZeiError::ParameterError));
+    }
+
+    let mut proposal = XfrNoteProposal::new();
+    for input in inputs {
+        proposal.add_input(AssetRecord::from_open_asset_record_no_asset_tracing(input));
+    }
+    proposal.add_output(
+        confidential_record(prng, receive_amount, receive_asset_type, owner).c(d!())?,
+    );
+    let change = (total - give_amount as u128) as u64;
+    if change > 0 {
+        proposal.add_output(
+            confidential_record(prng, change, give_asset_type, owner).c(d!())?,
+        );
+    }
+    Ok((proposal, give_asset_type))
+}
+
+fn confidential_record<R: CryptoRng + RngCore>(
+    prng: &mut R,
+    amount: u64,
+    asset_type: AssetType,
+    owner: &XfrPublicKey,
+) -> Result<AssetRecord> {
+    let template = AssetRecordTemplate::with_no_asset_tracing(
+        amount,
+        asset_type,
+        AssetRecordType::ConfidentialAmount_ConfidentialAssetType,
+        *owner,
+    );
+    AssetRecord::from_template_no_identity_tracing(prng, &template).c(d!())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::setup::PublicParams;
+    use crate::xfr::asset_record::{open_blind_asset_record, AssetRecordType};
+    use crate::xfr::lib::{verify_xfr_note, XfrNotePolicies};
+    use crate::xfr::sig::XfrKeyPair;
+    use crate::xfr::structs::AssetType;
+    use crate::xfr::swap::{
+        accept_swap_offer, complete_swap, create_swap_offer, XfrSwapAcceptance,
+        XfrSwapOffer,
+    };
+    use crate::xfr::test_utils::untraced_asset_record;
+    use rand_chacha::ChaChaRng;
+    use rand_core::SeedableRng;
+    use utils::errors::ZeiError;
+
+    #[test]
+    fn test_swap() {
+        let mut prng = ChaChaRng::from_seed([0u8; 32]);
+        let mut params = PublicParams::default();
+        let asset_x = AssetType::from_identical_byte(1);
+        let asset_y = AssetType::from_identical_byte(2);
+        let alice = XfrKeyPair::generate(&mut prng);
+        let bob = XfrKeyPair::generate(&mut prng);
+
+        // alice offers 6 X out of a record of 10 X, in exchange of 3 Y
+        let alice_input = untraced_asset_record(
+            &mut prng,
+            10,
+            asset_x,
+            AssetRecordType::ConfidentialAmount_ConfidentialAssetType,
+            &alice.pub_key,
+        )
+        .open_asset_record;
+        let offer = create_swap_offer(
+            &mut prng,
+            vec![alice_input],
+            6,
+            3,
+            asset_y,
+            &alice.pub_key,
+        )
+        .unwrap();
+        let offer: XfrSwapOffer =
+            serde_json::from_str(&serde_json::to_string(&offer).unwrap()).unwrap();
+
+        // bob accepts with a record of 5 Y
+        let bob_input = untraced_asset_record(
+            &mut prng,
+            5,
+            asset_y,
+            AssetRecordType::ConfidentialAmount_ConfidentialAssetType,
+            &bob.pub_key,
+        )
+        .open_asset_record;
+        let acceptance =
+            accept_swap_offer(&mut prng, &offer, vec![bob_input], &bob).unwrap();
+        let acceptance: XfrSwapAcceptance =
+            serde_json::from_str(&serde_json::to_string(&acceptance).unwrap()).unwrap();
+
+        // the note is not valid until alice signs it
+        assert_eq!(acceptance.note.missing_signers(), vec![alice.pub_key]);
+        assert!(acceptance.note.clone().finalize().is_err());
+
+        let note = complete_swap(&offer, acceptance.clone(), &alice).unwrap();
+        let policies = XfrNotePolicies::empty_policies(2, 4);
+        assert!(
+            verify_xfr_note(&mut prng, &mut params, &note, &policies.to_ref()).is_ok()
+        );
+
+        // outputs: alice 3 Y, alice 4 X change, bob 6 X, bob 2 Y change
+        let expected = [
+            (&alice, 3, asset_y),
+            (&alice, 4, asset_x),
+            (&bob, 6, asset_x),
+            (&bob, 2, asset_y),
+        ];
+        for (i, (key_pair, amount, asset_type)) in expected.iter().enumerate() {
+            let oar = open_blind_asset_record(
+                &note.body.outputs[i],
+                &note.body.owners_memos[i],
+                key_pair,
+            )
+            .unwrap();
+            assert_eq!(oar.amount, *amount);
+            assert_eq!(oar.asset_type, *asset_type);
+        }
+
+        // the counterparty cannot redirect the outputs of the offerer
+        let mut bad_acceptance = acceptance.clone();
+        bad_acceptance.note.body.outputs.swap(0, 2);
+        let err = complete_swap(&offer, bad_acceptance, &alice).unwrap_err();
+        err_eq!(ZeiError::ParameterError, err);
+        let mut bad_acceptance = acceptance;
+        bad_acceptance.note.body.owners_memos.swap(0, 2);
+        let err = complete_swap(&offer, bad_acceptance, &alice).unwrap_err();
+        err_eq!(ZeiError::ParameterError, err);
+    }
+
+    #[test]
+    fn test_swap_abort() {
+        let mut prng = ChaChaRng::from_seed([1u8; 32]);
+        let asset_x = AssetType::from_identical_byte(1);
+        let asset_y = AssetType::from_identical_byte(2);
+        let asset_z = AssetType::from_identical_byte(3);
+        let alice = XfrKeyPair::generate(&mut prng);
+        let bob = XfrKeyPair::generate(&mut prng);
+
+        let alice_input = untraced_asset_record(
+            &mut prng,
+            10,
+            asset_x,
+            AssetRecordType::ConfidentialAmount_ConfidentialAssetType,
+            &alice.pub_key,
+        )
+        .open_asset_record;
+        // not enough funds, same asset types, inputs not owned by the offerer
+        assert!(create_swap_offer(
+            &mut prng,
+            vec![alice_input.clone()],
+            11,
+            3,
+            asset_y,
+            &alice.pub_key
+        )
+        .is_err());
+        assert!(create_swap_offer(
+            &mut prng,
+            vec![alice_input.clone()],
+            6,
+            3,
+            asset_x,
+            &alice.pub_key
+        )
+        .is_err());
+        assert!(create_swap_offer(
+            &mut prng,
+            vec![alice_input.clone()],
+            6,
+            3,
+            asset_y,
+            &bob.pub_key
+        )
+        .is_err());
+
+        let offer = create_swap_offer(
+            &mut prng,
+            vec![alice_input],
+            6,
+            3,
+            asset_y,
+            &alice.pub_key,
+        )
+        .unwrap();
+
+        // bob does not have enough of the requested asset
+        let bob_input = untraced_asset_record(
+            &mut prng,
+            2,
+            asset_y,
+            AssetRecordType::ConfidentialAmount_ConfidentialAssetType,
+            &bob.pub_key,
+        )
+        .open_asset_record;
+        assert!(accept_swap_offer(&mut prng, &offer, vec![bob_input], &bob).is_err());
+        // bob pays with another asset type
+        let bob_input = untraced_asset_record(
+            &mut prng,
+            5,
+            asset_z,
+            AssetRecordType::ConfidentialAmount_ConfidentialAssetType,
+            &bob.pub_key,
+        )
+        .open_asset_record;
+        assert!(accept_swap_offer(&mut prng, &offer, vec![bob_input], &bob).is_err());
+
+        // an offer whose records do not match its amounts does not balance
+        let bob_input = untraced_asset_record(
+            &mut prng,
+            5,
+            asset_y,
+            AssetRecordType::ConfidentialAmount_ConfidentialAssetType,
+            &bob.pub_key,
+        )
+        .open_asset_record;
+        let mut bad_offer = offer.clone();
+        bad_offer.offered_amount = 7;
+        let err =
+            accept_swap_offer(&mut prng, &bad_offer, vec![bob_input.clone()], &bob)
+                .unwrap_err();
+        err_eq!(ZeiError::XfrCreationAssetAmountError, err);
+
+        // alice cannot complete a swap with another offer
+        let acceptance =
+            accept_swap_offer(&mut prng, &offer, vec![bob_input], &bob).unwrap();
+        let alice_input = untraced_asset_record(
+            &mut prng,
+            10,
+            asset_x,
+            AssetRecordType::ConfidentialAmount_ConfidentialAssetType,
+            &alice.pub_key,
+        )
+        .open_asset_record;
+        let other_offer = create_swap_offer(
+            &mut prng,
+            vec![alice_input],
+            6,
+            3,
+            asset_y,
+            &alice.pub_key,
+        )
+        .unwrap();
+        let err = complete_swap(&other_offer, acceptance.clone(), &alice).unwrap_err();
+        err_eq!(ZeiError::ParameterError, err);
+        // only alice completes the swap
+        let err = complete_swap(&offer, acceptance, &bob).unwrap_err();
+        err_eq!(ZeiError::ParameterError, err);
+    }
+
+    #[test]
+    fn test_swap_theft() {
+        let mut prng = ChaChaRng::from_seed([2u8; 32]);
+        let asset_x = AssetType::from_identical_byte(1);
+        let asset_y = AssetType::from_identical_byte(2);
+        let alice = XfrKeyPair::generate(&mut prng);
+        let bob = XfrKeyPair::generate(&mut prng);
+        let alice_input = untraced_asset_record(
+            &mut prng,
+            10,
+            asset_x,
+            AssetRecordType::ConfidentialAmount_ConfidentialAssetType,
+            &alice.pub_key,
+        )
+        .open_asset_record;
+        let offer = create_swap_offer(
+            &mut prng,
+            vec![alice_input],
+            6,
+            3,
+            asset_y,
+            &alice.pub_key,
+        )
+        .unwrap();
+
+        // bob adds another record of alice, whose opening he knows, and pays it to himself
+        let alice_record = untraced_asset_record(
+            &mut prng,
+            8,
+            asset_x,
+            AssetRecordType::ConfidentialAmount_ConfidentialAssetType,
+            &alice.pub_key,
+        );
+        let mut bad_offer = offer.clone();
+        bad_offer.proposal.add_input(alice_record);
+        bad_offer.proposal.add_output(untraced_asset_record(
+            &mut prng,
+            8,
+            asset_x,
+            AssetRecordType::ConfidentialAmount_ConfidentialAssetType,
+            &bob.pub_key,
+        ));
+        let bob_input = untraced_asset_record(
+            &mut prng,
+            5,
+            asset_y,
+            AssetRecordType::ConfidentialAmount_ConfidentialAssetType,
+            &bob.pub_key,
+        )
+        .open_asset_record;
+        let acceptance =
+            accept_swap_offer(&mut prng, &bad_offer, vec![bob_input.clone()], &bob)
+                .unwrap();
+        let err = complete_swap(&offer, acceptance, &alice).unwrap_err();
+        err_eq!(ZeiError::ParameterError, err);
+
+        // alice adds a record of bob, whose opening she knows, and pays it to herself
+        let bob_record = untraced_asset_record(
+            &mut prng,
+            8,
+            asset_y,
+            AssetRecordType::ConfidentialAmount_ConfidentialAssetType,
+            &bob.pub_key,
+        );
+        let mut bad_offer = offer.clone();
+        bad_offer.proposal.add_input(bob_record);
+        bad_offer.proposal.add_output(untraced_asset_record(
+            &mut prng,
+            8,
+            asset_y,
+            AssetRecordType::ConfidentialAmount_ConfidentialAssetType,
+            &alice.pub_key,
+        ));
+        let err =
+            accept_swap_offer(&mut prng, &bad_offer, vec![bob_input.clone()], &bob)
+                .unwrap_err();
+        err_eq!(ZeiError::ParameterError, err);
+
+        // the honest offer is settled
+        let acceptance =
+            accept_swap_offer(&mut prng, &offer, vec![bob_input], &bob).unwrap();
+        assert!(complete_swap(&offer, acceptance, &alice).is_ok());
+    }
+}
@@ -1,5 +1,5 @@
 use rand_chacha::ChaChaRng;
-use rand_core::SeedableRng;
+use rand_core::{CryptoRng, RngCore, SeedableRng};
 
 use super::asset_record::{
     build_blind_asset_record, open_blind_asset_record, AssetRecordType,
@@ -7,9 +7,9 @@ use super::asset_record::{
 use super::lib::XfrNotePolicies;
 use super::sig::{XfrKeyPair, XfrPublicKey};
 use super::structs::{
-    AssetRecord, AssetRecordTemplate, AssetTracerKeyPair, AssetType, BlindAssetRecord,
-    IdentityRevealPolicy, OwnerMemo, TracingPolicies, TracingPolicy, XfrAmount,
-    XfrAssetType, ASSET_TYPE_LENGTH,
+    AssetRecord, AssetRecordTemplate, AssetTracerEncKeys, AssetTracerKeyPair, AssetType,
+    BlindAssetRecord, IdentityRevealPolicy, OwnerMemo, TracingPolicies, TracingPolicy,
+    XfrAmount, XfrAssetType, ASSET_TYPE_LENGTH,
 };
 use crate::api::anon_creds;
 use crate::api::anon_creds::{
@@ -50,6 +50,7 @@ pub fn conf_blind_asset_record_from_ledger(
         public_key: key.clone(),
         asset_record_type: AssetRecordType::ConfidentialAmount_ConfidentialAssetType,
         asset_tracing_policies: Default::default(),
+        threshold_owner: None,
     };
     let (bar, _, owner) = build_blind_asset_record(
         &mut prng,
@@ -61,6 +62,59 @@ pub fn conf_blind_asset_record_from_ledger(
     (bar, owner.unwrap())
 }
 
+/// Untraced record of `amount` units of `asset_type` owned by `owner`
+pub fn untraced_asset_record<R: CryptoRng + RngCore>(
+    prng: &mut R,
+    amount: u64,
+    asset_type: AssetType,
+    asset_record_type: AssetRecordType,
+    owner: &XfrPublicKey,
+) -> AssetRecord {
+    let template = AssetRecordTemplate::with_no_asset_tracing(
+        amount,
+        asset_type,
+        asset_record_type,
+        *owner,
+    );
+    AssetRecord::from_template_no_identity_tracing(prng, &template).unwrap()
+}
+
+/// Credential of a user with attributes [1, 2, 3], committed for `owner`, and the tracing
+/// policies revealing its first and last attributes to `enc_keys`
+#[allow(clippy::type_complexity)]
+pub fn credential_with_tracing_policies<R: CryptoRng + RngCore>(
+    prng: &mut R,
+    enc_keys: AssetTracerEncKeys,
+    owner: &XfrPublicKey,
+) -> (
+    ACUserSecretKey,
+    Credential,
+    ACCommitmentKey,
+    ACCommitment,
+    TracingPolicies,
+) {
+    let attrs = vec![1u32, 2, 3];
+    let (issuer_pk, issuer_sk) = anon_creds::ac_keygen_issuer(prng, 3);
+    let (user_pk, user_sk) = anon_creds::ac_keygen_user(prng, &issuer_pk);
+    let signature = ac_sign(prng, &issuer_sk, &user_pk, &attrs).unwrap();
+    let credential = Credential {
+        signature,
+        attributes: attrs,
+        issuer_pub_key: issuer_pk.clone(),
+    };
+    let (commitment, _, key) =
+        ac_commit(prng, &user_sk, &credential, owner.as_bytes()).unwrap();
+    let policies = TracingPolicies::from_policy(TracingPolicy {
+        enc_keys,
+        asset_tracing: true,
+        identity_tracing: Some(IdentityRevealPolicy {
+            cred_issuer_pub_key: issuer_pk,
+            reveal_map: vec![true, false, true],
+        }),
+    });
+    (user_sk, credential, key.unwrap(), commitment, policies)
+}
+
 #[allow(clippy::type_complexity)]
 pub fn setup_with_policies(
     n: usize,
@@ -369,6 +369,38 @@ mod single_asset_no_tracing {
         do_transfer_tests_single_asset(&mut params, &inputs_template, &outputs_template);
     }
 
+    #[test]
+    fn test_transfer_confidential_amount64() {
+        /*! Test single commitment confidential amounts in all inputs and outputs*/
+        let mut params = PublicParams::from_file_if_exists(DEFAULT_BP_NUM_GENS, None);
+        let inputs_template =
+            [AssetRecordType::ConfidentialAmount64_ConfidentialAssetType; 4];
+        let outputs_template =
+            [AssetRecordType::ConfidentialAmount64_NonConfidentialAssetType; 6];
+        do_transfer_tests_single_asset(&mut params, &inputs_template, &outputs_template);
+    }
+
+    #[test]
+    fn test_transfer_confidential_amount_mixed_encodings() {
+        /*! Test inputs with split amounts and outputs with some single commitment amounts*/
+        let mut params = PublicParams::from_file_if_exists(DEFAULT_BP_NUM_GENS, None);
+        let inputs_template = [
+            AssetRecordType::ConfidentialAmount_ConfidentialAssetType,
+            AssetRecordType::ConfidentialAmount_ConfidentialAssetType,
+            AssetRecordType::NonConfidentialAmount_ConfidentialAssetType,
+            AssetRecordType::ConfidentialAmount_ConfidentialAssetType,
+        ];
+        let outputs_template = [
+            AssetRecordType::ConfidentialAmount64_ConfidentialAssetType,
+            AssetRecordType::ConfidentialAmount_ConfidentialAssetType,
+            AssetRecordType::NonConfidentialAmount_NonConfidentialAssetType,
+            AssetRecordType::ConfidentialAmount64_ConfidentialAssetType,
+            AssetRecordType::ConfidentialAmount_NonConfidentialAssetType,
+            AssetRecordType::ConfidentialAmount64_NonConfidentialAssetType,
+        ];
+        do_transfer_tests_single_asset(&mut params, &inputs_template, &outputs_template);
+    }
+
     #[test]
     fn test_transfer_input_some_amount_confidential_output_non_confidential() {
         /*! Test confidential amount in some inputs transfers*/
@@ -876,11 +908,14 @@ mod asset_tracing {
     //////////////////////////////////////////////////////////////////////////////////////////////////
 
     use super::*;
+    use crate::xfr::asset_record::open_blind_asset_record;
     use crate::xfr::lib::{
         trace_assets, trace_assets_brute_force, XfrNotePolicies, XfrNotePoliciesRef,
     };
     use crate::xfr::structs::XfrAmount::NonConfidential;
-    use crate::xfr::structs::{AssetTracerKeyPair, TracingPolicies};
+    use crate::xfr::structs::{
+        AssetTracerKeyPair, AssetTypeAndAmountProof, TracingPolicies,
+    };
     use algebra::bls12_381::BLSScalar;
     use algebra::groups::GroupArithmetic;
     use algebra::jubjub::JubjubScalar;
@@ -1275,6 +1310,130 @@ mod asset_tracing {
         do_test_asset_tracing(&mut params, &input_templates, &output_templates);
     }
 
+    #[test]
+    fn test_one_input_one_output_amount64_confidential() {
+        let mut params = PublicParams::default();
+        let mut prng = ChaChaRng::from_seed([0u8; 32]);
+        let asset_tracer_keypair = AssetTracerKeyPair::generate(&mut prng);
+        let tracing_policy = TracingPolicies::from_policy(TracingPolicy {
+            enc_keys: asset_tracer_keypair.enc_key.clone(),
+            asset_tracing: true,
+            identity_tracing: None,
+        });
+
+        let input_templates = [(
+            AssetRecordType::ConfidentialAmount64_ConfidentialAssetType,
+            &tracing_policy,
+            &asset_tracer_keypair,
+            BITCOIN_ASSET,
+        )];
+        let output_templates = [(
+            AssetRecordType::ConfidentialAmount64_NonConfidentialAssetType,
+            &tracing_policy,
+            &asset_tracer_keypair,
+            BITCOIN_ASSET,
+        )];
+
+        do_test_asset_tracing(&mut params, &input_templates, &output_templates);
+    }
+
+    #[test]
+    fn test_amount64_tracing_above_32_bits() {
+        let mut prng = ChaChaRng::from_seed([0u8; 32]);
+        let mut params = PublicParams::default();
+        let tracer_keypair = AssetTracerKeyPair::generate(&mut prng);
+        let tracing_policies = TracingPolicies::from_policy(TracingPolicy {
+            enc_keys: tracer_keypair.enc_key.clone(),
+            asset_tracing: true,
+            identity_tracing: None,
+        });
+        let no_policies = TracingPolicies::new();
+        let keys = gen_key_pair_vec(3, &mut prng);
+
+        let amount = (1u64 << 40) + 7;
+        let input = AssetRecordTemplate::with_asset_tracing(
+            amount,
+            BITCOIN_ASSET,
+            AssetRecordType::ConfidentialAmount64_ConfidentialAssetType,
+            keys[0].pub_key,
+            tracing_policies.clone(),
+        );
+        // outputs use both encodings of the amount
+        let outputs = [
+            AssetRecordTemplate::with_no_asset_tracing(
+                1u64 << 40,
+                BITCOIN_ASSET,
+                AssetRecordType::ConfidentialAmount64_ConfidentialAssetType,
+                keys[1].pub_key,
+            ),
+            AssetRecordTemplate::with_no_asset_tracing(
+                7,
+                BITCOIN_ASSET,
+                AssetRecordType::ConfidentialAmount_ConfidentialAssetType,
+                keys[2].pub_key,
+            ),
+        ];
+        let (xfr_note, _, _) = create_xfr(&mut prng, &[input], &outputs, &[&keys[0]]);
+        assert!(xfr_note.body.inputs[0].amount.is_single_commitment());
+        assert!(!xfr_note.body.outputs[1].amount.is_single_commitment());
+
+        let policies = XfrNotePoliciesRef::new(
+            vec![&tracing_policies],
+            vec![None],
+            vec![&no_policies, &no_policies],
+            vec![None, None],
+        );
+        pnk!(verify_xfr_note(
+            &mut prng,
+            &mut params,
+            &xfr_note,
+            &policies
+        ));
+
+        // the tracer recovers the amount from the memo hint
+        let records_data = trace_assets(&xfr_note.body, &tracer_keypair).unwrap();
+        assert_eq!(records_data.len(), 1);
+        assert_eq!(records_data[0].0, amount);
+        assert_eq!(records_data[0].1, BITCOIN_ASSET);
+
+        let oar = open_blind_asset_record(
+            &xfr_note.body.outputs[0],
+            &xfr_note.body.owners_memos[0],
+            &keys[1],
+        )
+        .unwrap();
+        assert_eq!(oar.amount, 1u64 << 40);
+
+        // an output committing to a greater amount is rejected
+        let mut body = xfr_note.body.clone();
+        let pc_gens = RistrettoPedersenGens::default();
+        body.outputs[0].amount = XfrAmount::from_single_blind(
+            &pc_gens,
+            (1u64 << 40) + 1,
+            &oar.amount_blinds.0,
+        );
+        assert!(
+            batch_verify_xfr_body_asset_records(&mut prng, &mut params, &[&body])
+                .is_err()
+        );
+
+        // the difference must be committed as a whole
+        let mut body = xfr_note.body;
+        if let AssetTypeAndAmountProof::ConfAll(proofs) =
+            &mut body.proofs.asset_type_and_amount_proof
+        {
+            proofs.0.xfr_diff_commitment_high = pc_gens
+                .commit(Scalar::from_u32(0), Scalar::from_u32(1))
+                .compress();
+        } else {
+            panic!("transfer should hide amounts and asset types");
+        }
+        assert!(
+            batch_verify_xfr_body_asset_records(&mut prng, &mut params, &[&body])
+                .is_err()
+        );
+    }
+
     #[test]
     fn test_one_input_one_output_asset_confidential() {
         let mut prng = ChaChaRng::from_seed([0u8; 32]);
@@ -1873,3 +2032,95 @@ mod asset_tracing {
         assert_eq!(v2, v3);
     }
 }
+
+mod encoding {
+
+    use super::*;
+    use crate::xfr::lib::xfr_body_signing_message;
+
+    fn rmp_bytes<T: Serialize>(value: &T) -> Vec<u8> {
+        let mut bytes = vec![];
+        value.serialize(&mut Serializer::new(&mut bytes)).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn test_single_owner_note_baseline_encoding() {
+        let mut prng = ChaChaRng::from_seed([0u8; 32]);
+        let mut params = PublicParams::default();
+        let asset_type = AssetType::from_identical_byte(0);
+        let record_type = AssetRecordType::ConfidentialAmount_NonConfidentialAssetType;
+        let sender = XfrKeyPair::generate(&mut prng);
+        let receiver = XfrKeyPair::generate(&mut prng);
+        let input = AssetRecordTemplate::with_no_asset_tracing(
+            10,
+            asset_type,
+            record_type,
+            sender.pub_key,
+        );
+        let output = AssetRecordTemplate::with_no_asset_tracing(
+            10,
+            asset_type,
+            record_type,
+            receiver.pub_key,
+        );
+        let (xfr_note, _, _) = create_xfr(&mut prng, &[input], &[output], &[&sender]);
+        let policies = XfrNotePolicies::empty_policies(1, 1);
+        assert!(xfr_note.body.owners_member_locks.is_empty());
+
+        // baseline layouts: owner memos were (blind_share, lock), multisigs (signatures),
+        // bodies had no member locks and notes were (body, multisig)
+        let body = &xfr_note.body;
+        let baseline_memos = body
+            .owners_memos
+            .iter()
+            .map(|memo| memo.as_ref().map(|memo| (&memo.blind_share, &memo.lock)))
+            .collect_vec();
+        let baseline_body = (
+            &body.inputs,
+            &body.outputs,
+            &body.proofs,
+            &body.asset_tracing_memos,
+            &baseline_memos,
+        );
+        let baseline_multisig = (&xfr_note.multisig.signatures,);
+
+        // owner memos and multisigs are encoded as before in every format
+        let memo = body.owners_memos[0].as_ref().unwrap();
+        let baseline_memo = (&memo.blind_share, &memo.lock);
+        assert_eq!(rmp_bytes(memo), rmp_bytes(&baseline_memo));
+        assert_eq!(
+            bincode::serialize(memo).unwrap(),
+            bincode::serialize(&baseline_memo).unwrap()
+        );
+        assert_eq!(rmp_bytes(&xfr_note.multisig), rmp_bytes(&baseline_multisig));
+        assert_eq!(
+            bincode::serialize(&xfr_note.multisig).unwrap(),
+            bincode::serialize(&baseline_multisig).unwrap()
+        );
+
+        // the signed message is the baseline encoding of the body, so that notes signed
+        // before bodies had member locks still verify
+        assert_eq!(
+            xfr_body_signing_message(body).unwrap(),
+            rmp_bytes(&baseline_body)
+        );
+
+        // a note encoded with the baseline layout is decoded and still verifies
+        let baseline_note = rmp_bytes(&(&baseline_body, &baseline_multisig));
+        let mut de = Deserializer::new(&baseline_note[..]);
+        let decoded = XfrNote::deserialize(&mut de).unwrap();
+        assert_eq!(decoded, xfr_note);
+        pnk!(verify_xfr_note(
+            &mut prng,
+            &mut params,
+            &decoded,
+            &policies.to_ref()
+        ));
+
+        // notes round-trip through bincode, which is not self-describing
+        let bytes = bincode::serialize(&xfr_note).unwrap();
+        let decoded: XfrNote = bincode::deserialize(&bytes).unwrap();
+        assert_eq!(decoded, xfr_note);
+    }
+}
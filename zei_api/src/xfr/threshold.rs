@@ -0,0 +1,515 @@
+use crate::xfr::asset_record::{single_commitment_amount_blinds, AssetRecordType};
+use crate::xfr::sig::{XfrKeyPair, XfrPublicKey, XfrSignature};
+use crate::xfr::structs::{
+    AssetType, BlindAssetRecord, OpenAssetRecord, OwnerMemo, ASSET_TYPE_LENGTH,
+};
+use algebra::groups::Zero;
+use algebra::ristretto::{CompressedEdwardsY, RistrettoScalar as Scalar};
+use crypto::basics::hybrid_encryption::{self, ZeiHybridCipher};
+use curve25519_dalek::edwards::CompressedEdwardsY as DalekCompressedEdwardsY;
+use digest::Digest;
+use rand_core::{CryptoRng, RngCore};
+use ruc::*;
+use sha2::Sha512;
+use utils::errors::ZeiError;
+use utils::serialization::ZeiFromToBytes;
+
+const THRESHOLD_ADDRESS_DOMAIN: &[u8] = b"Zei threshold owner address";
+
+/// An m-of-n ownership policy: a record owned by the policy can be spent with the signatures of
+/// `threshold` distinct members. Keys are kept sorted so that a policy has a unique address.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct XfrThresholdPolicy {
+    threshold: u32,
+    keys: Vec<XfrPublicKey>,
+}
+
+impl XfrThresholdPolicy {
+    /// Creates a policy requiring `threshold` signatures out of `keys`.
+    /// Returns ParameterError if the threshold is not in 1..=keys.len() or if a key is repeated
+    pub fn new(threshold: u32, mut keys: Vec<XfrPublicKey>) -> Result<Self> {
+        keys.sort();
+        keys.dedup();
+        if threshold == 0 || threshold as usize > keys.len() {
+            return Err(eg!(ZeiError::ParameterError));
+        }
+        Ok(XfrThresholdPolicy { threshold, keys })
+    }
+
+    pub fn get_threshold(&self) -> u32 {
+        self.threshold
+    }
+
+    pub fn get_keys(&self) -> &[XfrPublicKey] {
+        &self.keys
+    }
+
+    /// Returns the address of the policy, to be used as `BlindAssetRecord.public_key`.
+    /// The address is a curve point hashed from the policy, hence nobody knows its secret key
+    /// and records sent to it can only be spent with a threshold signature.
+    pub fn address(&self) -> XfrPublicKey {
+        let mut counter = 0u32;
+        loop {
+            let mut hasher = Sha512::new();
+            hasher.update(THRESHOLD_ADDRESS_DOMAIN);
+            hasher.update(&self.threshold.to_le_bytes());
+            for key in self.keys.iter() {
+                hasher.update(key.as_bytes());
+            }
+            hasher.update(&counter.to_le_bytes());
+            let hash = hasher.finalize();
+            if let Some(point) =
+                DalekCompressedEdwardsY::from_slice(&hash[..32]).decompress()
+            {
+                if !point.is_small_order() {
+                    let address = point.mul_by_cofactor().compress();
+                    // safe unwrap: the bytes are a valid point encoding
+                    return XfrPublicKey::zei_from_bytes(address.as_bytes()).unwrap();
+                }
+            }
+            counter += 1;
+        }
+    }
+
+    fn member_index(&self, pub_key: &XfrPublicKey) -> Result<usize> {
+        self.keys
+            .binary_search(pub_key)
+            .ok()
+            .c(d!(ZeiError::ParameterError))
+    }
+}
+
+/// Signature of a message by at least `threshold` members of a policy
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct XfrThresholdSig {
+    pub policy: XfrThresholdPolicy,
+    /// (index of the signer in the policy keys, signature), sorted by index
+    pub signatures: Vec<(u32, XfrSignature)>,
+}
+
+impl XfrThresholdSig {
+    /// Signs `message` with the key pairs of the members of `policy`.
+    /// Returns ParameterError if a key pair is not a member, is repeated, or if there are
+    /// less key pairs than the policy threshold
+    pub fn sign(
+        policy: &XfrThresholdPolicy,
+        key_pairs: &[&XfrKeyPair],
+        message: &[u8],
+    ) -> Result<Self> {
+        let mut signatures = vec![];
+        for key_pair in key_pairs.iter() {
+            let index = policy.member_index(&key_pair.pub_key).c(d!())?;
+            signatures.push((index as u32, key_pair.sign(message)));
+        }
+        signatures.sort_by_key(|(index, _)| *index);
+        signatures.dedup_by_key(|(index, _)| *index);
+        if signatures.len() != key_pairs.len()
+            || signatures.len() < policy.threshold as usize
+        {
+            return Err(eg!(ZeiError::ParameterError));
+        }
+        Ok(XfrThresholdSig {
+            policy: policy.clone(),
+            signatures,
+        })
+    }
+
+    /// Verifies that at least `threshold` distinct members of the policy signed `message`
+    pub fn verify(&self, message: &[u8]) -> Result<()> {
+        if self.signatures.len() < self.policy.threshold as usize {
+            return Err(eg!(ZeiError::SignatureError));
+        }
+        let mut next_index = 0usize;
+        for (index, signature) in self.signatures.iter() {
+            let index = *index as usize;
+            // indices are strictly increasing, thus every signer is counted once
+            if index < next_index || index >= self.policy.keys.len() {
+                return Err(eg!(ZeiError::SignatureError));
+            }
+            self.policy.keys[index].verify(message, signature).c(d!())?;
+            next_index = index + 1;
+        }
+        Ok(())
+    }
+}
+
+/// Key material authorizing the spending of a transfer input
+pub enum XfrInputSigner<'a> {
+    /// Input owned by the public key of the key pair
+    Key(&'a XfrKeyPair),
+    /// Input owned by the address of the policy, signed by the given members
+    Threshold(&'a XfrThresholdPolicy, Vec<&'a XfrKeyPair>),
+}
+
+impl<'a> XfrInputSigner<'a> {
+    /// Returns the public key of the inputs the signer can spend
+    pub fn owner(&self) -> XfrPublicKey {
+        match self {
+            XfrInputSigner::Key(key_pair) => key_pair.pub_key,
+            XfrInputSigner::Threshold(policy, _) => policy.address(),
+        }
+    }
+}
+
+impl OwnerMemo {
+    /// constructs an `OwnerMemo` for an asset record owned by `policy`: the shared point is derived
+    /// from the policy address, and each member gets a lock with the shared point and the
+    /// confidential fields. `lock` is addressed to the first key of the policy and the returned
+    /// member locks to the others, in the order of the keys.
+    /// returns (OwnerMemo, member locks, (amount_blind_low, amount_blind_high), asset_type_blind),
+    /// blinds of non-confidential fields being zero
+    /// PRNG should be seeded with good entropy instead of being deterministically seeded
+    pub fn for_threshold_owner<R: CryptoRng + RngCore>(
+        prng: &mut R,
+        amount: Option<u64>,
+        asset_type: Option<&AssetType>,
+        policy: &XfrThresholdPolicy,
+    ) -> Result<(Self, Vec<ZeiHybridCipher>, (Scalar, Scalar), Scalar)> {
+        let (r, blind_share) = Scalar::random_scalar_with_compressed_edwards(prng);
+        let shared_point = OwnerMemo::derive_shared_edwards_point(
+            &r,
+            &policy.address().as_compressed_edwards_point(),
+        )
+        .c(d!())?;
+
+        let mut plaintext = shared_point.0.as_bytes().to_vec();
+        let mut amount_blinds = (Scalar::zero(), Scalar::zero());
+        let mut asset_type_blind = Scalar::zero();
+        if let Some(amount) = amount {
+            plaintext.extend_from_slice(&amount.to_be_bytes());
+            amount_blinds = OwnerMemo::calc_amount_blinds(&shared_point);
+        }
+        if let Some(asset_type) = asset_type {
+            plaintext.extend_from_slice(&asset_type.0);
+            asset_type_blind = OwnerMemo::calc_asset_type_blind(&shared_point);
+        }
+
+        let mut locks = policy
+            .keys
+            .iter()
+            .map(|key| {
+                hybrid_encryption::hybrid_encrypt_with_sign_key(prng, &key.0, &plaintext)
+            })
+            .collect::<Vec<ZeiHybridCipher>>();
+        let lock = locks.remove(0);
+        Ok((
+            OwnerMemo { blind_share, lock },
+            locks,
+            amount_blinds,
+            asset_type_blind,
+        ))
+    }
+}
+
+/// Open a blind asset record owned by `policy` using the key pair of one of its members, and
+/// the owner memo and member locks created with `OwnerMemo::for_threshold_owner`.
+/// Return Ok(OpenAssetRecord) or
+/// ZeiError if the record is not owned by the policy, the key pair is not a member, or
+/// in case of decryption error or inconsistent plaintext error.
+pub fn open_threshold_blind_asset_record(
+    input: &BlindAssetRecord,
+    owner_memo: &Option<OwnerMemo>,
+    member_locks: &[ZeiHybridCipher],
+    policy: &XfrThresholdPolicy,
+    member: &XfrKeyPair,
+) -> Result<OpenAssetRecord> {
+    if input.public_key != policy.address() {
+        return Err(eg!(ZeiError::ParameterError));
+    }
+    let record_type = input.get_record_type();
+    let mut amount = input.amount.get_amount();
+    let mut asset_type = input.asset_type.get_asset_type();
+    let mut amount_blinds = (Scalar::zero(), Scalar::zero());
+    let mut type_blind = Scalar::zero();
+
+    if record_type != AssetRecordType::NonConfidentialAmount_NonConfidentialAssetType {
+        let owner_memo = owner_memo.as_ref().c(d!(ZeiError::ParameterError))?;
+        let index = policy.member_index(&member.pub_key).c(d!())?;
+        let lock = if index == 0 {
+            &owner_memo.lock
+        } else {
+            member_locks
+                .get(index - 1)
+                .c(d!(ZeiError::InconsistentStructureError))?
+        };
+        let plaintext = hybrid_encryption::hybrid_decrypt_with_ed25519_secret_key(
+            lock,
+            &member.sec_key.0,
+        );
+        let mut expected_len = 32;
+        if record_type.is_confidential_amount() {
+            expected_len += 8;
+        }
+        if record_type.is_confidential_asset_type() {
+            expected_len += ASSET_TYPE_LENGTH;
+        }
+        if plaintext.len() != expected_len {
+            return Err(eg!(ZeiError::InconsistentStructureError));
+        }
+
+        let shared_point = CompressedEdwardsY::from_slice(&plaintext[..32]);
+        let mut pos = 32;
+        if record_type.is_confidential_amount() {
+            let mut amount_bytes = [0u8; 8];
+            amount_bytes.copy_from_slice(&plaintext[pos..pos + 8]);
+            amount = Some(u64::from_be_bytes(amount_bytes));
+            amount_blinds = OwnerMemo::calc_amount_blinds(&shared_point);
+            if record_type.is_single_commitment_amount() {
+                amount_blinds = single_commitment_amount_blinds(amount_blinds);
+            }
+            pos += 8;
+        }
+        if record_type.is_confidential_asset_type() {
+            let mut asset_type_bytes = [0u8; ASSET_TYPE_LENGTH];
+            asset_type_bytes.copy_from_slice(&plaintext[pos..]);
+            asset_type = Some(AssetType(asset_type_bytes));
+            type_blind = OwnerMemo::calc_asset_type_blind(&shared_point);
+        }
+    }
+
+    Ok(OpenAssetRecord {
+        blind_asset_record: input.clone(),
+        amount: amount.c(d!(ZeiError::ParameterError))?,
+        amount_blinds,
+        asset_type: asset_type.c(d!(ZeiError::ParameterError))?,
+        type_blind,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::setup::PublicParams;
+    use crate::xfr::asset_record::{open_blind_asset_record, AssetRecordType};
+    use crate::xfr::lib::{
+        gen_xfr_note, gen_xfr_note_with_input_signers, verify_xfr_note, XfrNotePolicies,
+    };
+    use crate::xfr::sig::XfrKeyPair;
+    use crate::xfr::structs::{AssetRecord, AssetRecordTemplate, AssetType};
+    use crate::xfr::threshold::{
+        open_threshold_blind_asset_record, XfrInputSigner, XfrThresholdPolicy,
+        XfrThresholdSig,
+    };
+    use rand_chacha::ChaChaRng;
+    use rand_core::SeedableRng;
+    use utils::errors::ZeiError;
+
+    #[test]
+    fn test_threshold_policy_and_sig() {
+        let mut prng = ChaChaRng::from_seed([0u8; 32]);
+        let members = (0..3)
+            .map(|_| XfrKeyPair::generate(&mut prng))
+            .collect::<Vec<_>>();
+        let keys = members.iter().map(|kp| kp.pub_key).collect::<Vec<_>>();
+        let other = XfrKeyPair::generate(&mut prng);
+
+        assert!(XfrThresholdPolicy::new(0, keys.clone()).is_err());
+        assert!(XfrThresholdPolicy::new(4, keys.clone()).is_err());
+        assert!(XfrThresholdPolicy::new(3, vec![keys[0], keys[0], keys[1]]).is_err());
+
+        // the address does not depend on the order of the keys
+        let policy = XfrThresholdPolicy::new(2, keys.clone()).unwrap();
+        let reversed =
+            XfrThresholdPolicy::new(2, keys.iter().rev().cloned().collect()).unwrap();
+        assert_eq!(policy.address(), reversed.address());
+        let policy3 = XfrThresholdPolicy::new(3, keys.clone()).unwrap();
+        assert_ne!(policy.address(), policy3.address());
+
+        let msg = b"message";
+        let sig =
+            XfrThresholdSig::sign(&policy, &[&members[2], &members[0]], msg).unwrap();
+        assert!(sig.verify(msg).is_ok());
+        let err = sig.verify(b"other message").unwrap_err();
+        err_eq!(ZeiError::SignatureError, err);
+
+        // not enough, repeated or foreign signers
+        assert!(XfrThresholdSig::sign(&policy, &[&members[0]], msg).is_err());
+        assert!(
+            XfrThresholdSig::sign(&policy, &[&members[0], &members[0]], msg).is_err()
+        );
+        assert!(XfrThresholdSig::sign(&policy, &[&members[0], &other], msg).is_err());
+
+        // a signature counted twice does not reach the threshold
+        let mut bad = sig.clone();
+        bad.signatures[1] = bad.signatures[0].clone();
+        let err = bad.verify(msg).unwrap_err();
+        err_eq!(ZeiError::SignatureError, err);
+        let mut bad = sig;
+        bad.signatures.pop();
+        let err = bad.verify(msg).unwrap_err();
+        err_eq!(ZeiError::SignatureError, err);
+    }
+
+    #[test]
+    fn test_threshold_owned_records() {
+        let mut prng = ChaChaRng::from_seed([1u8; 32]);
+        let mut params = PublicParams::default();
+        let asset_type = AssetType::from_identical_byte(0);
+        let members = (0..3)
+            .map(|_| XfrKeyPair::generate(&mut prng))
+            .collect::<Vec<_>>();
+        let policy =
+            XfrThresholdPolicy::new(2, members.iter().map(|kp| kp.pub_key).collect())
+                .unwrap();
+        let single_owner = XfrKeyPair::generate(&mut prng);
+        let recipient = XfrKeyPair::generate(&mut prng);
+
+        for record_type in [
+            AssetRecordType::NonConfidentialAmount_NonConfidentialAssetType,
+            AssetRecordType::ConfidentialAmount_NonConfidentialAssetType,
+            AssetRecordType::NonConfidentialAmount_ConfidentialAssetType,
+            AssetRecordType::ConfidentialAmount_ConfidentialAssetType,
+        ]
+        .iter()
+        {
+            let template = AssetRecordTemplate::with_threshold_owner(
+                10,
+                asset_type,
+                *record_type,
+                policy.clone(),
+            );
+            assert_eq!(template.public_key, policy.address());
+            let record =
+                AssetRecord::from_template_no_identity_tracing(&mut prng, &template)
+                    .unwrap();
+            let bar = &record.open_asset_record.blind_asset_record;
+
+            // every member opens the record
+            for member in members.iter() {
+                let oar = open_threshold_blind_asset_record(
+                    bar,
+                    &record.owner_memo,
+                    &record.owner_member_locks,
+                    &policy,
+                    member,
+                )
+                .unwrap();
+                assert_eq!(oar, record.open_asset_record);
+            }
+            assert!(open_threshold_blind_asset_record(
+                bar,
+                &record.owner_memo,
+                &record.owner_member_locks,
+                &policy,
+                &recipient
+            )
+            .is_err());
+
+            // spend the record together with a single owned record
+            let single_template = AssetRecordTemplate::with_no_asset_tracing(
+                5,
+                asset_type,
+                *record_type,
+                single_owner.pub_key,
+            );
+            let single_input = AssetRecord::from_template_no_identity_tracing(
+                &mut prng,
+                &single_template,
+            )
+            .unwrap();
+            let input = AssetRecord::from_open_asset_record_no_asset_tracing(
+                record.open_asset_record.clone(),
+            );
+            let out_template = AssetRecordTemplate::with_no_asset_tracing(
+                15,
+                asset_type,
+                *record_type,
+                recipient.pub_key,
+            );
+            let output =
+                AssetRecord::from_template_no_identity_tracing(&mut prng, &out_template)
+                    .unwrap();
+            let inputs = [input, single_input];
+            let policies = XfrNotePolicies::empty_policies(2, 1);
+
+            let note = gen_xfr_note_with_input_signers(
+                &mut prng,
+                &inputs,
+                &[output.clone()],
+                &[
+                    XfrInputSigner::Threshold(&policy, vec![&members[0], &members[2]]),
+                    XfrInputSigner::Key(&single_owner),
+                ],
+            )
+            .unwrap();
+            assert!(
+                verify_xfr_note(&mut prng, &mut params, &note, &policies.to_ref())
+                    .is_ok()
+            );
+            let oar = open_blind_asset_record(
+                &note.body.outputs[0],
+                &note.body.owners_memos[0],
+                &recipient,
+            )
+            .unwrap();
+            assert_eq!(oar.amount, 15);
+
+            // the members open a record sent to the policy with the locks carried by the note
+            let policy_template = AssetRecordTemplate::with_threshold_owner(
+                5,
+                asset_type,
+                *record_type,
+                policy.clone(),
+            );
+            let policy_output = AssetRecord::from_template_no_identity_tracing(
+                &mut prng,
+                &policy_template,
+            )
+            .unwrap();
+            let policy_note = gen_xfr_note(
+                &mut prng,
+                &inputs[1..],
+                &[policy_output],
+                &[&single_owner],
+            )
+            .unwrap();
+            let policy_note_policies = XfrNotePolicies::empty_policies(1, 1);
+            assert!(verify_xfr_note(
+                &mut prng,
+                &mut params,
+                &policy_note,
+                &policy_note_policies.to_ref()
+            )
+            .is_ok());
+            for member in members.iter() {
+                let oar = open_threshold_blind_asset_record(
+                    &policy_note.body.outputs[0],
+                    &policy_note.body.owners_memos[0],
+                    policy_note.body.output_member_locks(0),
+                    &policy,
+                    member,
+                )
+                .unwrap();
+                assert_eq!(oar.amount, 5);
+            }
+
+            // a single member cannot spend the record
+            assert!(gen_xfr_note_with_input_signers(
+                &mut prng,
+                &inputs,
+                &[output],
+                &[
+                    XfrInputSigner::Threshold(&policy, vec![&members[1]]),
+                    XfrInputSigner::Key(&single_owner),
+                ],
+            )
+            .is_err());
+
+            // the threshold signature is required
+            let mut bad_note = note.clone();
+            bad_note.threshold_signatures[0].signatures.pop();
+            let err =
+                verify_xfr_note(&mut prng, &mut params, &bad_note, &policies.to_ref())
+                    .unwrap_err();
+            err_eq!(ZeiError::SignatureError, err);
+            let mut bad_note = note;
+            bad_note.threshold_signatures.clear();
+            assert!(verify_xfr_note(
+                &mut prng,
+                &mut params,
+                &bad_note,
+                &policies.to_ref()
+            )
+            .is_err());
+        }
+    }
+}